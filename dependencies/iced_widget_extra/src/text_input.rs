@@ -1,6 +1,6 @@
 //! Text inputs display fields that can be filled with text.
 //!
-//! Custom: on_focus and on_blur messages.
+//! Custom: on_focus, on_blur, on_arrow_up and on_arrow_down messages.
 //!
 //! # Example
 //! ```no_run
@@ -113,6 +113,8 @@ where
     on_focus: Option<Message>,
     on_blur: Option<Message>,
     on_escape: Option<Message>,
+    on_arrow_up: Option<Message>,
+    on_arrow_down: Option<Message>,
     on_input: Option<Box<dyn Fn(String) -> Message + 'a>>,
     on_paste: Option<Box<dyn Fn(String) -> Message + 'a>>,
     on_submit: Option<Message>,
@@ -147,6 +149,8 @@ where
             on_focus: None,
             on_blur: None,
             on_escape: None,
+            on_arrow_up: None,
+            on_arrow_down: None,
             on_input: None,
             on_paste: None,
             on_submit: None,
@@ -210,6 +214,34 @@ where
         self
     }
 
+    /// Sets the message that should be produced when the key [`key::Named::ArrowUp`] is
+    /// pressed.
+    pub fn on_arrow_up(mut self, on_arrow_up: Message) -> Self {
+        self.on_arrow_up = Some(on_arrow_up);
+        self
+    }
+
+    /// Sets the message that should be produced when the key [`key::Named::ArrowUp`] is
+    /// pressed, if `Some`.
+    pub fn on_arrow_up_maybe(mut self, on_arrow_up: Option<Message>) -> Self {
+        self.on_arrow_up = on_arrow_up;
+        self
+    }
+
+    /// Sets the message that should be produced when the key [`key::Named::ArrowDown`] is
+    /// pressed.
+    pub fn on_arrow_down(mut self, on_arrow_down: Message) -> Self {
+        self.on_arrow_down = Some(on_arrow_down);
+        self
+    }
+
+    /// Sets the message that should be produced when the key [`key::Named::ArrowDown`] is
+    /// pressed, if `Some`.
+    pub fn on_arrow_down_maybe(mut self, on_arrow_down: Option<Message>) -> Self {
+        self.on_arrow_down = on_arrow_down;
+        self
+    }
+
     /// Sets the message that should be produced when some text is typed into
     /// the [`TextInput`].
     ///
@@ -1177,6 +1209,18 @@ where
 
                             shell.capture_event();
                         }
+                        keyboard::Key::Named(key::Named::ArrowUp) => {
+                            if let Some(on_arrow_up) = self.on_arrow_up.clone() {
+                                shell.publish(on_arrow_up);
+                                shell.capture_event();
+                            }
+                        }
+                        keyboard::Key::Named(key::Named::ArrowDown) => {
+                            if let Some(on_arrow_down) = self.on_arrow_down.clone() {
+                                shell.publish(on_arrow_down);
+                                shell.capture_event();
+                            }
+                        }
                         keyboard::Key::Named(key::Named::Escape) => {
                             state.is_focused = None;
                             state.is_dragging = None;
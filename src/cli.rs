@@ -0,0 +1,288 @@
+use std::{collections::HashSet, env, path::{Path, PathBuf}};
+
+use iced::futures::StreamExt;
+use itertools::Itertools;
+
+use crate::{ModProvider, ProgramData, reqwests};
+use crate::states::mod_downloader::{modrinth::{self, ModrinthVersionDownload}, provider::{Provider, SearchQuery, Version}};
+use crate::util::installed_mods::{self, InstalledMod};
+use crate::util::{mod_backups, modpack, updater::{self, UpdateEvent}};
+
+// subcommands for scripts and CI. they work on the server in the current folder, the same way the main window would
+
+const USAGE: &str = "\
+usage: mcservermodgui [command]
+
+with no command the app opens as usual. commands work on the server in the current folder:
+    search <query> [--provider modrinth|curseforge|hangar]
+    install <slug|id> [--version <id|name>] [--provider ...]   also installs required dependencies (modrinth)
+    list
+    update [--check]                                           --check only prints what would be updated
+    remove <file|id|name>                                      the jar is moved to .mcservermodgui/backups
+    info <slug|id> [--provider ...]";
+
+/// runs the command in `args` (without the binary name). None if there isn't one and the gui should start instead
+pub fn run(args: &[String]) -> Option<i32> {
+    let command = args.first()?;
+    if matches!(command.as_str(), "-h" | "--help" | "help") {
+        println!("{USAGE}");
+        return Some(0);
+    }
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("Couldn't start the async runtime: {e}");
+            return Some(1);
+        }
+    };
+    let result = runtime.block_on(async {
+        let root = env::current_dir().map_err(|e| format!("Couldn't get the current folder: {e}"))?;
+        let program_data = ProgramData::load(&root)?
+            .ok_or("There's no server set up in this folder. Open it in the app once to set it up")?;
+        let args = Args::parse(&args[1..])?;
+        match command.as_str() {
+            "search" => search(&program_data, &args).await,
+            "install" => install(&program_data, &root, &args).await,
+            "list" => list(&program_data, &root),
+            "update" => update(&program_data, &root, &args).await,
+            "remove" => remove(&program_data, &root, &args),
+            "info" => info(&program_data, &args).await,
+            other => Err(format!("Unknown command {other}\n\n{USAGE}")),
+        }
+    });
+    match result {
+        Ok(()) => Some(0),
+        Err(err) => {
+            eprintln!("{err}");
+            Some(1)
+        }
+    }
+}
+
+#[derive(Default)]
+struct Args {
+    positional: Vec<String>,
+    version: Option<String>,
+    provider: ModProvider,
+    check: bool,
+}
+impl Args {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut parsed = Args::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--version" => parsed.version = Some(args.next().ok_or("--version needs a value")?.clone()),
+                "--provider" => parsed.provider = match args.next().map(String::as_str) {
+                    Some("modrinth") => ModProvider::Modrinth,
+                    Some("curseforge") => ModProvider::Curseforge,
+                    Some("hangar") => ModProvider::Hangar,
+                    Some(other) => return Err(format!("Unknown provider {other}, use modrinth, curseforge or hangar")),
+                    None => return Err("--provider needs a value".to_string()),
+                },
+                "--check" => parsed.check = true,
+                s if s.starts_with("--") => return Err(format!("Unknown option {s}\n\n{USAGE}")),
+                s => parsed.positional.push(s.to_string()),
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn target(&self, what: &str) -> Result<String, String> {
+        match self.positional.as_slice() {
+            [] => Err(format!("Which {what}?\n\n{USAGE}")),
+            p => Ok(p.join(" ")),
+        }
+    }
+}
+
+fn _backend(program_data: &ProgramData, args: &Args) -> Box<dyn Provider> {
+    args.provider.backend(&program_data.curseforge)
+}
+
+/// the same loader and version filters the mod downloader starts out with for this server
+fn _filters(program_data: &ProgramData) -> (Vec<crate::ModLoader>, Vec<String>) {
+    (vec![program_data.loader], vec![program_data.version.id.clone()])
+}
+
+async fn search(program_data: &ProgramData, args: &Args) -> Result<(), String> {
+    let (loaders, game_versions) = _filters(program_data);
+    let query = SearchQuery {
+        query: args.target("search")?,
+        offset: 0,
+        loaders,
+        game_versions,
+        categories: vec![],
+        server_side_only: true,
+    };
+    let page = _backend(program_data, args).search(query).await?;
+    if page.projects.is_empty() {
+        println!("nothing found for {} {}", program_data.loader, program_data.version);
+    }
+    for p in page.projects {
+        println!("{}  {}\n    {}", p.id, p.title, p.description);
+    }
+    Ok(())
+}
+
+async fn info(program_data: &ProgramData, args: &Args) -> Result<(), String> {
+    let id = args.target("project")?;
+    let backend = _backend(program_data, args);
+    let project = backend.project(id.clone()).await?;
+    println!("{} ({})\n{}\n{}", project.title, project.id, project.description, project.page_url);
+
+    let (loaders, game_versions) = _filters(program_data);
+    let versions = backend.versions(project.id, loaders, game_versions).await?;
+    match versions.first() {
+        None => println!("\nno versions for {} {}", program_data.loader, program_data.version),
+        Some(_) => {
+            println!("\nversions for {} {}:", program_data.loader, program_data.version);
+            for v in versions.iter().take(10) {
+                println!("    {}  {}", v.id, v.name);
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn install(program_data: &ProgramData, root: &Path, args: &Args) -> Result<(), String> {
+    let id = args.target("project")?;
+    let backend = _backend(program_data, args);
+    let (loaders, game_versions) = _filters(program_data);
+    let versions = backend.versions(id.clone(), loaders, game_versions).await?;
+    // newest first, so without --version that's the latest one
+    let version = match &args.version {
+        None => versions.first(),
+        Some(want) => versions.iter().find(|v| &v.id == want || &v.name == want),
+    }.ok_or_else(|| format!("{id} has no {}version for {} {}", args.version.as_ref().map_or(String::new(), |v| format!("{v} ")), program_data.loader, program_data.version))?;
+
+    let mut to_install = backend.download_versions(vec![(version.project_id.clone(), version.id.clone())], Some(program_data.loader)).await?;
+    if args.provider == ModProvider::Modrinth {
+        let deps = modrinth::resolve_required_dependencies(version.id.clone(), id.clone(), program_data.loader, program_data.version.clone(), HashSet::from([version.project_id.clone()])).await?;
+        for d in deps {
+            println!("{} is required by {}", d.project.title, d.pulled_in_by);
+            to_install.push(d.version);
+        }
+    }
+
+    let mods_dir = root.join(program_data.loader.mods_folder());
+    for v in to_install {
+        let Some(file) = v.files.iter().find(|f| f.primary).or(v.files.first()) else {
+            return Err(format!("{} has no files", v.name));
+        };
+        if modpack::join_file_name(&mods_dir, &file.filename)?.exists() {
+            println!("{} is already installed", file.filename);
+            continue;
+        }
+        _download(root, &mods_dir, &v).await?;
+    }
+    Ok(())
+}
+
+async fn _download(root: &Path, mods_dir: &Path, version: &Version) -> Result<PathBuf, String> {
+    let file = version.files.iter().find(|f| f.primary).or(version.files.first()).ok_or_else(|| format!("{} has no files", version.name))?;
+    if let Some(page) = &file.manual_url {
+        return Err(format!("{} can't be downloaded here, its author only allows it from {page}", file.filename));
+    }
+    println!("downloading {}", file.filename);
+    let mut events = Box::pin(reqwests::download_file(
+        file.url.clone(),
+        modpack::join_file_name(mods_dir, &file.filename)?,
+        file.hashes.clone(),
+        root.join(".mcservermodgui").join("quarantine"),
+    ));
+    while let Some(event) = events.next().await {
+        if let reqwests::DownloadEvent::Finished(result) = event {
+            return result;
+        }
+    }
+    Err(format!("The download of {} stopped without finishing", file.filename))
+}
+
+fn list(program_data: &ProgramData, root: &Path) -> Result<(), String> {
+    let mods = installed_mods::scan_mods_folder(root, program_data.loader);
+    if mods.is_empty() {
+        println!("no mods installed in {}/", program_data.loader.mods_folder());
+    }
+    for m in mods {
+        println!("{}{}  {}  {}", if m.enabled {""} else {"(disabled) "}, m.name, m.version, m.file_name);
+    }
+    Ok(())
+}
+
+async fn update(program_data: &ProgramData, root: &Path, args: &Args) -> Result<(), String> {
+    let installed: Vec<InstalledMod> = installed_mods::scan_mods_folder(root, program_data.loader).into_iter()
+        .filter(|m| m.enabled && !m.sha1.is_empty())
+        .collect();
+    let hashes = installed.iter().map(|m| m.sha1.clone()).collect_vec();
+    if hashes.is_empty() {
+        println!("nothing to update");
+        return Ok(());
+    }
+
+    let current: std::collections::HashMap<String, ModrinthVersionDownload> = _parse(&reqwests::get_versions_from_hashes(hashes.clone(), "sha1").await?, "version_files")?;
+    let latest: std::collections::HashMap<String, ModrinthVersionDownload> = _parse(&reqwests::get_latest_versions_from_hashes(
+        hashes,
+        "sha1",
+        modrinth::compatible_loader_tags(program_data.loader),
+        vec![program_data.version.id.clone()],
+    ).await?, "update check")?;
+
+    let updates = installed.iter().filter_map(|m| {
+        let new = latest.get(&m.sha1)?;
+        (current.get(&m.sha1)?.id != new.id).then_some((m, new))
+    }).collect_vec();
+    if updates.is_empty() {
+        println!("everything is up to date");
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    for (old, new) in updates {
+        println!("{}: {} -> {}", old.name, old.version, new.version_number);
+        if args.check {continue}
+        let mut events = Box::pin(updater::update_jar(root.to_path_buf(), program_data.loader, old.file_name.clone(), Version::from(new.clone())));
+        while let Some(event) = events.next().await {
+            match event {
+                UpdateEvent::Progressed { .. } => (),
+                UpdateEvent::Done(left_over) => if let Some(err) = left_over {eprintln!("{err}")},
+                UpdateEvent::Failed(err) => {
+                    eprintln!("Couldn't update {}: {err}", old.name);
+                    failed += 1;
+                }
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(format!("{failed} updates failed"));
+    }
+    Ok(())
+}
+
+fn remove(program_data: &ProgramData, root: &Path, args: &Args) -> Result<(), String> {
+    let target = args.target("mod")?;
+    let mods = installed_mods::scan_mods_folder(root, program_data.loader);
+    let m = mods.iter().find(|m| m.file_name == target)
+        .or_else(|| mods.iter().find(|m| m.id.as_deref() == Some(target.as_str())))
+        .or_else(|| mods.iter().find(|m| m.name.eq_ignore_ascii_case(&target)))
+        .ok_or_else(|| format!("No installed mod called {target}"))?;
+
+    let path = if m.enabled {
+        root.join(program_data.loader.mods_folder()).join(&m.file_name)
+    } else {
+        installed_mods::disabled_path(root, program_data.loader, &m.file_name)
+    };
+    let backup = mod_backups::move_to_backups(root, &path)?;
+    if !m.enabled {
+        let mut disabled = installed_mods::DisabledMods::load(root);
+        disabled.files.retain(|f| f != &m.file_name);
+        disabled.save(root)?;
+    }
+    println!("removed {} ({}), it's in .mcservermodgui/backups/{backup} if you want it back", m.name, m.file_name);
+    Ok(())
+}
+
+fn _parse<T: serde::de::DeserializeOwned>(b: &[u8], what: &str) -> Result<T, String> {
+    let des = &mut serde_json::Deserializer::from_slice(b);
+    serde_path_to_error::deserialize(des).map_err(|e| format!("error parsing JSON from {what} at {}: {e}", e.path()))
+}
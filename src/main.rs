@@ -7,6 +7,7 @@ use iced::{Color, Element, Task, advanced::{image::Handle as RasterHandle, svg::
 use iced::advanced::svg::Svg;
 use reqwest::Client;
 
+mod cli;
 mod states;
 mod util;
 use states::*;
@@ -14,6 +15,7 @@ use util::*;
 
 use mod_downloader::{ModDownloaderState,ModDownMsg};
 use setup::{SetupState,SetupMessage};
+use server_picker::{PickerState,PickerMessage};
 use states::{init::InitState,main_window::MainState};
 
 use crate::states::{init::InitMessage, main_window::MainMessage};
@@ -44,7 +46,8 @@ struct _StaticImages {
 
 static MC_VERSIONS: OnceLock<Vec<MinecraftVersion>> = OnceLock::new();
 static PROGRAM_DATA: RwLock<Option<ProgramData>> = RwLock::new(None);
-static REQ_CLIENT: LazyLock<Client> = LazyLock::new(|| Client::new());
+// papermc (and modrinth, politely) want to know who is asking
+static REQ_CLIENT: LazyLock<Client> = LazyLock::new(|| Client::builder().user_agent(concat!("mcservermodgui/", env!("CARGO_PKG_VERSION"))).build().unwrap());
 static STATIC_IMAGES: LazyLock<_StaticImages> = LazyLock::new(|| _StaticImages {
     missing: RasterHandle::from_bytes(include_bytes!("../assets/missing_image.png").to_vec()),
     unknown: RasterHandle::from_bytes(include_bytes!("../assets/unknown_image.png").to_vec()),
@@ -80,11 +83,46 @@ static SVG_MOD_LOADERS: LazyLock<[Svg;8]> = LazyLock::new(|| [
     Svg::new(STATIC_IMAGES.velocity.clone()),
 ]);
 
-#[derive(serde::Serialize,serde::Deserialize,Debug)]
+#[derive(serde::Serialize,serde::Deserialize,Debug,Clone)]
 struct ProgramData {
     name: String,
     loader: ModLoader,
     version: MinecraftVersion,
+    #[serde(default)]
+    curseforge: CurseforgeConfig,
+    #[serde(default)]
+    server_jar: Option<String>, // relative to the server root. None if we didn't install the server ourselves
+    #[serde(default)]
+    java: Option<String>, // the java binary to run the server with. falls back to whatever `java` is on PATH
+}
+impl ProgramData {
+    /// reads `.mcservermodgui/mcservermodgui.toml` under `root`. None if there isn't one yet
+    fn load(root: &std::path::Path) -> Result<Option<Self>, String> {
+        let path = root.join(".mcservermodgui").join("mcservermodgui.toml");
+        if !path.is_file() {return Ok(None)}
+        let s = std::fs::read_to_string(&path).map_err(|e| format!("Couldn't read {}: {e}", path.display()))?;
+        toml::from_str(&s).map(Some).map_err(|e| format!("Couldn't parse {}: {e}", path.display()))
+    }
+
+    /// writes this to `.mcservermodgui/mcservermodgui.toml` under `root`, where init looks for it
+    fn save(&self, root: &std::path::Path) -> Result<(), String> {
+        let dir = root.join(".mcservermodgui");
+        let s = toml::to_string_pretty(self).map_err(|e| format!("Couldn't serialize program data: {e}"))?;
+        std::fs::create_dir_all(&dir).map_err(|e| format!("Couldn't create {}: {e}", dir.display()))?;
+        std::fs::write(dir.join("mcservermodgui.toml"), s).map_err(|e| format!("Couldn't write program data: {e}"))
+    }
+}
+
+/// the `[curseforge]` table of mcservermodgui.toml. curseforge won't answer without an api key
+#[derive(serde::Serialize,serde::Deserialize,Debug,Clone,Default)]
+struct CurseforgeConfig {
+    api_key: Option<String>,
+    api_url: Option<String>, // only really useful for pointing at a mock server
+}
+impl CurseforgeConfig {
+    fn base_url(&self) -> &str {
+        self.api_url.as_deref().unwrap_or("https://api.curseforge.com")
+    }
 }
 
 
@@ -110,8 +148,17 @@ enum ModLoader {
     Folia,
     Velocity,
 }
+impl ModLoader {
+    /// the folder (relative to the server root) that this loader reads its jars from
+    fn mods_folder(&self) -> &'static str {
+        match self {
+            ModLoader::Fabric | ModLoader::NeoForge | ModLoader::Forge => "mods",
+            ModLoader::Paper | ModLoader::Purpur | ModLoader::Folia | ModLoader::Velocity => "plugins",
+        }
+    }
+}
 
-#[derive(Default, Debug, Copy, Clone, PartialEq)]
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash)]
 enum ModProvider {
     #[default]
     Modrinth,
@@ -137,6 +184,11 @@ enum ImageType {
 
 
 fn main() -> iced::Result {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = cli::run(&args) {
+        std::process::exit(code);
+    }
+
     // let (mc_versions, program_data) = init::init().map_err(|e| panic!("error in init: {e}")).unwrap();
 
     // MC_VERSIONS.set(mc_versions).unwrap();
@@ -145,7 +197,7 @@ fn main() -> iced::Result {
         || {
             let app = AppState::default();
 
-            (app,Task::done(Message::OpenWindow(WindowType::Init)))
+            (app,Task::done(Message::OpenWindow(WindowType::Picker)))
         },
         AppState::update,
         AppState::view
@@ -155,7 +207,7 @@ fn main() -> iced::Result {
         //     ..iced::theme::Palette::DARK
         // })))).antialiasing(true)
         .theme(iced::Theme::KanagawaDragon)
-        .subscription(|_| Subscription::batch([window::close_events().map(Message::WindowClosed),window::close_requests().map(Message::WindowCloseRequested)]))
+        .subscription(AppState::subscription)
         .run()
 }
 
@@ -169,6 +221,7 @@ enum Message {
     WindowCloseRequested(window::Id),
     WindowClosed(window::Id),
 
+    PickerMessage(PickerMessage),
     InitMessage(InitMessage),
     SetupMessage(SetupMessage),
     MainMessage(MainMessage),
@@ -179,6 +232,7 @@ enum Message {
 struct AppState {
     windows: HashMap<window::Id, Window>,
 
+    picker_state: Option<PickerState>,
     init_state: Option<InitState>,
     setup_state: Option<SetupState>,
     main_state: Option<MainState>,
@@ -196,7 +250,7 @@ impl AppState {
                         let Some(main_state) = self.main_state.as_mut() else {panic!("Had ModDownloader window without corresponding main window")};
                         if main_state.mod_downloader_state.is_some() {panic!("Tried to open ModDownloader window while ModDownloader state already exists")};
 
-                        let (state,t) = ModDownloaderState::new(&main_state.program_data);
+                        let (state,t) = ModDownloaderState::new(&main_state.program_data, &main_state.server_path);
                         task = t;
                         main_state.mod_downloader_state = Some(state);
 
@@ -205,15 +259,17 @@ impl AppState {
                             ..Default::default()
                         };
                     }
-                    WindowType::Init => {
-                        if self.init_state.is_some() {panic!("Tried to open Init window while Init state already exists")};
+                    WindowType::Picker => {
+                        if self.picker_state.is_some() {panic!("Tried to open Picker window while Picker state already exists")};
 
-                        let mut state = InitState::default();
-                        task = state.init().map(|v| Message::InitMessage(v));
-                        self.init_state = Some(state);
+                        self.picker_state = Some(PickerState::default());
+                        task = Task::none();
 
                         settings = window::Settings::default();
                     }
+                    WindowType::Init => {
+                        panic!("init should not be opened on its own")
+                    }
                     WindowType::Setup => {
                         panic!("setup should not be opened on its own")
                     },
@@ -245,39 +301,75 @@ impl AppState {
                         let Some(main_state) = self.main_state.as_mut() else {panic!("Had ModDownloader window without corresponding main window")};
 
                         main_state.mod_downloader_state = None;
+                        return main_state.refresh_installed_mods();
                     }
                     _ => {
                         return iced::exit();
                     }
                 }
             }
+            Message::PickerMessage(m) => {
+                if let PickerMessage::OpenPressed(root) = m {
+                    let id = self.windows.iter().find_map(|(&id,w)| if w.window_type == WindowType::Picker {Some(id)} else {None}).expect("tried to close a Picker window that didn't exist");
+                    let picker = self.picker_state.as_mut().unwrap();
+                    // a broken mcservermodgui.toml keeps the picker up with the error instead of opening the server
+                    let program_data = match ProgramData::load(&root) {
+                        Ok(p) => p,
+                        Err(err) => {
+                            eprintln!("{err}");
+                            picker.couldnt_open(err);
+                            return Task::none();
+                        }
+                    };
+                    picker.opened(&root);
+                    self.picker_state = None;
+
+                    let mut state = InitState::default();
+                    let task = state.init(root, program_data).map(Message::InitMessage);
+                    self.init_state = Some(state);
+                    self.windows.insert(id, Window{window_type: WindowType::Init});
+                    return task;
+                }
+                return self.picker_state.as_mut().unwrap().update(m)
+            }
+            Message::MainMessage(MainMessage::SwitchServerPressed) => {
+                let id = self.windows.iter().find_map(|(&id,w)| if w.window_type == WindowType::Main {Some(id)} else {None}).expect("tried to close a Main window that didn't exist");
+                // the button is only there while the server is stopped and the mod downloader is closed
+                self.main_state = None;
+                self.picker_state = Some(PickerState::default());
+                self.windows.insert(id, Window{window_type: WindowType::Picker});
+            }
             Message::InitMessage(m) => {
                 if let InitMessage::InitConcluded = m {
                     let id = self.windows.iter().find_map(|(&id,w)| if w.window_type == WindowType::Init {Some(id)} else {None}).expect("tried to close an Init window that didn't exist");   
                     let mut state = self.init_state.take().unwrap();
                     let versions = state.versions_list.unwrap().versions;
-                    let kind: WindowType;
                     
                     println!("removing id...");
 
-                    if let Some(program_data) = state.program_data.take() {
-                        kind = WindowType::Main;
-                        let main = MainState::new(program_data);
+                    let (kind, task) = if let Some(program_data) = state.program_data.take() {
+                        let mut main = MainState::new(program_data, state.current_path.clone());
+                        let task = main.refresh_installed_mods();
                         self.main_state = Some(main);
+                        (WindowType::Main, task)
                     } else {
-                        kind = WindowType::Setup;
                         let mut setup = SetupState::default();
+                        setup.root = state.current_path.clone();
                         if let Some(v) = &state.assumed_version && versions.iter().find(|s| *s == v).is_some() {
                             setup.selected_version = state.assumed_version;
                         }
                         setup.name = state.assumed_name;
                         setup.selected_loader = state.assumed_loader;
+                        setup.skip_install = state.assumed_loader.is_some(); // there's already a server here
+                        let task = setup.detect_java();
                         self.setup_state = Some(setup);
-                    }
-                    MC_VERSIONS.set(versions).expect("versions already set on init");
+                        (WindowType::Setup, task)
+                    };
+                    // already set if this isn't the first server opened since launch
+                    let _ = MC_VERSIONS.set(versions);
                     self.windows.insert(id, Window{window_type: kind});
                     // return Task::batch([window::close(id),Task::done(Message::OpenWindow(kind))]);
-                    return Task::none()
+                    return task
                 }
 
                 return self.init_state.as_mut().unwrap().update(m).map(|v|Message::InitMessage(v));
@@ -287,10 +379,11 @@ impl AppState {
                     let id = self.windows.iter().find_map(|(&id,w)| if w.window_type == WindowType::Setup {Some(id)} else {None}).expect("tried to close an Setup window that didn't exist");   
                     let mut state = self.setup_state.take().unwrap();
 
-                    let main = MainState::new(state.program_data.take().expect("received unfinished program data"));
+                    let mut main = MainState::new(state.program_data.take().expect("received unfinished program data"), state.root.clone());
+                    let task = main.refresh_installed_mods();
                     self.main_state = Some(main);
                     self.windows.insert(id, Window{window_type: WindowType::Main});
-                    return Task::none();
+                    return task;
                 }
                 return self.setup_state.as_mut().unwrap().update(m)
             }
@@ -300,8 +393,14 @@ impl AppState {
         Task::none()
     }
 
+    fn subscription(&self) -> Subscription<Message> {
+        let main = self.main_state.as_ref().map_or(Subscription::none(), |m| m.subscription().map(Message::MainMessage));
+        Subscription::batch([window::close_events().map(Message::WindowClosed),window::close_requests().map(Message::WindowCloseRequested),main])
+    }
+
     fn view(&'_ self, window_id: window::Id) -> Element<'_, Message> {
         match self.windows.get(&window_id).expect(&format!("{window_id} not in windows list")).window_type {
+        WindowType::Picker => self.picker_state.as_ref().unwrap().view().map(Message::PickerMessage),
         WindowType::Init => self.init_state.as_ref().unwrap().view().map(|v| Message::InitMessage(v)),
         WindowType::Setup => self.setup_state.as_ref().unwrap().view().map(|v| Message::SetupMessage(v)),
         WindowType::Main => self.main_state.as_ref().unwrap().view().map(|v| Message::MainMessage(v)),
@@ -314,6 +413,18 @@ pub fn bold<'a, M: iced::widget::text::Catalog + 'a>(content: impl iced::widget:
     iced::widget::text(content).font(iced::Font {weight: iced::font::Weight::Bold, ..Default::default()})
 }
 
+fn loader_icon<'a>(loader: ModLoader) -> iced::widget::Svg<'a> {
+    iced::widget::svg(match loader {
+        ModLoader::Fabric => STATIC_IMAGES.fabric.clone(),
+        ModLoader::NeoForge => STATIC_IMAGES.neoforge.clone(),
+        ModLoader::Forge => STATIC_IMAGES.forge.clone(),
+        ModLoader::Paper => STATIC_IMAGES.paper.clone(),
+        ModLoader::Purpur => STATIC_IMAGES.purpur.clone(),
+        ModLoader::Folia => STATIC_IMAGES.folia.clone(),
+        ModLoader::Velocity => STATIC_IMAGES.velocity.clone()
+    })
+}
+
 // pub enum ProgramPhase {
 //     Init(InitState),
 //     Setup(SetupState),
@@ -322,6 +433,7 @@ pub fn bold<'a, M: iced::widget::text::Catalog + 'a>(content: impl iced::widget:
 
 #[derive(Debug,Clone,Copy,PartialEq)]
 pub enum WindowType {
+    Picker,
     Init,
     Setup,
     Main,
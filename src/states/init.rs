@@ -1,5 +1,5 @@
 use std::{
-    env, fs,
+    fs,
     io::{Cursor, Read},
     path::{Path, PathBuf},
 };
@@ -51,7 +51,7 @@ enum ErrType {
 #[derive(Default)]
 pub struct InitState {
     phase: InitPhase,
-    current_path: PathBuf,
+    pub current_path: PathBuf,
     err: Option<ErrType>,
 
     pub program_data: Option<ProgramData>,
@@ -167,18 +167,14 @@ impl InitState {
         }.into()
     }
 
-    pub fn init(&mut self) -> Task<InitMessage> {
+    /// `program_data` is what's already been loaded from `root`, if it's been set up
+    pub fn init(&mut self, root: PathBuf, program_data: Option<ProgramData>) -> Task<InitMessage> {
         println!("** INIT:");
-        self.current_path = env::current_dir().unwrap();
+        self.current_path = root;
 
         let m = self.current_path.join(".mcservermodgui");
         if Path::is_dir(&m) {
-            let d = m.join("mcservermodgui.toml");
-            if Path::is_file(&d) {
-                let b = fs::read(d).unwrap();
-                let result: Result<ProgramData, _> = toml::from_slice(&b);
-                self.program_data = Some(result.unwrap());
-            }
+            self.program_data = program_data;
             let v = m.join("minecraft_versions_list_cache.json");
             if Path::is_file(&v) {
                 let b = fs::read(v).unwrap();
@@ -1,23 +1,151 @@
-use iced::{Border, Color, Element, Font, Task, font, widget::{Svg, button, column, container, space, svg, table, text}};
-use iced::widget::pick_list;
-use rand::seq::IteratorRandom;
-use smart_default::SmartDefault;
+use std::{collections::HashMap, path::PathBuf, time::{Duration, SystemTime}};
+use frostmark::{MarkState, MarkWidget};
+use iced::{Color, Element, Font, Subscription, Task, font, widget::{self, button, checkbox, column, container, progress_bar, rich_text, row, rule, scrollable, space, span, stack, svg, text}};
+use iced_widget_extra::{table, text_input};
+use itertools::Itertools;
 
-use crate::{Message, MinecraftVersion, ProgramData, STATIC_IMAGES, states::mod_downloader::ModDownloaderState};
-use crate::util::icon_pick_list::icon_pick_list;
+use crate::{Message, ProgramData, STATIC_IMAGES, bold, loader_icon, reqwests};
+use crate::states::mod_downloader::{self, ModDownloaderState, modrinth::{self, ModrinthMod, ModrinthVersionDownload}, provider::Version};
+use crate::util::bisect::{Bisection, Outcome, RunStep, TestRun};
+use crate::util::crash_triage::{self, CrashTriage};
+use crate::util::installed_mods::{self, InstalledMod};
+use crate::util::updater::{self, UpdateEvent};
+use crate::util::modpack::{self, ExportDownload, ExportJar};
+use crate::util::supervisor::{self, LaunchConfig, ServerState, Supervisor, SupervisorEvent};
+
+mod config_editor;
+mod console;
+mod properties;
+use config_editor::{ConfigEditor, ConfigMessage};
+use console::Console;
+use properties::{PropertiesEditor, PropertiesMessage};
+
+const CONSOLE_INPUT: &str = "console-input";
+/// how long a bisection run gets to reach "Done" before it counts as a crash. big packs can take minutes to start
+const BISECT_START_TIMEOUT: Duration = Duration::from_secs(10 * 60);
 
 #[derive(Clone, Debug)]
 pub enum MainMessage {
     OpenButtonPressed,
-    TestSelected(String),
+    RefreshButtonPressed,
+    InstalledModsScanned(Vec<InstalledMod>),
+    ModrinthVersionsIdentified(Result<Vec<u8>, String>),
+    ModrinthProjectsReceived(Result<Vec<u8>, String>),
+    OpenLink(String),
+
+    CheckUpdatesButtonPressed,
+    UpdatesReceived(Result<Vec<u8>, String>),
+    UpdateButtonPressed(String),
+    UpdateAllButtonPressed,
+    UpdateEvent(String, UpdateEvent),
+
+    Supervisor(SupervisorEvent),
+    StartServerPressed,
+    StopServerPressed,
+    RestartServerPressed,
+    KillServerPressed,
+
+    ConsoleTyped(String),
+    ConsoleSubmitted,
+    ConsoleHistoryPrevious,
+    ConsoleHistoryNext,
+
+    CrashTriaged(CrashTriage),
+    DisableSuspectPressed(String),
+    RollBackSuspectPressed(String),
+    ClosePopupPressed,
+
+    ModToggled(String, bool),
+
+    BisectButtonPressed,
+    StopBisectingPressed,
+
+    ExportButtonPressed,
+    ExportPathTyped(String),
+    ExportVersionTyped(String),
+    ExportConfirmed,
+    ExportFinished(Result<(usize, usize), String>),
+
+    SwitchServerPressed, // handled by the app, which swaps this window for the picker
+
+    TabSelected(MainTab),
+    Properties(PropertiesMessage),
+    Configs(ConfigMessage),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MainTab {
+    Mods,
+    Properties,
+    Configs,
+}
+
+#[derive(Default)]
+enum PopupState {
+    #[default]
+    None,
+    CrashTriage(CrashTriage),
+    BisectResult(Outcome),
+    Error(&'static str, String),
+    Info(&'static str, String),
+    Export(ExportForm),
+}
+
+struct ExportForm {
+    path: String,
+    version: String,
+    exporting: bool,
+    error: String,
+}
+
+/// a "find the broken mod" session in progress
+struct BisectRun {
+    bisection: Bisection,
+    moved: Vec<String>, // jars this has turned off, to be turned back on afterwards
+    test: TestRun,
+    aborted: bool,
+}
+
+struct AvailableUpdate {
+    sha1: String, // of the installed jar this replaces
+    version: ModrinthVersionDownload,
+    changelog: MarkState,
+    state: UpdateState,
+}
+
+enum UpdateState {
+    Available,
+    Downloading(f32),
+    Failed(String),
 }
 
 pub struct MainState {
     pub program_data: ProgramData,
     pub mod_downloader_state: Option<ModDownloaderState>,
 
-    include_snapshots: bool,
-    selected_gem: String,
+    pub server_path: PathBuf,
+    installed_mods: Vec<InstalledMod>,
+    is_scanning: bool,
+
+    modrinth_versions: HashMap<String, ModrinthVersionDownload>, // keyed by sha1 of the installed file
+    modrinth_projects: HashMap<String, ModrinthMod>,
+    identify_error: Option<String>,
+
+    available_updates: Vec<AvailableUpdate>,
+    is_checking_updates: bool,
+
+    supervisor: Option<Supervisor>,
+    server_state: ServerState,
+    launch_error: Option<String>,
+    console: Console,
+    server_started_at: Option<SystemTime>, // crash reports older than this aren't about the current run
+
+    popup_state: PopupState,
+    bisect: Option<BisectRun>,
+
+    tab: MainTab,
+    properties: PropertiesEditor,
+    configs: ConfigEditor,
 }
 impl MainState {
     pub fn update(&mut self, _message: MainMessage) -> Task<Message> {
@@ -25,48 +153,679 @@ impl MainState {
             MainMessage::OpenButtonPressed => {
                 return Task::done(Message::OpenWindow(crate::WindowType::ModDownload))
             },
-            MainMessage::TestSelected(s) => {
-                self.selected_gem = s;
+            MainMessage::RefreshButtonPressed => {
+                return self.refresh_installed_mods();
+            }
+            MainMessage::InstalledModsScanned(mods) => {
+                self.is_scanning = false;
+                self.installed_mods = mods;
+
+                let unknown = self.installed_mods.iter()
+                    .filter(|m| !m.sha1.is_empty() && !self.modrinth_versions.contains_key(&m.sha1))
+                    .map(|m| m.sha1.clone()).collect_vec();
+                if unknown.is_empty() {return Task::none()}
+                return Task::perform(reqwests::get_versions_from_hashes(unknown, "sha1"), MainMessage::ModrinthVersionsIdentified)
+                    .map(Message::MainMessage);
+            }
+            MainMessage::ModrinthVersionsIdentified(res) => match res {
+                Ok(val) => {
+                    let des = &mut serde_json::Deserializer::from_slice(&val);
+                    let result: Result<HashMap<String, ModrinthVersionDownload>, _> = serde_path_to_error::deserialize(des);
+                    match result {
+                        Ok(versions) => {
+                            let missing_projects = versions.values()
+                                .map(|v| v.project_id.clone())
+                                .filter(|id| !self.modrinth_projects.contains_key(id))
+                                .unique().collect_vec();
+                            self.modrinth_versions.extend(versions);
+                            if missing_projects.is_empty() {return Task::none()}
+                            return Task::perform(reqwests::fetch_mods(missing_projects), MainMessage::ModrinthProjectsReceived)
+                                .map(Message::MainMessage);
+                        }
+                        Err(e) => {
+                            eprintln!("error parsing JSON from version_files at {}: {e}", e.path());
+                            self.identify_error = Some(e.to_string());
+                        }
+                    }
+                }
+                Err(err) => {
+                    eprintln!("Couldn't identify installed mods: {err}");
+                    self.identify_error = Some(err);
+                }
+            }
+            MainMessage::ModrinthProjectsReceived(res) => match res {
+                Ok(val) => {
+                    let des = &mut serde_json::Deserializer::from_slice(&val);
+                    let result: Result<Vec<ModrinthMod>, _> = serde_path_to_error::deserialize(des);
+                    match result {
+                        Ok(projects) => {
+                            self.modrinth_projects.extend(projects.into_iter().map(|p| (p.id.clone(), p)));
+                            self.identify_error = None;
+                        }
+                        Err(e) => {
+                            eprintln!("error parsing JSON from projects fetch at {}: {e}", e.path());
+                            self.identify_error = Some(e.to_string());
+                        }
+                    }
+                }
+                Err(err) => {
+                    eprintln!("Couldn't get projects of installed mods: {err}");
+                    self.identify_error = Some(err);
+                }
+            }
+            MainMessage::OpenLink(url) => return Task::done(Message::OpenLink(url)),
+            MainMessage::CheckUpdatesButtonPressed => {
+                let hashes = self.installed_mods.iter()
+                    .filter(|m| m.enabled && self.modrinth_versions.contains_key(&m.sha1))
+                    .map(|m| m.sha1.clone()).collect_vec();
+                if hashes.is_empty() {return Task::none()}
+                self.is_checking_updates = true;
+                return Task::perform(
+                    reqwests::get_latest_versions_from_hashes(
+                        hashes,
+                        "sha1",
+                        modrinth::compatible_loader_tags(self.program_data.loader),
+                        vec![self.program_data.version.id.clone()],
+                    ),
+                    MainMessage::UpdatesReceived
+                ).map(Message::MainMessage);
+            }
+            MainMessage::UpdatesReceived(res) => {
+                self.is_checking_updates = false;
+                match res {
+                    Ok(val) => {
+                        let des = &mut serde_json::Deserializer::from_slice(&val);
+                        let result: Result<HashMap<String, ModrinthVersionDownload>, _> = serde_path_to_error::deserialize(des);
+                        match result {
+                            Ok(latest) => {
+                                self.available_updates = latest.into_iter()
+                                    .filter(|(sha1, v)| self.modrinth_versions.get(sha1).is_some_and(|current| current.id != v.id))
+                                    .map(|(sha1, version)| AvailableUpdate {
+                                        sha1,
+                                        changelog: MarkState::with_html_and_markdown(version.changelog.as_deref().unwrap_or("*no changelog*")),
+                                        version,
+                                        state: UpdateState::Available,
+                                    })
+                                    .collect();
+                            }
+                            Err(e) => {
+                                eprintln!("error parsing JSON from update check at {}: {e}", e.path());
+                                self.identify_error = Some(e.to_string());
+                            }
+                        }
+                    }
+                    Err(err) => {
+                        eprintln!("Couldn't check for updates: {err}");
+                        self.identify_error = Some(err);
+                    }
+                }
+            }
+            MainMessage::UpdateButtonPressed(sha1) => {
+                return self._apply_update(&sha1);
+            }
+            MainMessage::UpdateAllButtonPressed => {
+                let hashes = self.available_updates.iter()
+                    .filter(|u| matches!(u.state, UpdateState::Available | UpdateState::Failed(_)))
+                    .map(|u| u.sha1.clone()).collect_vec();
+                return Task::batch(hashes.iter().map(|sha1| self._apply_update(sha1)));
+            }
+            MainMessage::UpdateEvent(sha1, event) => {
+                let Some(i) = self.available_updates.iter().position(|u| u.sha1 == sha1) else {return Task::none()};
+                match event {
+                    UpdateEvent::Progressed { downloaded, total } => {
+                        let total = total.unwrap_or(0).max(1);
+                        self.available_updates[i].state = UpdateState::Downloading((downloaded as f32 / total as f32).min(1.0));
+                    }
+                    UpdateEvent::Done(left_over) => {
+                        if let Some(err) = left_over {
+                            eprintln!("{err}");
+                            self.popup_state = PopupState::Error(
+                                "Couldn't remove the old jar",
+                                format!("{} was updated, but the old jar is still in the mods folder next to the new one. Remove it by hand. {err}", self.available_updates[i].version.name),
+                            );
+                        }
+                        self.available_updates.remove(i);
+                        return self.refresh_installed_mods();
+                    }
+                    UpdateEvent::Failed(err) => {
+                        eprintln!("Couldn't update {sha1}: {err}");
+                        self.available_updates[i].state = UpdateState::Failed(err);
+                    }
+                }
+            }
+            MainMessage::Supervisor(event) => match event {
+                SupervisorEvent::Ready(s) => self.supervisor = Some(s),
+                SupervisorEvent::StateChanged(state) => {
+                    if state == ServerState::Starting {
+                        self.server_started_at = Some(SystemTime::now());
+                    }
+                    let crashed_while_up = matches!(state, ServerState::Crashed(_)) && matches!(self.server_state, ServerState::Starting | ServerState::Running);
+                    self.server_state = state;
+                    if self.bisect.is_some() {
+                        return self._bisect_state_changed();
+                    }
+                    if crashed_while_up && let Some(since) = self.server_started_at {
+                        let installed = self.installed_mods.iter().filter(|m| m.enabled).cloned().collect_vec();
+                        let (root, mods_dir) = (self.server_path.clone(), self._mods_dir());
+                        return Task::perform(_blocking(move || crash_triage::triage(&root, &mods_dir, &installed, since)), MainMessage::CrashTriaged)
+                            .map(Message::MainMessage);
+                    }
+                }
+                SupervisorEvent::Output(line) => self.console.push(&line),
+            }
+            MainMessage::StartServerPressed | MainMessage::RestartServerPressed => {
+                let Some(supervisor) = &self.supervisor else {return Task::none()};
+                match LaunchConfig::for_server(&self.program_data, &self.server_path) {
+                    Ok(config) => {
+                        self.launch_error = None;
+                        if let MainMessage::RestartServerPressed = _message {supervisor.restart(config)} else {supervisor.start(config)}
+                    }
+                    Err(err) => self.launch_error = Some(err),
+                }
+            }
+            MainMessage::StopServerPressed => if let Some(s) = &self.supervisor {s.stop()},
+            MainMessage::KillServerPressed => if let Some(s) = &self.supervisor {s.kill()},
+            MainMessage::CrashTriaged(triage) => {
+                self.popup_state = PopupState::CrashTriage(triage);
+            }
+            MainMessage::DisableSuspectPressed(file_name) => {
+                let result = installed_mods::set_enabled(&self.server_path, self.program_data.loader, &file_name, false);
+                return self._suspect_handled(&file_name, result);
+            }
+            MainMessage::RollBackSuspectPressed(file_name) => {
+                let PopupState::CrashTriage(triage) = &self.popup_state else {return Task::none()};
+                let Some(backup) = triage.suspects.iter().find(|s| s.file_name == file_name).and_then(|s| s.backup.clone()) else {return Task::none()};
+                let result = crash_triage::roll_back(&self.server_path, &self._mods_dir(), &file_name, &backup);
+                return self._suspect_handled(&file_name, result);
+            }
+            MainMessage::ClosePopupPressed => self.popup_state = PopupState::None,
+            MainMessage::ExportButtonPressed => {
+                let file_name = format!("{}.mrpack", self.program_data.name.trim().replace(['/', '\\'], "_"));
+                self.popup_state = PopupState::Export(ExportForm {
+                    path: self.server_path.join(".mcservermodgui").join(file_name).display().to_string(),
+                    version: "1.0.0".to_string(),
+                    exporting: false,
+                    error: String::new(),
+                });
+            }
+            MainMessage::ExportPathTyped(s) => if let PopupState::Export(form) = &mut self.popup_state {form.path = s},
+            MainMessage::ExportVersionTyped(s) => if let PopupState::Export(form) = &mut self.popup_state {form.version = s},
+            MainMessage::ExportConfirmed => {
+                let jars = self._export_jars();
+                let PopupState::Export(form) = &mut self.popup_state else {return Task::none()};
+                form.exporting = true;
+                form.error.clear();
+                let (root, dest, program_data, version) = (self.server_path.clone(), PathBuf::from(form.path.trim()), self.program_data.clone(), form.version.trim().to_string());
+                return Task::perform(
+                    _blocking(move || modpack::export_mrpack(&root, &dest, &program_data, &version, &jars)),
+                    MainMessage::ExportFinished
+                ).map(Message::MainMessage);
+            }
+            MainMessage::ExportFinished(res) => {
+                let PopupState::Export(form) = &mut self.popup_state else {return Task::none()};
+                form.exporting = false;
+                match res {
+                    Ok((downloaded, shipped)) => {
+                        let body = format!("Saved to {}. {downloaded} mods are downloaded from Modrinth and {shipped} are included in the pack, along with config/", form.path.trim());
+                        self.popup_state = PopupState::Info("Modpack exported", body);
+                    }
+                    Err(err) => {
+                        eprintln!("Couldn't export modpack: {err}");
+                        form.error = err;
+                    }
+                }
+            }
+            MainMessage::SwitchServerPressed => unreachable!(),
+            MainMessage::TabSelected(tab) => self.tab = tab,
+            MainMessage::Properties(m) => self.properties.update(m, &self.server_path),
+            MainMessage::Configs(m) => self.configs.update(m, &self.server_path, self.program_data.loader),
+            MainMessage::BisectButtonPressed => {
+                let jars = self.installed_mods.iter().filter(|m| m.enabled).map(|m| m.file_name.clone()).collect_vec();
+                if jars.is_empty() {return Task::none()}
+                self.bisect = Some(BisectRun { bisection: Bisection::new(jars, &self._required_jars()), moved: vec![], test: TestRun::default(), aborted: false });
+                self.popup_state = PopupState::None;
+                return self._bisect_run(vec![]);
+            }
+            MainMessage::StopBisectingPressed => {
+                let Some(run) = &mut self.bisect else {return Task::none()};
+                run.aborted = true;
+                if matches!(self.server_state, ServerState::Starting | ServerState::Running) {
+                    if let Some(s) = &self.supervisor {s.stop()}
+                } else {
+                    return self._bisect_finish(None);
+                }
+            }
+            MainMessage::ModToggled(file_name, enabled) => {
+                if let Err(err) = installed_mods::set_enabled(&self.server_path, self.program_data.loader, &file_name, enabled) {
+                    eprintln!("{err}");
+                    self.popup_state = PopupState::Error("Couldn't change the mod", err);
+                }
+                return self.refresh_installed_mods();
+            }
+            MainMessage::ConsoleTyped(s) => self.console.typed(s),
+            MainMessage::ConsoleSubmitted => {
+                if let Some(s) = &self.supervisor && let Some(line) = self.console.submit() {
+                    s.send_line(line);
+                }
+            }
+            MainMessage::ConsoleHistoryPrevious | MainMessage::ConsoleHistoryNext => {
+                if let MainMessage::ConsoleHistoryPrevious = _message {self.console.history_previous()} else {self.console.history_next()}
+                return widget::operation::move_cursor_to_end(CONSOLE_INPUT);
             }
         };
         Task::none()
     }
 
-    pub fn view(&self) -> Element<MainMessage> {
+    pub fn view(&self) -> Element<'_, MainMessage> {
         let columns = [
-            table::column("Mustard", |s: crate::SvgHandle| 
-            // container(svg(s)).style(|t|container::Style::default().border(Border::default().width(1).color(Color::WHITE)))
-            svg(s).width(30)//.height(30)
-        )
+            table::column(bold("On"), |m: &InstalledMod| checkbox(m.enabled).on_toggle(|b| MainMessage::ModToggled(m.file_name.clone(), b))),
+            table::column(bold("Name"), |m: &InstalledMod| text(&m.name).color_maybe((!m.enabled).then_some(Color::from_rgb8(150, 150, 150)))),
+            table::column(bold("Version"), |m: &InstalledMod| text(&m.version)),
+            table::column(bold("Loader"), move |m: &InstalledMod| -> Element<'_, MainMessage> {
+                match m.loader {
+                    Some(l) => loader_icon(l).width(20).height(20).into(),
+                    None => svg(STATIC_IMAGES.unknown_loader.clone()).width(20).height(20).into(),
+                }
+            }),
+            table::column(bold("Minecraft"), |m: &InstalledMod| text(&m.minecraft_range)),
+            table::column(bold("Modrinth"), |m: &InstalledMod| -> Element<'_, MainMessage> {
+                match self.modrinth_link(m) {
+                    Some((project, _)) => rich_text![span(&project.title).link(format!("https://modrinth.com/project/{}", project.slug)).color(Color::from_rgb8(27, 217, 106))]
+                        .on_link_click(MainMessage::OpenLink).into(),
+                    None => text("unknown").color(Color::from_rgb8(150, 150, 150)).into(),
+                }
+            }),
+            table::column(bold("File"), |m: &InstalledMod| text(&m.file_name).size(12).color(Color::from_rgb8(150, 150, 150))),
         ];
+
+        let inventory: Element<MainMessage> = if self.installed_mods.is_empty() {
+            container(text(if self.is_scanning {"scanning..."} else {"no mods installed yet"})).padding(20).into()
+        } else {
+            scrollable(table(columns, self.installed_mods.iter()).padding(6).width(iced::Fill)).height(iced::Fill).spacing(5).into()
+        };
+
+        let updates: Element<MainMessage> = if self.available_updates.is_empty() {
+            space().into()
+        } else {
+            column![
+                row![
+                    bold(format!("{} updates available", self.available_updates.len())),
+                    space().width(iced::Fill),
+                    button("Update all").on_press(MainMessage::UpdateAllButtonPressed),
+                ].align_y(iced::Center),
+                scrollable(column(self.available_updates.iter().map(|u| self._create_update_listing(u))).spacing(10)).height(250).spacing(5),
+                rule::horizontal(1),
+            ].spacing(8).into()
+        };
+
+        let view = column![
+            row![
+                text(&self.program_data.name).font(Font {weight: font::Weight::Bold, ..Default::default()}).size(30),
+                loader_icon(self.program_data.loader).width(30).height(30),
+                text(format!("{} {}", self.program_data.loader, self.program_data.version)).color(Color::from_rgb8(150, 150, 150)),
+                space().width(iced::Fill),
+                // the server and the mod downloader both belong to this folder, so they have to be closed first
+                button("Switch server")
+                    .on_press_maybe((matches!(self.server_state, ServerState::Stopped | ServerState::Crashed(_)) && self.mod_downloader_state.is_none() && self.bisect.is_none())
+                        .then_some(MainMessage::SwitchServerPressed))
+                    .style(button::secondary),
+            ].spacing(10).align_y(iced::Center),
+            self._server_controls(),
+            row(
+                [(MainTab::Mods, "Mods"), (MainTab::Properties, "server.properties"), (MainTab::Configs, "Configs")].map(|(tab, label)| {
+                    button(label).on_press(MainMessage::TabSelected(tab))
+                        .style(if self.tab == tab {button::primary} else {button::text})
+                        .into()
+                })
+            ).spacing(5),
+            match self.tab {
+                MainTab::Mods => self._mods_tab(updates, inventory),
+                MainTab::Properties => self.properties.view().map(MainMessage::Properties),
+                MainTab::Configs => self.configs.view(&self.server_path).map(MainMessage::Configs),
+            },
+            self._console_pane(),
+        ].spacing(10).padding(20);
+
+        self._dialogs(view.into())
+    }
+
+    fn _mods_tab<'a>(&'a self, updates: Element<'a, MainMessage>, inventory: Element<'a, MainMessage>) -> Element<'a, MainMessage> {
+        column![
+            row![
+                text(match self.installed_mods.iter().filter(|m| !m.enabled).count() {
+                    0 => format!("{} installed in {}/", self.installed_mods.len(), self.program_data.loader.mods_folder()),
+                    n => format!("{} installed in {}/, {n} disabled", self.installed_mods.len(), self.program_data.loader.mods_folder()),
+                }),
+                text(self.identify_error.as_ref().map_or(String::new(), |e| format!("couldn't check modrinth: {e}"))).color(Color::from_rgb8(200, 0, 0)),
+                space().width(iced::Fill),
+                button("Refresh").on_press(MainMessage::RefreshButtonPressed).style(button::secondary),
+                button(if self.is_checking_updates {"Checking..."} else {"Check for updates"})
+                    .on_press_maybe((!self.is_checking_updates && !self.modrinth_versions.is_empty()).then_some(MainMessage::CheckUpdatesButtonPressed))
+                    .style(button::secondary),
+                button("Export modpack").on_press_maybe((!self.is_scanning).then_some(MainMessage::ExportButtonPressed)).style(button::secondary),
+                button("Download mods").on_press(MainMessage::OpenButtonPressed),
+            ].spacing(5).align_y(iced::Center),
+            updates,
+            inventory,
+        ].spacing(10).height(iced::Fill).into()
+    }
+
+    fn _dialogs<'a>(&'a self, view: Element<'a, MainMessage>) -> Element<'a, MainMessage> {
+        match &self.popup_state {
+            PopupState::None => view,
+            PopupState::CrashTriage(triage) => stack![view, self._crash_dialog(triage)].into(),
+            PopupState::BisectResult(outcome) => stack![view, self._bisect_dialog(outcome)].into(),
+            PopupState::Export(form) => stack![view, self._export_dialog(form)].into(),
+            PopupState::Error(title, body) | PopupState::Info(title, body) => stack![
+                view,
+                mod_downloader::dialog(title, text(body).into(), button("Ok").on_press(MainMessage::ClosePopupPressed).into())
+            ].into(),
+        }
+    }
+
+    fn _crash_dialog<'a>(&'a self, triage: &'a CrashTriage) -> Element<'a, MainMessage> {
+        let gray = Color::from_rgb8(150, 150, 150);
+        let suspects: Element<MainMessage> = if triage.suspects.is_empty() {
+            text("Couldn't tell which mod caused it.").color(gray).into()
+        } else {
+            scrollable(column(triage.suspects.iter().map(|s| row![
+                column![
+                    bold(&s.name),
+                    text(&s.file_name).size(12).color(gray),
+                    column(s.reasons.iter().map(|r| text(format!("• {r}")).size(13).into())),
+                ].spacing(2).width(iced::Fill),
+                button("Disable").on_press(MainMessage::DisableSuspectPressed(s.file_name.clone())).style(button::secondary),
+                button("Roll back").on_press_maybe(s.backup.as_ref().map(|_| MainMessage::RollBackSuspectPressed(s.file_name.clone()))).style(button::secondary),
+            ].spacing(5).align_y(iced::Center).into())).spacing(10)).height(iced::Shrink).into()
+        };
+        mod_downloader::dialog(
+            "The server crashed",
+            column![
+                text(&triage.summary).font(Font::MONOSPACE).size(13),
+                text(triage.report.as_ref().map_or(String::new(), |p| format!("crash report: {}", p.display()))).size(12).color(gray),
+                suspects,
+            ].spacing(10).into(),
+            button("Close").on_press(MainMessage::ClosePopupPressed).into(),
+        )
+    }
+
+    /// drops a suspect from the crash dialog once it's been dealt with
+    fn _suspect_handled(&mut self, file_name: &str, result: Result<(), String>) -> Task<Message> {
+        if let Err(err) = result {
+            eprintln!("{err}");
+            self.popup_state = PopupState::Error("Couldn't change the mod", err);
+            return Task::none();
+        }
+        if let PopupState::CrashTriage(triage) = &mut self.popup_state {
+            triage.suspects.retain(|s| s.file_name != file_name);
+            if triage.suspects.is_empty() {
+                self.popup_state = PopupState::None;
+            }
+        }
+        self.refresh_installed_mods()
+    }
+
+    fn _console_pane(&self) -> Element<'_, MainMessage> {
+        let accepts_input = matches!(self.server_state, ServerState::Starting | ServerState::Running);
         column![
-            text(&self.program_data.name).font(Font {weight: font::Weight::Bold, ..Default::default()}).size(30),
-            svg(STATIC_IMAGES.modrinth.clone()).style(|t,v|svg::Style { color: Some(Color::from_rgb8(27, 217, 106)) }),
-            svg(STATIC_IMAGES.curseforge.clone()).style(|t,v|svg::Style { color: Some(Color::from_rgb8(255, 120, 77)) }),
-            svg(STATIC_IMAGES.file.clone()).style(|t,v|svg::Style { color: Some(Color::from_rgb8(255, 200, 122)) }),
-            svg(STATIC_IMAGES.filter.clone()),
-            svg(STATIC_IMAGES.neoforge.clone()),
-            button("open").on_press(MainMessage::OpenButtonPressed),
-            space().height(40),
-            table(columns, [STATIC_IMAGES.modrinth.clone()]),
-            space().height(80),
-            icon_pick_list(vec!["foid".to_string(),"chud".to_string(),"gem".to_string()],Some(&self.selected_gem), MainMessage::TestSelected,
-                |s| match s.as_str() {
-                    "chud" => vec![iced::advanced::svg::Svg::new(STATIC_IMAGES.modrinth.clone()), iced::advanced::svg::Svg::new(STATIC_IMAGES.curseforge.clone())],
-                    _ => vec![iced::advanced::svg::Svg::new(STATIC_IMAGES.neoforge.clone())]
-                },
-            ),
-            pick_list(vec!["foid".to_string(),"chud".to_string(),"gem".to_string()],Some(&self.selected_gem), MainMessage::TestSelected)
-        ].padding(20).into()
-        
-    }
-
-    pub fn new(program_data: ProgramData) -> Self {
+            container(self.console.view()).height(250),
+            text_input(if accepts_input {"Send a command..."} else {"The server isn't running"}, &self.console.input)
+                .id(CONSOLE_INPUT)
+                .font(Font::MONOSPACE)
+                .on_input_maybe(accepts_input.then_some(MainMessage::ConsoleTyped))
+                .on_submit_maybe(accepts_input.then_some(MainMessage::ConsoleSubmitted))
+                .on_arrow_up(MainMessage::ConsoleHistoryPrevious)
+                .on_arrow_down(MainMessage::ConsoleHistoryNext),
+        ].spacing(4).into()
+    }
+
+    pub fn new(program_data: ProgramData, server_path: PathBuf) -> Self {
         MainState {
-            program_data,
             mod_downloader_state: None,
-            include_snapshots: Default::default(),
-            selected_gem: Default::default(),
+            properties: PropertiesEditor::load(&server_path),
+            configs: ConfigEditor::load(&server_path, program_data.loader),
+            program_data,
+            server_path,
+            installed_mods: vec![],
+            is_scanning: false,
+            modrinth_versions: HashMap::new(),
+            modrinth_projects: HashMap::new(),
+            identify_error: None,
+            available_updates: vec![],
+            is_checking_updates: false,
+            supervisor: None,
+            server_state: ServerState::Stopped,
+            launch_error: None,
+            console: Console::default(),
+            server_started_at: None,
+            popup_state: PopupState::None,
+            bisect: None,
+            tab: MainTab::Mods,
         }
     }
+
+    pub fn subscription(&self) -> Subscription<MainMessage> {
+        Subscription::run(supervisor::run).map(MainMessage::Supervisor)
+    }
+
+    fn _server_controls(&self) -> Element<'_, MainMessage> {
+        let color = match self.server_state {
+            ServerState::Running => Color::from_rgb8(27, 217, 106),
+            ServerState::Starting | ServerState::Stopping => Color::from_rgb8(230, 180, 50),
+            ServerState::Crashed(_) => Color::from_rgb8(200, 0, 0),
+            ServerState::Stopped => Color::from_rgb8(150, 150, 150),
+        };
+        let is_up = matches!(self.server_state, ServerState::Starting | ServerState::Running);
+        let ready = self.supervisor.is_some();
+        if let Some(run) = &self.bisect {
+            return row![
+                text(format!("Server {}", self.server_state)).color(color),
+                text(format!("Finding the broken mod: round {}, {} jars left", run.bisection.rounds + 1, run.bisection.candidates().len())),
+                space().width(iced::Fill),
+                button(if run.aborted {"Stopping..."} else {"Stop searching"}).on_press_maybe((!run.aborted).then_some(MainMessage::StopBisectingPressed)).style(button::danger),
+            ].spacing(10).align_y(iced::Center).into();
+        }
+        row![
+            text(format!("Server {}", self.server_state)).color(color),
+            text(self.launch_error.as_deref().unwrap_or("")).color(Color::from_rgb8(200, 0, 0)),
+            space().width(iced::Fill),
+            if self.server_state == ServerState::Stopping {
+                button("Kill").on_press(MainMessage::KillServerPressed).style(button::danger)
+            } else if is_up {
+                button("Stop").on_press(MainMessage::StopServerPressed).style(button::secondary)
+            } else {
+                button("Start").on_press_maybe(ready.then_some(MainMessage::StartServerPressed)).style(button::success)
+            },
+            button("Restart").on_press_maybe((ready && is_up).then_some(MainMessage::RestartServerPressed)).style(button::secondary),
+            button("Find broken mod").on_press_maybe((ready && !is_up && self.server_state != ServerState::Stopping).then_some(MainMessage::BisectButtonPressed))
+                .style(button::secondary),
+        ].spacing(5).align_y(iced::Center).into()
+    }
+
+    /// which enabled jars need which others, going by the required dependencies of the modrinth versions they were identified as
+    fn _required_jars(&self) -> HashMap<String, Vec<String>> {
+        let jar_of_project: HashMap<&str, &str> = self.installed_mods.iter()
+            .filter_map(|m| Some((self.modrinth_versions.get(&m.sha1)?.project_id.as_str(), m.file_name.as_str())))
+            .collect();
+        self.installed_mods.iter().filter_map(|m| {
+            let version = self.modrinth_versions.get(&m.sha1)?;
+            let deps = version.dependencies.iter()
+                .filter(|d| d.dependency_type == "required")
+                .filter_map(|d| jar_of_project.get(d.project_id.as_deref()?).map(|j| j.to_string()))
+                .collect_vec();
+            Some((m.file_name.clone(), deps))
+        }).collect()
+    }
+
+    fn _bisect_state_changed(&mut self) -> Task<Message> {
+        let Some(run) = &mut self.bisect else {return Task::none()};
+        match run.test.state_changed(&self.server_state) {
+            RunStep::Wait => Task::none(),
+            RunStep::StopServer => {
+                if let Some(s) = &self.supervisor {s.stop()}
+                Task::none()
+            }
+            RunStep::Finished { .. } if run.aborted => self._bisect_finish(None),
+            RunStep::Finished { crashed } => match run.bisection.record(crashed) {
+                Outcome::Test(disabled) => self._bisect_run(disabled),
+                outcome => self._bisect_finish(Some(outcome)),
+            },
+        }
+    }
+
+    /// turns off exactly `disabled` out of the bisected jars and starts the server
+    fn _bisect_run(&mut self, disabled: Vec<String>) -> Task<Message> {
+        let Some(run) = &mut self.bisect else {return Task::none()};
+        let loader = self.program_data.loader;
+        let mut result = Ok(());
+        for jar in run.moved.iter().filter(|j| !disabled.contains(j)) {
+            result = result.and(installed_mods::set_enabled(&self.server_path, loader, jar, true));
+        }
+        for jar in disabled.iter().filter(|j| !run.moved.contains(j)) {
+            result = result.and(installed_mods::set_enabled(&self.server_path, loader, jar, false));
+        }
+        run.moved = disabled;
+        run.test = TestRun::default();
+
+        // a server that hangs while starting would otherwise stall the search for good
+        let config = LaunchConfig::for_server(&self.program_data, &self.server_path)
+            .map(|config| LaunchConfig { start_timeout: Some(BISECT_START_TIMEOUT), ..config });
+        match (result, config, &self.supervisor) {
+            (Ok(()), Ok(config), Some(s)) => {
+                s.start(config);
+                Task::none()
+            }
+            (Err(err), ..) | (_, Err(err), _) => {
+                self.popup_state = PopupState::Error("Couldn't search for the broken mod", err);
+                self._bisect_finish(None)
+            }
+            (_, _, None) => self._bisect_finish(None),
+        }
+    }
+
+    /// puts every jar back the way it was
+    fn _bisect_finish(&mut self, outcome: Option<Outcome>) -> Task<Message> {
+        let Some(run) = self.bisect.take() else {return Task::none()};
+        for jar in &run.moved {
+            if let Err(err) = installed_mods::set_enabled(&self.server_path, self.program_data.loader, jar, true) {
+                eprintln!("{err}");
+                self.popup_state = PopupState::Error("Couldn't turn a mod back on", err);
+            }
+        }
+        if let Some(outcome) = outcome {
+            self.popup_state = PopupState::BisectResult(outcome);
+        }
+        self.refresh_installed_mods()
+    }
+
+    fn _bisect_dialog<'a>(&'a self, outcome: &'a Outcome) -> Element<'a, MainMessage> {
+        let body: Element<MainMessage> = match outcome {
+            Outcome::Found(jars) => column![
+                text(if jars.len() == 1 {"The server crashes because of:"} else {"The server crashes because of one of these, which depend on each other:"}),
+                column(jars.iter().map(|jar| row![
+                    bold(self.installed_mods.iter().find(|m| &m.file_name == jar).map_or(jar.as_str(), |m| m.name.as_str())).width(iced::Fill),
+                    button("Disable").on_press(MainMessage::ModToggled(jar.clone(), false)).style(button::secondary),
+                ].align_y(iced::Center).into())).spacing(5),
+            ].spacing(10).into(),
+            Outcome::NoCrash => text("The server started fine with every mod on, so there was nothing to find.").into(),
+            Outcome::NotAMod => text("The server crashes even with every mod off, so it isn't caused by a mod.").into(),
+            Outcome::Test(_) => space().into(),
+        };
+        mod_downloader::dialog("Find broken mod", body, button("Close").on_press(MainMessage::ClosePopupPressed).into())
+    }
+
+    fn _export_dialog<'a>(&'a self, form: &'a ExportForm) -> Element<'a, MainMessage> {
+        let unknown = self.installed_mods.iter().filter(|m| m.enabled && self.modrinth_link(m).is_none()).count();
+        let note = match (&self.identify_error, unknown) {
+            (Some(_), _) => "Modrinth couldn't be reached, so every jar is included in the pack instead of being downloaded".to_string(),
+            (None, 0) => "Every mod is downloaded from Modrinth when the pack is imported. config/ is included".to_string(),
+            (None, n) => format!("{n} mods aren't on Modrinth and are included in the pack as they are, along with config/. Make sure you're allowed to share them"),
+        };
+        let body = column![
+            text(note).size(14).color(Color::from_rgb8(150, 150, 150)),
+            row![text("Save to").width(90), text_input("path/to/pack.mrpack", &form.path).on_input(MainMessage::ExportPathTyped)].spacing(8).align_y(iced::Center),
+            row![text("Pack version").width(90), text_input("1.0.0", &form.version).on_input(MainMessage::ExportVersionTyped)].spacing(8).align_y(iced::Center),
+            text(&form.error).color(Color::from_rgb8(200, 0, 0)),
+        ].spacing(8);
+        let ready = !form.exporting && !form.path.trim().is_empty() && !form.version.trim().is_empty();
+        mod_downloader::dialog("Export modpack", body.into(), row![
+            button("Cancel").on_press_maybe((!form.exporting).then_some(MainMessage::ClosePopupPressed)).style(button::secondary),
+            button(if form.exporting {"Exporting..."} else {"Export"}).on_press_maybe(ready.then_some(MainMessage::ExportConfirmed)),
+        ].spacing(5).into())
+    }
+
+    /// the enabled jars with how modrinth would download them, for the pack index
+    fn _export_jars(&self) -> Vec<ExportJar> {
+        self.installed_mods.iter().filter(|m| m.enabled).map(|m| ExportJar {
+            file_name: m.file_name.clone(),
+            download: self.modrinth_link(m).and_then(|(project, version)| {
+                let file = version.files.iter().find(|f| f.hashes.get("sha1") == Some(&m.sha1))?;
+                Some(ExportDownload {
+                    url: file.url.clone(),
+                    hashes: file.hashes.clone(),
+                    size: file.size,
+                    client_side: project.client_side.clone(),
+                    server_side: project.server_side.clone(),
+                })
+            }),
+        }).collect()
+    }
+
+    /// the modrinth project and version an installed jar was identified as, if any
+    pub fn modrinth_link(&self, m: &InstalledMod) -> Option<(&ModrinthMod, &ModrinthVersionDownload)> {
+        let version = self.modrinth_versions.get(&m.sha1)?;
+        Some((self.modrinth_projects.get(&version.project_id)?, version))
+    }
+
+    fn _create_update_listing<'a>(&'a self, update: &'a AvailableUpdate) -> Element<'a, MainMessage> {
+        let installed = self.installed_mods.iter().find(|m| m.sha1 == update.sha1);
+        let name = installed.and_then(|m| self.modrinth_link(m)).map_or(update.version.name.as_str(), |(p, _)| p.title.as_str());
+        let old_version = installed.map_or("?", |m| m.version.as_str());
+
+        let action: Element<MainMessage> = match &update.state {
+            UpdateState::Available => button("Update").on_press(MainMessage::UpdateButtonPressed(update.sha1.clone())).into(),
+            UpdateState::Downloading(p) => progress_bar(0.0..=1.0, *p).length(100).girth(8).into(),
+            UpdateState::Failed(err) => column![
+                text(err).size(12).color(Color::from_rgb8(200, 0, 0)),
+                button("Retry").on_press(MainMessage::UpdateButtonPressed(update.sha1.clone())),
+            ].spacing(4).into(),
+        };
+        column![
+            row![
+                bold(name),
+                text(format!("{old_version} → {}", update.version.version_number)).color(Color::from_rgb8(150, 150, 150)),
+                space().width(iced::Fill),
+                action,
+            ].spacing(10).align_y(iced::Center),
+            container(MarkWidget::new(&update.changelog).text_size(13).on_clicking_link(MainMessage::OpenLink)).padding([0, 15]),
+        ].spacing(4).into()
+    }
+
+    /// downloads the replacement of the installed jar and swaps it in, backing the old one up once the new one is in place
+    fn _apply_update(&mut self, sha1: &str) -> Task<Message> {
+        let Some(update) = self.available_updates.iter_mut().find(|u| u.sha1 == sha1) else {return Task::none()};
+        let Some(old) = self.installed_mods.iter().find(|m| m.sha1 == sha1) else {return Task::none()};
+        update.state = UpdateState::Downloading(0.0);
+        let sha1 = sha1.to_string();
+        Task::run(
+            updater::update_jar(self.server_path.clone(), self.program_data.loader, old.file_name.clone(), Version::from(update.version.clone())),
+            move |e| Message::MainMessage(MainMessage::UpdateEvent(sha1.clone(), e))
+        )
+    }
+
+    fn _mods_dir(&self) -> PathBuf {
+        self.server_path.join(self.program_data.loader.mods_folder())
+    }
+
+    pub fn refresh_installed_mods(&mut self) -> Task<Message> {
+        self.is_scanning = true;
+        let (root, loader) = (self.server_path.clone(), self.program_data.loader);
+        Task::perform(_blocking(move || installed_mods::scan_mods_folder(&root, loader)), MainMessage::InstalledModsScanned)
+            .map(Message::MainMessage)
+    }
+}
+
+/// runs file walking, hashing and zipping on tokio's blocking pool, so big mods folders don't hold up the executor
+async fn _blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+    tokio::task::spawn_blocking(f).await.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
 }
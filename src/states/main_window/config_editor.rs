@@ -0,0 +1,161 @@
+use std::{collections::HashSet, fs, path::{Path, PathBuf}};
+
+use iced::{Color, Element, Font, Length::Fill, Padding, highlighter, widget::{button, column, container, row, rule, scrollable, space, text}};
+use iced_widget_extra::text_editor;
+
+use crate::ModLoader;
+use crate::util::config_files::{self, ConfigFormat, ConfigNode};
+
+#[derive(Debug, Clone)]
+pub enum ConfigMessage {
+    FolderToggled(PathBuf),
+    FileSelected(PathBuf),
+    Edited(text_editor::Action),
+    SavePressed,
+    RevertPressed,
+    RestoreBackupPressed,
+    RefreshPressed,
+}
+
+/// the configs tab: the tree of config files on the left, the open one on the right
+#[derive(Default)]
+pub struct ConfigEditor {
+    dir: &'static str,
+    tree: Vec<ConfigNode>,
+    expanded: HashSet<PathBuf>,
+    open: Option<PathBuf>, // relative to the server root
+    content: text_editor::Content,
+    dirty: bool,
+    status: Option<Result<String, String>>,
+}
+impl ConfigEditor {
+    pub fn load(root: &Path, loader: ModLoader) -> Self {
+        ConfigEditor { dir: config_files::config_dir(loader), tree: config_files::list_tree(root, loader), ..Default::default() }
+    }
+
+    pub fn update(&mut self, message: ConfigMessage, root: &Path, loader: ModLoader) {
+        match message {
+            ConfigMessage::FolderToggled(path) => {
+                if !self.expanded.remove(&path) {
+                    self.expanded.insert(path);
+                }
+            }
+            ConfigMessage::FileSelected(path) => {
+                // switching files throws away unsaved edits, same as closing an editor would without asking
+                self._open(root, path);
+            }
+            ConfigMessage::Edited(action) => {
+                self.dirty |= action.is_edit();
+                self.content.perform(action);
+            }
+            ConfigMessage::SavePressed => {
+                let Some(open) = &self.open else {return};
+                self.status = Some(config_files::save(root, open, &self.content.text()).map(|_| format!("Saved {}", open.display())));
+                match &self.status {
+                    Some(Err(err)) => eprintln!("{err}"),
+                    _ => self.dirty = false,
+                }
+            }
+            ConfigMessage::RevertPressed => {
+                if let Some(open) = self.open.clone() {
+                    self._open(root, open);
+                }
+            }
+            ConfigMessage::RestoreBackupPressed => {
+                let Some(open) = &self.open else {return};
+                // only loads it into the editor, saving it is still up to the user
+                match fs::read_to_string(config_files::backup_path(root, open)) {
+                    Ok(s) => {
+                        self.content = text_editor::Content::with_text(&s);
+                        self.dirty = true;
+                        self.status = Some(Ok("Loaded the version from before the last save".to_string()));
+                    }
+                    Err(e) => self.status = Some(Err(format!("Couldn't read the backup: {e}"))),
+                }
+            }
+            ConfigMessage::RefreshPressed => self.tree = config_files::list_tree(root, loader),
+        }
+    }
+
+    fn _open(&mut self, root: &Path, path: PathBuf) {
+        match fs::read_to_string(root.join(&path)) {
+            Ok(s) => {
+                self.content = text_editor::Content::with_text(&s);
+                self.open = Some(path);
+                self.status = None;
+                self.dirty = false;
+            }
+            Err(e) => self.status = Some(Err(format!("Couldn't open {}: {e}", path.display()))),
+        }
+    }
+
+    /// folders are collapsed until clicked, so a node shows when every folder above it is expanded
+    fn _is_visible(&self, node: &ConfigNode) -> bool {
+        node.path.ancestors().skip(1).take(node.depth).all(|a| self.expanded.contains(a))
+    }
+
+    pub fn view(&self, root: &Path) -> Element<'_, ConfigMessage> {
+        let gray = Color::from_rgb8(150, 150, 150);
+        let red = Color::from_rgb8(200, 0, 0);
+
+        let tree: Element<ConfigMessage> = if self.tree.is_empty() {
+            text("no config files yet, most mods write theirs on the first start").color(gray).into()
+        } else {
+            column(self.tree.iter().filter(|n| self._is_visible(n)).map(|n| {
+                let name = n.path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                let (label, message) = if n.is_dir {
+                    (format!("{} {name}/", if self.expanded.contains(&n.path) {"v"} else {">"}), ConfigMessage::FolderToggled(n.path.clone()))
+                } else {
+                    (name, ConfigMessage::FileSelected(n.path.clone()))
+                };
+                button(text(label).size(13))
+                    .on_press(message)
+                    .style(if self.open.as_ref() == Some(&n.path) {button::primary} else {button::text})
+                    .padding(Padding::from([2, 6]).left(6.0 + 14.0 * n.depth as f32))
+                    .width(Fill)
+                    .into()
+            })).into()
+        };
+
+        let editor: Element<ConfigMessage> = match &self.open {
+            None => container(text("pick a file to edit").color(gray)).padding(20).into(),
+            Some(open) => {
+                let syntax = ConfigFormat::from_path(open).map_or("txt", |f| f.syntax());
+                column![
+                    row![
+                        text(open.display().to_string()).font(Font::MONOSPACE),
+                        match &self.status {
+                            Some(Ok(s)) => text(s).color(gray),
+                            Some(Err(s)) => text(s).color(red),
+                            None => text(""),
+                        },
+                        space().width(Fill),
+                        button("Restore backup")
+                            .on_press_maybe(config_files::backup_path(root, open).exists().then_some(ConfigMessage::RestoreBackupPressed))
+                            .style(button::secondary),
+                        button("Revert").on_press_maybe(self.dirty.then_some(ConfigMessage::RevertPressed)).style(button::secondary),
+                        button("Save").on_press_maybe(self.dirty.then_some(ConfigMessage::SavePressed)),
+                    ].spacing(10).align_y(iced::Center),
+                    text_editor(&self.content)
+                        .on_action(ConfigMessage::Edited)
+                        .font(Font::MONOSPACE)
+                        .height(Fill)
+                        .highlight(syntax, highlighter::Theme::Base16Ocean),
+                ].spacing(10).into()
+            }
+        };
+
+        row![
+            column![
+                row![
+                    text(format!("{}/", self.dir)).color(gray),
+                    space().width(Fill),
+                    button("Refresh").on_press(ConfigMessage::RefreshPressed).style(button::secondary),
+                ].align_y(iced::Center),
+                scrollable(tree).height(Fill).spacing(5),
+            ].spacing(5).width(260),
+            rule::vertical(1),
+            editor,
+        ].spacing(10).height(Fill).into()
+    }
+}
@@ -0,0 +1,291 @@
+use std::collections::VecDeque;
+
+use iced::{Color, Element, Font, Length::Fill, font, widget::{column, container, rich_text, scrollable, span, text::Span}};
+
+const MAX_LINES: usize = 2000;
+const MAX_HISTORY: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogLevel {
+    Info,
+    Warn,
+    Error,
+    Debug,
+    Input, // commands we sent, echoed back
+}
+impl LogLevel {
+    fn color(self) -> Color {
+        match self {
+            LogLevel::Info => Color::from_rgb8(210, 210, 210),
+            LogLevel::Warn => Color::from_rgb8(230, 180, 50),
+            LogLevel::Error => Color::from_rgb8(230, 70, 70),
+            LogLevel::Debug => Color::from_rgb8(130, 130, 130),
+            LogLevel::Input => Color::from_rgb8(120, 170, 240),
+        }
+    }
+}
+
+/// a piece of a line with the style its ansi codes gave it. `None` falls back to the line's log level color
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub text: String,
+    pub color: Option<Color>,
+    pub bold: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConsoleLine {
+    pub level: LogLevel,
+    pub segments: Vec<Segment>,
+}
+
+/// the server's output plus the command box under it
+#[derive(Default)]
+pub struct Console {
+    lines: VecDeque<ConsoleLine>,
+    pub input: String,
+    history: Vec<String>,
+    history_pos: Option<usize>, // where up/down currently are in `history`
+    draft: String, // what was typed before going through the history
+}
+impl Console {
+    pub fn push(&mut self, raw: &str) {
+        let segments = parse_ansi(raw);
+        let plain: String = segments.iter().map(|s| s.text.as_str()).collect();
+        // stack traces and other continuation lines have no prefix, so they belong to the line above
+        let level = parse_level(&plain).unwrap_or_else(|| self.lines.back().map_or(LogLevel::Info, |l| l.level));
+        self._push_line(ConsoleLine { level, segments });
+    }
+
+    /// takes what's been typed, remembers it and echoes it into the log. returns the line to send to the server, if there is one
+    pub fn submit(&mut self) -> Option<String> {
+        let command = std::mem::take(&mut self.input).trim().to_string();
+        self.history_pos = None;
+        self.draft.clear();
+        if command.is_empty() {return None}
+
+        if self.history.last() != Some(&command) {
+            self.history.push(command.clone());
+            if self.history.len() > MAX_HISTORY {
+                self.history.remove(0);
+            }
+        }
+        self._push_line(ConsoleLine { level: LogLevel::Input, segments: vec![Segment { text: format!("> {command}"), color: None, bold: false }] });
+        Some(command)
+    }
+
+    pub fn history_previous(&mut self) {
+        let pos = match self.history_pos {
+            Some(0) => return,
+            Some(p) => p - 1,
+            None if self.history.is_empty() => return,
+            None => {
+                self.draft = self.input.clone();
+                self.history.len() - 1
+            }
+        };
+        self.history_pos = Some(pos);
+        self.input = self.history[pos].clone();
+    }
+
+    pub fn history_next(&mut self) {
+        let Some(pos) = self.history_pos else {return};
+        if pos + 1 < self.history.len() {
+            self.history_pos = Some(pos + 1);
+            self.input = self.history[pos + 1].clone();
+        } else {
+            self.history_pos = None;
+            self.input = std::mem::take(&mut self.draft);
+        }
+    }
+
+    pub fn typed(&mut self, s: String) {
+        self.input = s;
+        self.history_pos = None;
+    }
+
+    fn _push_line(&mut self, line: ConsoleLine) {
+        if self.lines.len() == MAX_LINES {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+    }
+
+    pub fn view<'a, M: 'a>(&'a self) -> Element<'a, M> {
+        let lines = self.lines.iter().map(|l| {
+            let spans: Vec<Span<'a, (), Font>> = l.segments.iter().map(|s| {
+                let mut sp = span(s.text.as_str()).color(s.color.unwrap_or(l.level.color()));
+                if s.bold {
+                    sp = sp.font(Font {weight: font::Weight::Bold, ..Font::MONOSPACE});
+                }
+                sp
+            }).collect();
+            rich_text(spans).font(Font::MONOSPACE).size(13).into()
+        });
+        container(scrollable(column(lines).padding(6).width(Fill)).anchor_bottom().height(Fill))
+            .style(container::dark)
+            .into()
+    }
+}
+
+/// finds the level in the usual log prefixes:
+/// `[12:00:00] [Server thread/INFO]: ...` (vanilla, fabric), `[12:00:00 WARN]: ...` (paper) and
+/// `[12:00:00] [main/ERROR] [forge/]: ...` (forge, neoforge)
+pub fn parse_level(line: &str) -> Option<LogLevel> {
+    let mut rest = line;
+    // the level is always the last word of one of the first few bracketed groups
+    for _ in 0..3 {
+        let group = rest.strip_prefix('[')?;
+        let end = group.find(']')?;
+        match group[..end].rsplit(['/', ' ']).next() {
+            Some("INFO") => return Some(LogLevel::Info),
+            Some("WARN") => return Some(LogLevel::Warn),
+            Some("ERROR" | "FATAL") => return Some(LogLevel::Error),
+            Some("DEBUG" | "TRACE") => return Some(LogLevel::Debug),
+            _ => rest = group[end + 1..].trim_start(),
+        }
+    }
+    None
+}
+
+/// splits `line` on ansi sgr codes (colors, bold, reset). other escape sequences are dropped
+pub fn parse_ansi(line: &str) -> Vec<Segment> {
+    let mut segments = vec![];
+    let mut current = Segment { text: String::new(), color: None, bold: false };
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\u{1b}' {
+            current.text.push(c);
+            continue;
+        }
+        if chars.peek() != Some(&'[') {continue}
+        chars.next();
+        let mut params = String::new();
+        let mut end = None;
+        for c in chars.by_ref() {
+            if ('@'..='~').contains(&c) {
+                end = Some(c);
+                break;
+            }
+            params.push(c);
+        }
+        if end != Some('m') {continue}
+
+        let (color, bold) = apply_sgr(&params, current.color, current.bold);
+        if (color, bold) != (current.color, current.bold) {
+            let next = Segment { text: String::new(), color, bold };
+            let done = std::mem::replace(&mut current, next);
+            if !done.text.is_empty() {
+                segments.push(done);
+            }
+        }
+    }
+    if !current.text.is_empty() || segments.is_empty() {
+        segments.push(current);
+    }
+    segments
+}
+
+fn apply_sgr(params: &str, mut color: Option<Color>, mut bold: bool) -> (Option<Color>, bool) {
+    let mut codes = params.split(';').map(|p| p.parse::<u16>().unwrap_or(0));
+    while let Some(code) = codes.next() {
+        match code {
+            0 => (color, bold) = (None, false),
+            1 => bold = true,
+            22 => bold = false,
+            30..=37 => color = Some(ansi_color(code - 30, false)),
+            90..=97 => color = Some(ansi_color(code - 90, true)),
+            39 => color = None,
+            38 => match codes.next() {
+                // anything past the palette is the server's mistake, and would overflow the math below
+                Some(5) => if let Some(n) = codes.next().filter(|n| *n <= 255) {
+                    color = Some(if n < 16 {ansi_color(n % 8, n >= 8)} else {xterm_color(n)});
+                },
+                Some(2) => {
+                    let mut c = || codes.next().unwrap_or(0).min(255) as u8;
+                    color = Some(Color::from_rgb8(c(), c(), c()));
+                }
+                _ => (),
+            },
+            _ => (),
+        }
+    }
+    (color, bold)
+}
+
+fn ansi_color(n: u16, bright: bool) -> Color {
+    let (r, g, b) = match (n, bright) {
+        (0, false) => (0, 0, 0),
+        (1, false) => (205, 49, 49),
+        (2, false) => (13, 188, 121),
+        (3, false) => (229, 229, 16),
+        (4, false) => (36, 114, 200),
+        (5, false) => (188, 63, 188),
+        (6, false) => (17, 168, 205),
+        (7, false) => (229, 229, 229),
+        (0, true) => (102, 102, 102),
+        (1, true) => (241, 76, 76),
+        (2, true) => (35, 209, 139),
+        (3, true) => (245, 245, 67),
+        (4, true) => (59, 142, 234),
+        (5, true) => (214, 112, 214),
+        (6, true) => (41, 184, 219),
+        _ => (255, 255, 255),
+    };
+    Color::from_rgb8(r, g, b)
+}
+
+/// the 6x6x6 cube and grayscale ramp of the 256 color palette
+fn xterm_color(n: u16) -> Color {
+    if n >= 232 {
+        let v = (8 + (n - 232) * 10) as u8;
+        return Color::from_rgb8(v, v, v);
+    }
+    let n = n - 16;
+    let level = |c: u16| if c == 0 {0} else {(55 + c * 40) as u8};
+    Color::from_rgb8(level(n / 36), level(n / 6 % 6), level(n % 6))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seg(text: &str, color: Option<Color>, bold: bool) -> Segment {
+        Segment { text: text.to_string(), color, bold }
+    }
+
+    #[test]
+    fn levels_come_from_the_log_prefix() {
+        assert_eq!(parse_level("[12:00:00] [Server thread/WARN]: Can't keep up!"), Some(LogLevel::Warn));
+        assert_eq!(parse_level("[12:00:00] [Server thread/INFO]: Done (1.2s)!"), Some(LogLevel::Info));
+        assert_eq!(parse_level("[12:00:00 ERROR]: Could not load plugin"), Some(LogLevel::Error));
+        assert_eq!(parse_level("[12:00:00] [main/DEBUG] [forge/]: loading"), Some(LogLevel::Debug));
+        assert_eq!(parse_level("\tat net.minecraft.server.Main.main(Main.java:1)"), None);
+        assert_eq!(parse_level("[12:00:00] [Server thread/INFOS]: nope"), None);
+    }
+
+    #[test]
+    fn reset_ends_the_style() {
+        assert_eq!(parse_ansi("\u{1b}[1;31mred\u{1b}[0m plain"), vec![
+            seg("red", Some(ansi_color(1, false)), true),
+            seg(" plain", None, false),
+        ]);
+        assert_eq!(parse_ansi("\u{1b}[2Kno style"), vec![seg("no style", None, false)]);
+        assert_eq!(parse_ansi(""), vec![seg("", None, false)]);
+    }
+
+    #[test]
+    fn palette_and_truecolor() {
+        assert_eq!(parse_ansi("\u{1b}[38;5;196ma"), vec![seg("a", Some(Color::from_rgb8(255, 0, 0)), false)]);
+        assert_eq!(parse_ansi("\u{1b}[38;5;9ma"), vec![seg("a", Some(ansi_color(1, true)), false)]);
+        assert_eq!(parse_ansi("\u{1b}[38;5;244ma"), vec![seg("a", Some(Color::from_rgb8(128, 128, 128)), false)]);
+        assert_eq!(parse_ansi("\u{1b}[38;2;10;20;300ma"), vec![seg("a", Some(Color::from_rgb8(10, 20, 255)), false)]);
+    }
+
+    #[test]
+    fn out_of_range_palette_indexes_are_ignored() {
+        assert_eq!(parse_ansi("\u{1b}[38;5;256ma"), vec![seg("a", None, false)]);
+        assert_eq!(parse_ansi("\u{1b}[32m\u{1b}[38;5;9999mb"), vec![seg("b", Some(ansi_color(2, false)), false)]);
+    }
+}
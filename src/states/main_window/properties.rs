@@ -0,0 +1,151 @@
+use std::{collections::HashMap, path::Path};
+
+use iced::{Color, Element, Font, Length::Fill, widget::{button, checkbox, column, pick_list, row, scrollable, space, text}};
+use iced_widget_extra::{pair_grid, text_editor, text_input};
+
+use crate::util::server_properties::{self, FieldKind, ServerProperties, FIELDS};
+
+#[derive(Debug, Clone)]
+pub enum PropertiesMessage {
+    Changed(String, String),
+    RawEdited(text_editor::Action),
+    RawToggled(bool),
+    SavePressed,
+    ReloadPressed,
+}
+
+/// the server.properties tab: a form for the settings everyone changes, or the whole file as text
+#[derive(Default)]
+pub struct PropertiesEditor {
+    properties: ServerProperties,
+    raw: text_editor::Content,
+    raw_mode: bool,
+    errors: HashMap<String, String>,
+    dirty: bool,
+    status: Option<Result<String, String>>, // the last load/save
+}
+impl PropertiesEditor {
+    pub fn load(root: &Path) -> Self {
+        let mut editor = PropertiesEditor::default();
+        editor._reload(root);
+        editor
+    }
+
+    fn _reload(&mut self, root: &Path) {
+        match ServerProperties::load(root) {
+            Ok(p) => {
+                self.properties = p;
+                self.status = None;
+            }
+            Err(err) => self.status = Some(Err(err)),
+        }
+        self.raw = text_editor::Content::with_text(&self.properties.to_string());
+        self.errors.clear();
+        self.dirty = false;
+    }
+
+    pub fn update(&mut self, message: PropertiesMessage, root: &Path) {
+        match message {
+            PropertiesMessage::Changed(key, value) => {
+                match server_properties::validate(server_properties::field_kind(&key), &value) {
+                    Ok(()) => self.errors.remove(&key),
+                    Err(err) => self.errors.insert(key.clone(), err),
+                };
+                self.properties.set(&key, value);
+                self.dirty = true;
+            }
+            PropertiesMessage::RawEdited(action) => {
+                self.dirty |= action.is_edit();
+                self.raw.perform(action);
+            }
+            PropertiesMessage::RawToggled(raw) => {
+                if raw {
+                    self.raw = text_editor::Content::with_text(&self.properties.to_string());
+                } else {
+                    self._take_raw();
+                }
+                self.raw_mode = raw;
+            }
+            PropertiesMessage::SavePressed => {
+                if self.raw_mode {
+                    self._take_raw();
+                }
+                if !self.errors.is_empty() {return}
+                self.status = Some(self.properties.save(root).map(|_| "Saved. Changes apply the next time the server starts".to_string()));
+                if let Some(Err(err)) = &self.status {
+                    eprintln!("{err}");
+                } else {
+                    self.dirty = false;
+                }
+            }
+            PropertiesMessage::ReloadPressed => self._reload(root),
+        }
+    }
+
+    /// reads the text editor back in and checks it like the form would
+    fn _take_raw(&mut self) {
+        self.properties = ServerProperties::parse(&self.raw.text());
+        self.errors = self.properties.keys()
+            .filter_map(|k| {
+                let value = self.properties.get(k).unwrap_or_default();
+                server_properties::validate(server_properties::field_kind(k), value).err().map(|e| (k.to_string(), e))
+            })
+            .collect();
+    }
+
+    pub fn view(&self) -> Element<'_, PropertiesMessage> {
+        let red = Color::from_rgb8(200, 0, 0);
+        let body: Element<PropertiesMessage> = if self.raw_mode {
+            text_editor(&self.raw).on_action(PropertiesMessage::RawEdited).font(Font::MONOSPACE).height(Fill).into()
+        } else {
+            let known = FIELDS.iter().map(|(key, label, kind)| (key.to_string(), label.to_string(), *kind));
+            // whatever else is in the file still gets a plain text field
+            let others = self.properties.keys()
+                .filter(|k| !FIELDS.iter().any(|(f, _, _)| f == k))
+                .map(|k| (k.to_string(), k.to_string(), FieldKind::Text))
+                .collect::<Vec<_>>();
+            let pairs = known.chain(others).map(|(key, label, kind)| {
+                let value = self.properties.get(&key).unwrap_or_default().to_string();
+                let error = self.errors.get(&key);
+                pair_grid::pair(
+                    text(label).color_maybe(error.map(|_| red)),
+                    column![
+                        Self::_field(key, kind, value),
+                        text(error.cloned().unwrap_or_default()).size(12).color(red),
+                    ],
+                )
+            });
+            scrollable(pair_grid(pairs).spacing(6).width(Fill)).height(Fill).spacing(5).into()
+        };
+
+        column![
+            row![
+                checkbox(self.raw_mode).label("Edit as text").on_toggle(PropertiesMessage::RawToggled),
+                match &self.status {
+                    Some(Ok(s)) => text(s).color(Color::from_rgb8(150, 150, 150)),
+                    Some(Err(s)) => text(s).color(red),
+                    None if !self.errors.is_empty() => text(format!("{} invalid values", self.errors.len())).color(red),
+                    None => text(""),
+                },
+                space().width(Fill),
+                button("Reload").on_press(PropertiesMessage::ReloadPressed).style(button::secondary),
+                button("Save").on_press_maybe((self.dirty && (self.raw_mode || self.errors.is_empty())).then_some(PropertiesMessage::SavePressed)),
+            ].spacing(10).align_y(iced::Center),
+            body,
+        ].spacing(10).into()
+    }
+
+    fn _field(key: String, kind: FieldKind, value: String) -> Element<'static, PropertiesMessage> {
+        match kind {
+            FieldKind::Bool => checkbox(value == "true")
+                .on_toggle(move |b| PropertiesMessage::Changed(key.clone(), b.to_string()))
+                .into(),
+            FieldKind::Choice(options) => pick_list(options, options.iter().find(|o| **o == value).copied(), move |o: &str| PropertiesMessage::Changed(key.clone(), o.to_string()))
+                .into(),
+            FieldKind::Port | FieldKind::Int { .. } | FieldKind::Text => text_input("", &value)
+                .on_input(move |s| PropertiesMessage::Changed(key.clone(), s))
+                .width(Fill)
+                .into(),
+        }
+    }
+}
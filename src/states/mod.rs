@@ -1,4 +1,5 @@
 pub mod init;
 pub mod main_window;
 pub mod mod_downloader;
+pub mod server_picker;
 pub mod setup;
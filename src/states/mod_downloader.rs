@@ -1,6 +1,8 @@
 use std::{
     collections::{HashMap, HashSet},
+    fs,
     f32::consts::PI,
+    path::PathBuf,
 };
 use std::fmt::Display;
 use std::time::{Duration, Instant};
@@ -12,20 +14,28 @@ use iced::{Color, Element, Radians, Task, advanced::{image::Handle as RasterHand
 }, border, mouse, Border, Font, font};
 use iced::theme::palette::deviate;
 use iced::widget::scrollable::Scrollbar;
-use iced::widget::{center, hover, opaque, rich_text, right, rule, span, tooltip};
+use iced::widget::{center, hover, opaque, progress_bar, rich_text, right, rule, span, tooltip};
 use iced::widget::button::Status;
 use iced_widget_extra::pick_list_multi;
 use iced_widget_extra::pick_list_multi::{update_selection, SelectionState};
 use iced_selection;
 use itertools::Itertools;
 use rs_abbreviation_number::{AbbreviationOptions, NumericAbbreviate};
-use serde::Deserialize;
 use smart_default::{self, SmartDefault};
 use strum::VariantArray;
-use crate::{ImageType, STATIC_IMAGES, Message::{self, ModDLMessage as SuperMsg}, reqwests, MinecraftVersion, MC_VERSIONS, ProgramData, ModLoader, SVG_MOD_LOADERS, VersionKind, WindowType, bold, ModProvider};
+use crate::{ImageType, STATIC_IMAGES, Message::{self, ModDLMessage as SuperMsg}, reqwests, MinecraftVersion, MC_VERSIONS, ProgramData, ModLoader, SVG_MOD_LOADERS, VersionKind, WindowType, bold, ModProvider, CurseforgeConfig};
 use crate::util::{circular,icon_pick_list::{self, icon_pick_list, Catalog}};
+use crate::util::{hashes, modpack};
 
-mod modrinth;
+pub(crate) mod modrinth;
+pub(crate) mod curseforge;
+mod hangar;
+pub(crate) mod provider;
+
+use provider::{Dependency, DependencyKind, File, Project, Provider, SearchPage, SearchQuery, Version};
+
+/// the versions to download, with the provider each comes from
+type QueuedVersions = Vec<(ModProvider, Version)>;
 
 #[derive(Debug, Clone)]
 pub enum ModDownMsg {
@@ -33,11 +43,12 @@ pub enum ModDownMsg {
     CloseRequested,
 
     ImageDownloaded(Result<reqwests::ImageData, String>),
-    ModsSearchReceived(Result<Vec<u8>, String>),
-    ModReceived(Result<Vec<u8>, String>),
-    ModVersionsReceived(Result<(String, Vec<u8>), String>),
-    DownloadVersionsReceived(Result<Vec<u8>, String>),
-    CategoriesReceived(Result<Vec<u8>, String>),
+    SearchReceived(usize, Result<SearchPage, String>),
+    ProjectReceived(ModProvider, Result<Project, String>),
+    VersionsReceived(Result<(String, Vec<Version>), String>),
+    DownloadVersionsReceived(Result<(QueuedVersions, Vec<InstalledJar>), String>),
+    CategoriesReceived(Result<Vec<String>, String>),
+    DependenciesResolved(String, Result<Vec<modrinth::ResolvedDependency>, String>), // the version they were resolved for
 
     ProviderButtonPressed(ModProvider),
 
@@ -55,10 +66,15 @@ pub enum ModDownMsg {
     SearchSubmitted,
     RetrySearchPressed,
 
-    ModVersionPicked(ModVersion),
+    ModVersionPicked(Version),
     SelectVersionButtonPressed,
     SelectedVersionTrashPressed(usize),
     DownloadButtonPressed,
+    ConfirmDownloadButtonPressed,
+    FileDownloadEvent(usize, reqwests::DownloadEvent),
+    DownloadFinishedButtonPressed,
+    DropQueuedConflictPressed(usize),
+    DropInstalledConflictPressed(String),
 
     ConfirmCloseButtonPressed,
     CancelCloseButtonPressed,
@@ -91,7 +107,91 @@ enum DownloadVerState {
 }
 
 struct DownloadVerData {
+    files: Vec<FileDownload>,
+    installed: Vec<InstalledJar>,
+    conflicts: Vec<Conflict>,
+    started: bool,
+}
+impl DownloadVerData {
+    fn is_finished(&self) -> bool {
+        self.started && self.files.iter().all(|f| matches!(f.state, FileDownloadState::Done | FileDownloadState::Failed(_) | FileDownloadState::Manual(_)))
+    }
+
+    /// checks the `incompatible` edges of every queued version against the other queued versions and the jars already installed,
+    /// and those of the installed jars against the queue
+    fn find_conflicts(&mut self) {
+        self.conflicts.clear();
+        for (i, f) in self.files.iter().enumerate() {
+            for dep in &f.incompatible {
+                for (j, other) in self.files.iter().enumerate() {
+                    if i == j || f.provider != other.provider {continue}
+                    if dep.points_at(&other.project_id, &other.version_id) && !self.conflicts.iter().any(|c| matches!(c.target, ConflictTarget::Queued(t) if t == i) && c.source == j) {
+                        self.conflicts.push(Conflict { source: i, target: ConflictTarget::Queued(j) });
+                    }
+                }
+            }
+            for jar in &self.installed {
+                // installed jars are identified through modrinth, so only modrinth ids can be compared with them
+                let identified = jar.version.as_ref().filter(|_| f.provider == ModProvider::Modrinth);
+                let queued_rules_out = f.incompatible.iter().any(|dep| {
+                    dep.file_name.as_ref() == Some(&jar.file_name) || identified.is_some_and(|v| dep.points_at(&v.project_id, &v.id))
+                });
+                let installed_rules_out = identified.is_some_and(|v| v.dependencies.iter()
+                    .any(|dep| dep.kind == DependencyKind::Incompatible && dep.points_at(&f.project_id, &f.version_id)));
+                if queued_rules_out || installed_rules_out {
+                    self.conflicts.push(Conflict { source: i, target: ConflictTarget::Installed(jar.file_name.clone()) });
+                }
+            }
+        }
+    }
+}
+
+/// a jar in the mods folder and the modrinth version its sha1 belongs to, if modrinth knows it
+#[derive(Debug, Clone)]
+pub struct InstalledJar {
+    file_name: String,
+    version: Option<Version>,
+}
+
+/// every jar in `dir`, identified by its sha1
+async fn identify_installed(dir: PathBuf) -> Result<Vec<InstalledJar>, String> {
+    let jars = fs::read_dir(&dir).map_or(vec![], |r| r.filter_map(|e| {
+        let e = e.ok()?;
+        let file_name = e.file_name().to_string_lossy().into_owned();
+        if !file_name.ends_with(".jar") {return None}
+        Some((file_name, hashes::sha1_hex(&fs::read(e.path()).ok()?)))
+    }).collect_vec());
+    let mut versions = modrinth::versions_from_sha1s(jars.iter().map(|(_, sha1)| sha1.clone()).collect()).await?;
+    Ok(jars.into_iter().map(|(file_name, sha1)| InstalledJar { file_name, version: versions.remove(&sha1) }).collect())
+}
+
+struct FileDownload {
+    provider: ModProvider,
+    project_id: String,
+    project_name: String,
+    version_id: String,
+    version_name: String,
+    file: Option<File>,
+    incompatible: Vec<Dependency>,
+    state: FileDownloadState,
+}
 
+struct Conflict {
+    source: usize, // index into DownloadVerData::files
+    target: ConflictTarget,
+}
+
+enum ConflictTarget {
+    Queued(usize),
+    Installed(String),
+}
+
+enum FileDownloadState {
+    Waiting,
+    Downloading(u64, Option<u64>),
+    Done,
+    Failed(String),
+    Manual(String), // the page to get it from, the author doesn't allow it to be downloaded here
 }
 
 #[derive(Default)]
@@ -99,21 +199,28 @@ pub struct ModDownloaderState {
     popup_state: PopupState,
 
     current_provider: ModProvider,
+    curseforge: CurseforgeConfig,
 
     markup_state: MarkState,
 
     current_mod: Option<ModInfo>,
     cached_images: HashMap<String, ImageType>,
-    cached_mods: HashMap<String, ModrinthMod>,
+    cached_mods: HashMap<(ModProvider, String), Project>, // ids are only unique within a provider
     images_queued: HashSet<String>,
     cached_categories: Vec<String>,
 
     selected_mod_versions: Vec<ModVersionQueued>,
+    download_versions: DownloadVerState,
+    install_path: PathBuf,
+    quarantine_path: PathBuf,
+    server_loader: Option<ModLoader>,
+    server_version: Option<MinecraftVersion>,
 
     current_searchbar_text: String,
     current_query: String,
-    mods_search_results: Vec<ModrinthSearchResult>,
+    mods_search_results: Vec<Project>,
     mods_search_offset: u64,
+    mods_search_total: Option<u64>,
 
     show_filter_option: bool,
     show_snapshots: bool,
@@ -158,85 +265,89 @@ impl ModDownloaderState {
                     eprintln!("Couldn't download image: {err}");
                 }
             },
-            ModDownMsg::ModsSearchReceived(res) => match res {
-                Ok(val) => {
+            // a response to a search that's since been replaced
+            ModDownMsg::SearchReceived(sequence_number, _) if sequence_number != self.search_fetching_sequence_number => (),
+            ModDownMsg::SearchReceived(_, res) => match res {
+                Ok(page) => {
                     self.is_search_fetching = FetchState::Done;
-                    let des = &mut serde_json::Deserializer::from_slice(&val);
-                    let result: Result<ModrinthSearch, _> = serde_path_to_error::deserialize(des);
-                    match result {
-                        Ok(search) => {
-                            let mut img_tasks: Vec<Task<Message>> = vec![];
-                            // println!("{:?}",search);
-                            for r in search.hits {
-                                if let Some(url) = &r.icon_url {
-                                    img_tasks.push(Task::perform(
-                                        reqwests::download_image(url.clone()),
-                                        |r| SuperMsg(ModDownMsg::ImageDownloaded(r)),
-                                    ));
-                                }
-                                self.mods_search_results.push(r);
-                            }
-                            self.scroll_load_debounce = false;
-                            return Task::batch(img_tasks);
-                        }
-                        Err(e) => {
-                            let path = e.path().to_string();
-                            eprintln!(
-                                "error parsing JSON from search: {path}\ndumping json: {}",
-                                String::from_utf8(val).unwrap_or_else(|e| e.to_string())
-                            )
+                    self.mods_search_total = page.total;
+                    let mut img_tasks: Vec<Task<Message>> = vec![];
+                    for r in page.projects {
+                        if let Some(url) = &r.icon_url {
+                            img_tasks.push(Task::perform(
+                                reqwests::download_image(url.clone()),
+                                |r| SuperMsg(ModDownMsg::ImageDownloaded(r)),
+                            ));
                         }
-                    };
+                        self.mods_search_results.push(r);
+                    }
+                    self.scroll_load_debounce = false;
+                    return Task::batch(img_tasks);
                 }
                 Err(err) => {
                     self.is_search_fetching = FetchState::Errored;
-                    eprintln!("Couldn't get json: {err}");
+                    eprintln!("Couldn't search {:?}: {err}", self.current_provider);
                     self.set_popup_state(PopupState::NetworkError("Error searching for mods", err));
                 }
             },
-            ModDownMsg::ModVersionsReceived(res) => match res {
-                Ok((id, val)) => {
-                    let Some( current_mod) = self.current_mod.as_mut() else {
+            ModDownMsg::ProjectReceived(provider, res) => match res {
+                Ok(mut m) => {
+                    self.is_mod_fetching = FetchState::Done;
+                    m.is_in_selected_mod_list = self.selected_mod_versions.iter().any(|v| v.provider == provider && v.project_id == m.id);
+                    let is_current = provider == self.current_provider && self.current_mod.as_ref().is_some_and(|a_mod| a_mod.id == m.id);
+                    self.cached_mods.insert((provider, m.id.clone()), m);
+                    if is_current {
+                        return self.spotlight_mod();
+                    }
+                }
+                Err(err) => {
+                    self.is_mod_fetching = FetchState::Errored;
+                    eprintln!("Couldn't get {:?} project: {err}", self.current_provider);
+                    self.set_popup_state(PopupState::NetworkError("Error fetching mod", err));
+                }
+            },
+            ModDownMsg::VersionsReceived(res) => match res {
+                Ok((id, versions)) => {
+                    let Some(current_mod) = self.current_mod.as_mut() else {
                         return Task::none()
                     };
                     if id != current_mod.id {return Task::none()}
 
-                    let des = &mut serde_json::Deserializer::from_slice(&val);
-                    let result: Result<Vec<ModrinthVersionMass>, _> = serde_path_to_error::deserialize(des);
-                    match result {
-                        Ok(versions) => {
-                            let v = versions.into_iter().map(|m| ModVersion {
-                                name: m.name,
-                                id: m.id,
-                                loaders: m.loaders.iter().filter_map(|x| match x.as_str() {
-                                    "fabric" => Some(ModLoader::Fabric),
-                                    "neoforge" => Some(ModLoader::NeoForge),
-                                    "forge" => Some(ModLoader::Forge),
-                                    "paper" => Some(ModLoader::Paper),
-                                    "purpur" => Some(ModLoader::Purpur),
-                                    "folia" => Some(ModLoader::Folia),
-                                    "velocity" => Some(ModLoader::Velocity),
-                                    _ => None
-                                }).collect_vec(),
-                            }).collect_vec();
-                            current_mod.selected_version = v.get(0).map(|m|m.clone());
-                            current_mod.cached_versions = v;
-                        }
-                        Err(err) => {
-                            panic!("versions received error deserializing {err}")
-                        }
-                    }
+                    current_mod.selected_version = versions.first().cloned();
+                    current_mod.cached_versions = versions;
                 }
                 Err(err) => {
-                    eprintln!("Couldn't get categories: {err}");
+                    eprintln!("Couldn't get mod versions: {err}");
                     self.set_popup_state(PopupState::NetworkError("Error getting mod versions", err));
                 }
             }
             ModDownMsg::DownloadVersionsReceived(res) => match res {
-                Ok(val) => {
-                    let des = &mut serde_json::Deserializer::from_slice(&val);
-                    let result: Vec<ModrinthVersionDownload> = serde_path_to_error::deserialize(des).expect("error deserializing download versions");
-
+                Ok((versions, installed)) => {
+                    if !matches!(self.popup_state, PopupState::DownloadConfirmation) {return Task::none()}
+
+                    let files = versions.into_iter().map(|(provider, v)| {
+                        let project_name = self.selected_mod_versions.iter()
+                            .find(|q| q.version_id == v.id)
+                            .map_or_else(|| v.project_id.clone(), |q| q.project_name.clone());
+                        let file = v.files.iter().find(|f| f.primary).or(v.files.first()).cloned();
+                        FileDownload {
+                            state: match &file {
+                                None => FileDownloadState::Failed("version has no files".to_string()),
+                                Some(File { manual_url: Some(page), .. }) => FileDownloadState::Manual(page.clone()),
+                                Some(_) => FileDownloadState::Waiting,
+                            },
+                            provider,
+                            project_id: v.project_id,
+                            project_name,
+                            version_id: v.id,
+                            version_name: v.name,
+                            file,
+                            incompatible: v.dependencies.into_iter().filter(|d| d.kind == DependencyKind::Incompatible).collect(),
+                        }
+                    }).collect();
+                    let mut data = DownloadVerData { files, installed, conflicts: vec![], started: false };
+                    data.find_conflicts();
+                    self.download_versions = DownloadVerState::Done(data);
                 }
                 Err(err) => {
                     eprintln!("Couldn't get mod versions for download: {err}");
@@ -244,24 +355,7 @@ impl ModDownloaderState {
                 }
             }
             ModDownMsg::CategoriesReceived(res) => match res {
-                Ok(val) => {
-                    let des = &mut serde_json::Deserializer::from_slice(&val);
-                    let result: Result<Vec<ModrinthCategory>, _> = serde_path_to_error::deserialize(des);
-                    match result {
-                        Ok(categories) => {
-                            self.cached_categories = categories.into_iter().filter_map(|c|
-                                if "mod" == c.project_type {
-                                    Some(c.name)
-                                } else {
-                                    None
-                                }
-                            ).collect()
-                        }
-                        Err(err) => {
-                            panic!("categories received error deserializing {err}")
-                        }
-                    }
-                }
+                Ok(categories) => self.cached_categories = categories,
                 Err(err) => {
                     eprintln!("Couldn't get categories: {err}");
                     if !matches!(&self.popup_state, PopupState::NetworkError(_,_)) {
@@ -270,18 +364,62 @@ impl ModDownloaderState {
 
                 }
             }
+            // the version may have been dropped from the queue while its dependencies were being looked up
+            ModDownMsg::DependenciesResolved(root, _) if !self.selected_mod_versions.iter().any(|v| v.provider == ModProvider::Modrinth && v.version_id == root) => (),
+            ModDownMsg::DependenciesResolved(_, res) => match res {
+                Ok(deps) => {
+                    let mut img_tasks: Vec<Task<Message>> = vec![];
+                    for mut dep in deps {
+                        if self.selected_mod_versions.iter().any(|v| v.provider == ModProvider::Modrinth && v.project_id == dep.project.id) {continue}
+
+                        if let Some(url) = &dep.project.icon_url && self.images_queued.insert(url.clone()) {
+                            img_tasks.push(Task::perform(
+                                reqwests::download_image(url.clone()),
+                                |r| SuperMsg(ModDownMsg::ImageDownloaded(r)),
+                            ));
+                        }
+                        self.selected_mod_versions.push(ModVersionQueued {
+                            icon_url: dep.project.icon_url.clone(),
+                            project_name: dep.project.title.clone(),
+                            project_id: dep.project.id.clone(),
+                            version_name: dep.version.name,
+                            version_id: dep.version.id,
+                            loaders: dep.version.loaders,
+                            provider: ModProvider::Modrinth,
+                            pulled_in_by: Some(dep.pulled_in_by),
+                        });
+                        if let Some(current_mod) = &mut self.current_mod && current_mod.id == dep.project.id {
+                            current_mod.is_in_selected_mod_list = true;
+                        }
+                        dep.project.is_in_selected_mod_list = true;
+                        self.cached_mods.insert((ModProvider::Modrinth, dep.project.id.clone()), dep.project);
+                    }
+                    return Task::batch(img_tasks);
+                }
+                Err(err) => {
+                    eprintln!("Couldn't resolve dependencies: {err}");
+                    self.set_popup_state(PopupState::NetworkError("Error resolving required dependencies", err));
+                }
+            }
             ModDownMsg::ProviderButtonPressed(provider) => {
                 self.current_provider = provider;
+                // categories are per provider
+                self.cached_categories.clear();
+                self.selected_filter_categories.clear();
+                return Task::batch([
+                    self._new_mod_search(),
+                    Task::perform(self.backend().categories(), ModDownMsg::CategoriesReceived).map(SuperMsg),
+                ]);
             }
             ModDownMsg::ModListingPressed(index) => {
-                let id = self.mods_search_results[index].project_id.clone();
+                let id = self.mods_search_results[index].id.clone();
                 if let Some(current_mod) = self.current_mod.as_mut() && id == current_mod.id {
                     'abort: {
                         if let Some(timestamp) = self.time_since_mod_button_clicked && timestamp.elapsed() <= Duration::from_millis(300) {
-                            let Some(mod_data) = self.cached_mods.get_mut(&id) else { break 'abort };
+                            let Some(mod_data) = self.cached_mods.get_mut(&(self.current_provider, id.clone())) else { break 'abort };
                             if current_mod.is_in_selected_mod_list {
                                 println!("IS IN SELECTED MOD LIST");
-                                let i = self.selected_mod_versions.iter().find_position(|v| v.project_id == current_mod.id).unwrap().0;
+                                let i = self.selected_mod_versions.iter().find_position(|v| v.provider == self.current_provider && v.project_id == current_mod.id).unwrap().0;
                                 self.selected_mod_versions.remove(i);
                                 current_mod.is_in_selected_mod_list = false;
                                 mod_data.is_in_selected_mod_list = false;
@@ -297,7 +435,12 @@ impl ModDownloaderState {
                                     version_name: selected_version.name.clone(),
                                     version_id: selected_version.id.clone(),
                                     loaders: selected_version.loaders.clone(),
+                                    provider: self.current_provider,
+                                    pulled_in_by: None,
                                 });
+                                let (version_id, name) = (selected_version.id.clone(), mod_data.title.clone());
+                                self.time_since_mod_button_clicked = None;
+                                return self._resolve_dependencies(version_id, name);
                             }
                             self.time_since_mod_button_clicked = None;
                             return Task::none();
@@ -311,7 +454,7 @@ impl ModDownloaderState {
                     widget::Id::new("markup"),
                 scrollable::AbsoluteOffset::<f32>::default(),
                 );
-                if let Some(a_mod) = self.cached_mods.get(&id) {
+                if let Some(a_mod) = self.cached_mods.get(&(self.current_provider, id.clone())) {
                     self.current_mod = Some(ModInfo {
                         id: id.clone(),
                         is_in_selected_mod_list: a_mod.is_in_selected_mod_list,
@@ -325,49 +468,17 @@ impl ModDownloaderState {
                     });
                     self.is_mod_fetching = FetchState::Fetching;
                     self.markup_state = MarkState::with_html("loading...");
+                    let provider = self.current_provider;
                     return Task::batch([
-                        Task::perform(reqwests::fetch_mod(id), ModDownMsg::ModReceived),
+                        Task::perform(self.backend().project(id), move |r| ModDownMsg::ProjectReceived(provider, r)),
                         task,
-                    ])
-                    .map(|m| SuperMsg(m));
+                    ]).map(SuperMsg);
                 }
             }
-            ModDownMsg::ModReceived(res) => {
-                match res {
-                    Ok(val) => {
-                        self.is_mod_fetching = FetchState::Done;
-                        let des = &mut serde_json::Deserializer::from_slice(&val);
-                        let result: Result<ModrinthMod, _> = serde_path_to_error::deserialize(des);
-                        match result {
-                            Ok(m) => {
-                                println!("DIDDY AHH BLUD");
-                                if let Some(a_mod) = &self.current_mod && a_mod.id == m.id {
-                                    let id = m.id.clone();
-                                    self.cached_mods.insert(m.id.clone(), m);
-                                    return self.spotlight_mod();
-                                }
-                                self.cached_mods.insert(m.id.clone(), m);
-                            }
-                            Err(e) => {
-                                let path = e.path().to_string();
-                                eprintln!(
-                                    "error parsing JSON from mod fetch: {} path: {path}\ndumping json: {}",
-                                    e,
-                                    String::from_utf8(val).unwrap_or_else(|e| e.to_string())
-                                )
-                            }
-                        };
-                    }
-                    Err(err) => {
-                        self.is_mod_fetching = FetchState::Errored;
-                        eprintln!("Couldn't get json: {err}");
-                        self.set_popup_state(PopupState::NetworkError("Error fetching mod", err));
-                    }
-                }
-            },
             ModDownMsg::ModsListScrolled(viewport) => {
                 if viewport.absolute_offset_reversed().y <= 300.0 {
-                    if !self.scroll_load_debounce {
+                    let exhausted = self.mods_search_total.is_some_and(|t| self.mods_search_results.len() as u64 >= t);
+                    if !self.scroll_load_debounce && !exhausted {
                         self.mods_search_offset += 20;
                         self.scroll_load_debounce = true;
                         return self._search_and_append();
@@ -417,10 +528,10 @@ impl ModDownloaderState {
             }
             ModDownMsg::SelectVersionButtonPressed => {
                 let Some(current_mod) = self.current_mod.as_mut() else {return Task::none()};
-                let mod_data = self.cached_mods.get_mut(&current_mod.id).unwrap();
+                let mod_data = self.cached_mods.get_mut(&(self.current_provider, current_mod.id.clone())).unwrap();
                 if current_mod.is_in_selected_mod_list {
                     println!("IS IN SELECTED MOD LIST");
-                    let i = self.selected_mod_versions.iter().find_position(|v| v.project_id == current_mod.id).unwrap().0;
+                    let i = self.selected_mod_versions.iter().find_position(|v| v.provider == self.current_provider && v.project_id == current_mod.id).unwrap().0;
                     self.selected_mod_versions.remove(i);
                     current_mod.is_in_selected_mod_list = false;
                     mod_data.is_in_selected_mod_list = false;
@@ -435,8 +546,12 @@ impl ModDownloaderState {
                         project_id: mod_data.id.clone(),
                         version_name: selected_version.name.clone(),
                         version_id: selected_version.id.clone(),
-                        loaders: selected_version.loaders.clone()
+                        loaders: selected_version.loaders.clone(),
+                        provider: self.current_provider,
+                        pulled_in_by: None,
                     });
+                    let (version_id, name) = (selected_version.id.clone(), mod_data.title.clone());
+                    return self._resolve_dependencies(version_id, name);
                 }
             }
             ModDownMsg::SelectedVersionTrashPressed(i) => {
@@ -444,18 +559,98 @@ impl ModDownloaderState {
                 if let Some(current_mod) = &mut self.current_mod && current_mod.id == version.project_id {
                     current_mod.is_in_selected_mod_list = false;
                 };
-                self.cached_mods.get_mut(&version.project_id).unwrap().is_in_selected_mod_list = false;
+                if let Some(m) = self.cached_mods.get_mut(&(version.provider, version.project_id.clone())) {
+                    m.is_in_selected_mod_list = false;
+                }
             }
             ModDownMsg::DownloadButtonPressed => {
+                // the queue can mix providers, so each one fetches its own share
+                let fetches = [ModProvider::Modrinth, ModProvider::Curseforge, ModProvider::Hangar].into_iter().filter_map(|provider| {
+                    let versions = self.selected_mod_versions.iter()
+                        .filter(|v| v.provider == provider)
+                        .map(|v| (v.project_id.clone(), v.version_id.clone()))
+                        .collect_vec();
+                    (!versions.is_empty()).then(|| (provider, provider.backend(&self.curseforge).download_versions(versions, self.server_loader)))
+                }).collect_vec();
+                let install_path = self.install_path.clone();
                 let t = Task::perform(
-                    reqwests::get_mod_versions(
-                        self.selected_mod_versions.iter().map(|v| format!("\"{}\"",v.version_id)).collect()
-                    ),
+                    async move {
+                        let mut versions = vec![];
+                        for (provider, fetch) in fetches {
+                            versions.extend(fetch.await?.into_iter().map(|v| (provider, v)));
+                        }
+                        Ok((versions, identify_installed(install_path).await?))
+                    },
                     ModDownMsg::DownloadVersionsReceived
-                ).map(|m| SuperMsg(m));
+                ).map(SuperMsg);
+                self.download_versions = DownloadVerState::Fetching;
                 self.set_popup_state(PopupState::DownloadConfirmation);
                 return t;
             }
+            ModDownMsg::ConfirmDownloadButtonPressed => {
+                let DownloadVerState::Done(data) = &mut self.download_versions else {return Task::none()};
+                if data.started || !data.conflicts.is_empty() {return Task::none()}
+                data.started = true;
+
+                let install_path = self.install_path.clone();
+                return Task::batch(data.files.iter_mut().enumerate().filter_map(|(i, f)| {
+                    if !matches!(f.state, FileDownloadState::Waiting) {return None}
+                    let file = f.file.as_ref()?;
+                    let path = match modpack::join_file_name(&install_path, &file.filename) {
+                        Ok(p) => p,
+                        Err(err) => {
+                            f.state = FileDownloadState::Failed(err);
+                            return None;
+                        }
+                    };
+                    Some(Task::run(
+                        reqwests::download_file(file.url.clone(), path, file.hashes.clone(), self.quarantine_path.clone()),
+                        move |e| SuperMsg(ModDownMsg::FileDownloadEvent(i, e))
+                    ))
+                }));
+            }
+            ModDownMsg::FileDownloadEvent(i, event) => {
+                let DownloadVerState::Done(data) = &mut self.download_versions else {return Task::none()};
+                let Some(f) = data.files.get_mut(i) else {return Task::none()};
+                f.state = match event {
+                    reqwests::DownloadEvent::Progressed { downloaded, total } => FileDownloadState::Downloading(downloaded, total),
+                    reqwests::DownloadEvent::Finished(Ok(_)) => FileDownloadState::Done,
+                    reqwests::DownloadEvent::Finished(Err(err)) => {
+                        eprintln!("Couldn't download {}: {err}", f.project_name);
+                        FileDownloadState::Failed(err)
+                    }
+                };
+            }
+            ModDownMsg::DownloadFinishedButtonPressed => {
+                let DownloadVerState::Done(data) = std::mem::take(&mut self.download_versions) else {return Task::none()};
+                for f in data.files.iter().filter(|f| matches!(f.state, FileDownloadState::Done)) {
+                    self._unqueue_project(f.provider, &f.project_id);
+                }
+                self.popup_state = PopupState::None;
+            }
+            ModDownMsg::DropQueuedConflictPressed(i) => {
+                let DownloadVerState::Done(data) = &mut self.download_versions else {return Task::none()};
+                if data.started || i >= data.files.len() {return Task::none()}
+                let f = data.files.remove(i);
+                data.find_conflicts();
+                self._unqueue_project(f.provider, &f.project_id);
+            }
+            ModDownMsg::DropInstalledConflictPressed(file_name) => {
+                let DownloadVerState::Done(data) = &mut self.download_versions else {return Task::none()};
+                if data.started {return Task::none()}
+
+                // moved rather than deleted so a wrong click isn't the end of the world
+                let removed_dir = self.quarantine_path.with_file_name("removed");
+                let result = fs::create_dir_all(&removed_dir)
+                    .and_then(|_| fs::rename(self.install_path.join(&file_name), removed_dir.join(&file_name)));
+                if let Err(err) = result {
+                    eprintln!("Couldn't remove {file_name}: {err}");
+                    self.set_popup_state(PopupState::NetworkError("Error removing installed mod", err.to_string()));
+                    return Task::none();
+                }
+                data.installed.retain(|j| j.file_name != file_name);
+                data.find_conflicts();
+            }
 
             ModDownMsg::ConfirmCloseButtonPressed => {
                 return Task::done(Message::CloseWindow(WindowType::ModDownload));
@@ -523,6 +718,7 @@ impl ModDownloaderState {
                 row![
                     provider_button(STATIC_IMAGES.modrinth.clone(), Color::from_rgb8(27, 217, 106), "Modrinth", ModProvider::Modrinth),
                     provider_button(STATIC_IMAGES.curseforge.clone(), Color::from_rgb8(255, 120, 77), "Curseforge", ModProvider::Curseforge),
+                    provider_button(STATIC_IMAGES.hangar.clone(), Color::from_rgb8(0, 79, 255), "Hangar", ModProvider::Hangar),
                 ].spacing(5),
                 row![
                     text_input("Search...", &self.current_searchbar_text).on_input(|s| ModDownMsg::SearchTyped(s)).on_submit(ModDownMsg::SearchSubmitted).icon(text_input::Icon { font: iced::Font::DEFAULT, code_point: '⌕', size: None, spacing: 4.0, side: text_input::Side::Left }),
@@ -546,7 +742,7 @@ impl ModDownloaderState {
                         }
                     ).width(320).height(iced::Fill).spacing(5).on_scroll(ModDownMsg::ModsListScrolled).id(widget::Id::new("search")),
                     column![scrollable( // markdown section
-                        if let Some(a_mod) = &self.current_mod && let Some(listing) = self.cached_mods.get(&a_mod.id) {
+                        if let Some(a_mod) = &self.current_mod && let Some(listing) = self.cached_mods.get(&(self.current_provider, a_mod.id.clone())) {
                             const IMG_SIZE:u32 = 100;
                             let thumbnail: Element<ModDownMsg> = if let Some(url) = &listing.icon_url {
                                 if let Some(img) = self.cached_images.get(url).cloned() {
@@ -574,7 +770,7 @@ impl ModDownloaderState {
                             column![
                                 container(
                                     row![thumbnail,column![
-                                        rich_text![span(&listing.title).link(format!("https://modrinth.com/mod/{}",listing.slug)).color(Color::from_rgb8(175, 200, 240))]
+                                        rich_text![span(&listing.title).link(listing.page_url.clone()).color(Color::from_rgb8(175, 200, 240))]
                                             .size(32).line_height(text::LineHeight::Relative(1.0)).on_link_click(ModDownMsg::OpenLink),
                                         text(&listing.description).color(Color::WHITE).line_height(text::LineHeight::Relative(1.2))
                                     ].spacing(2)].spacing(10)).style(move |_: &_| {
@@ -663,7 +859,7 @@ impl ModDownloaderState {
                             } else if !current_mod.cached_versions.is_empty() {
                                     (button(text("Select").center()).on_press(ModDownMsg::SelectVersionButtonPressed).width(80),
                                     icon_pick_list(current_mod.cached_versions.clone(), current_mod.selected_version.clone(), ModDownMsg::ModVersionPicked,
-                                        |v:&ModVersion| v.loaders.iter().map(|l| match l {
+                                        |v:&Version| v.loaders.iter().map(|l| match l {
                                             ModLoader::Fabric => SVG_MOD_LOADERS[1].clone(),
                                             ModLoader::NeoForge => SVG_MOD_LOADERS[2].clone(),
                                             ModLoader::Forge => SVG_MOD_LOADERS[3].clone(),
@@ -739,6 +935,7 @@ impl ModDownloaderState {
                                 container(column![
                                     bold(&v.project_name).size(14).color(Color::WHITE),
                                     text(&v.version_name).size(10),
+                                    text(v.pulled_in_by.as_ref().map_or(String::new(), |p| format!("pulled in by {p}"))).size(10).color(Color::from_rgb8(150, 150, 150)),
                                     row(v.loaders.iter().map(|l|svg(match l {
                                         ModLoader::Fabric => STATIC_IMAGES.fabric.clone(),
                                         ModLoader::NeoForge => STATIC_IMAGES.neoforge.clone(),
@@ -817,13 +1014,28 @@ impl ModDownloaderState {
                 view,
                 opaque(center(container(column![
                     bold("Confirm Download").size(20),
-                    text("are you sure you want to download:"),
-                    row![space().width(15),iced_selection::text("are you sure?").line_height(1.5).wrapping(text::Wrapping::WordOrGlyph)],
-                    right(row![
-                        button("Cancel").on_press(ModDownMsg::CancelCloseButtonPressed).style(|t: &iced::Theme,s| {
-                            button::secondary(t,s)
-                        })
-                    ])
+                    text(format!("are you sure you want to download to {}:", self.install_path.display())),
+                    match &self.download_versions {
+                        DownloadVerState::Fetching => Element::from(center(circular::Circular::new())),
+                        DownloadVerState::Done(data) => scrollable(column![
+                            column(data.conflicts.iter().map(|c| Self::_create_conflict_listing(data, c))).spacing(5),
+                            column(data.files.iter().map(|f| Self::_create_download_listing(f))).spacing(5)
+                        ].spacing(10)).height(iced::Fill).spacing(5).into()
+                    },
+                    right(match &self.download_versions {
+                        DownloadVerState::Done(data) if data.is_finished() => row![
+                            button("Done").on_press(ModDownMsg::DownloadFinishedButtonPressed)
+                        ],
+                        DownloadVerState::Done(data) if data.started => row![
+                            button("Downloading...")
+                        ],
+                        _ => row![
+                            button("Download").on_press_maybe(matches!(&self.download_versions, DownloadVerState::Done(data) if data.conflicts.is_empty()).then_some(ModDownMsg::ConfirmDownloadButtonPressed)),
+                            button("Cancel").on_press(ModDownMsg::CancelCloseButtonPressed).style(|t: &iced::Theme,s| {
+                                button::secondary(t,s)
+                            })
+                        ].spacing(5)
+                    })
                 ].padding(25).spacing(8)
                 ).width(600).height(650)
                     .style(|t: &iced::Theme| container::Style {
@@ -836,15 +1048,21 @@ impl ModDownloaderState {
         }
     }
 
-    pub fn new(program_data: &crate::ProgramData) -> (Self, Task<Message>) {
+    pub fn new(program_data: &crate::ProgramData, root: &std::path::Path) -> (Self, Task<Message>) {
         let mut state = ModDownloaderState::default();
         state.server_sided_mods_only = true;
 
+        state.curseforge = program_data.curseforge.clone();
+        state.server_loader = Some(program_data.loader);
+        state.server_version = Some(program_data.version.clone());
+        state.install_path = root.join(program_data.loader.mods_folder());
+        state.quarantine_path = root.join(".mcservermodgui").join("quarantine");
+
         update_selection(&mut state.selected_filter_versions, Some(program_data.version.clone()), SelectionState::Included);
         update_selection(&mut state.selected_filter_loaders, Some(program_data.loader), SelectionState::Included);
         let task = Task::batch([
             state._new_mod_search(),
-            Task::perform(reqwests::get_categories(), ModDownMsg::CategoriesReceived).map(|m|SuperMsg(m))
+            Task::perform(state.backend().categories(), ModDownMsg::CategoriesReceived).map(SuperMsg)
         ]);
         (state,task)
     }
@@ -868,7 +1086,7 @@ impl ModDownloaderState {
 
     fn spotlight_mod(&mut self) -> Task<Message> {
         let Some(mod_info) = &self.current_mod else {return Task::none()};
-        let mod_data = self.cached_mods.get(&mod_info.id).unwrap();
+        let mod_data = self.cached_mods.get(&(self.current_provider, mod_info.id.clone())).unwrap();
         self.markup_state = MarkState::with_html_and_markdown(&mod_data.body);
 
         println!("{:#?}",self.current_mod);
@@ -879,25 +1097,13 @@ impl ModDownloaderState {
         ])
     }
     fn create_dialog<'a>(title: &'static str, body: String, buttons: Element<'a, ModDownMsg>) -> Element<'a, ModDownMsg> {
-        opaque(center(container(column![
-            iced_selection::text(title).size(20).font(Font {weight: font::Weight::Bold, ..Default::default()}),
-            row![space().width(15),iced_selection::text(body).line_height(1.5).wrapping(text::Wrapping::WordOrGlyph)],
-            right(buttons)
-        ].padding(25).spacing(8)
-        ).width(600)
-            .style(|t: &iced::Theme| container::Style {
-                background: Some(deviate(t.palette().background,-0.0).into()),
-                border: Border::default().rounded(10).color(t.extended_palette().background.stronger.color).width(1.5),
-                ..Default::default()
-            })
-        ).center(iced::Fill))
-
+        dialog(title, iced_selection::text(body).line_height(1.5).wrapping(text::Wrapping::WordOrGlyph).into(), buttons)
     }
 
     fn _create_mod_listing(&'_ self, mods_list_index: usize) -> Element<'_, ModDownMsg> {
         const IMG_SIZE: u32 = 75;
         let listing = &self.mods_search_results[mods_list_index];
-        let is_selected = self.cached_mods.get(&listing.project_id).map_or(false, |m|m.is_in_selected_mod_list);
+        let is_selected = self.cached_mods.get(&(self.current_provider, listing.id.clone())).is_some_and(|m| m.is_in_selected_mod_list);
         let thumbnail: Element<ModDownMsg> = if let Some(url) = &listing.icon_url {
             if let Some(img) = self.cached_images.get(url).cloned() {
                 match img {
@@ -942,7 +1148,7 @@ impl ModDownloaderState {
             .width(iced::Fill)
             .padding([5, 0])
             .style(move |t,s| {
-                let pair = if let Some(a_mod) = &self.current_mod && a_mod.id == listing.project_id {
+                let pair = if let Some(a_mod) = &self.current_mod && a_mod.id == listing.id {
                     t.extended_palette().secondary.weak
                 } else {
                     t.extended_palette().primary.base
@@ -961,6 +1167,59 @@ impl ModDownloaderState {
         button.into()
     }
 
+    fn _create_download_listing(file: &FileDownload) -> Element<'_, ModDownMsg> {
+        let status: Element<ModDownMsg> = match &file.state {
+            FileDownloadState::Waiting => text(file.file.as_ref().map_or(String::new(), |f| format!("{:.1} MB", f.size as f64 / 1_000_000.0))).size(12).into(),
+            FileDownloadState::Downloading(downloaded, total) => {
+                let total = total.or(file.file.as_ref().map(|f| f.size)).unwrap_or(0).max(1);
+                progress_bar(0.0..=1.0, *downloaded as f32 / total as f32).girth(8).into()
+            }
+            FileDownloadState::Done => text("installed").size(12).color(Color::from_rgb8(27, 217, 106)).into(),
+            FileDownloadState::Failed(err) => text(err).size(12).color(Color::from_rgb8(200, 0, 0)).into(),
+            FileDownloadState::Manual(page) => column![
+                text("Manual download required").size(12).color(Color::from_rgb8(230, 180, 50)),
+                button(text("Open download page").size(12)).on_press(ModDownMsg::OpenLink(page.clone())).style(button::secondary).padding([2, 6]),
+            ].spacing(2).into(),
+        };
+        row![
+            column![
+                bold(&file.project_name).size(14),
+                text(format!("{} - {}", file.version_name, file.file.as_ref().map_or("", |f| f.filename.as_str()))).size(10),
+            ].width(iced::FillPortion(3)),
+            container(status).width(iced::FillPortion(2)).align_y(iced::Center),
+        ].spacing(10).padding([4, 0]).into()
+    }
+
+    fn _create_conflict_listing<'a>(data: &'a DownloadVerData, conflict: &'a Conflict) -> Element<'a, ModDownMsg> {
+        let source = &data.files[conflict.source];
+        let (target_name, drop_target) = match &conflict.target {
+            ConflictTarget::Queued(j) => (data.files[*j].project_name.as_str(), ModDownMsg::DropQueuedConflictPressed(*j)),
+            ConflictTarget::Installed(file_name) => (file_name.as_str(), ModDownMsg::DropInstalledConflictPressed(file_name.clone())),
+        };
+        let installed = if matches!(conflict.target, ConflictTarget::Installed(_)) {" (installed)"} else {""};
+        container(column![
+            bold(format!("{} is incompatible with {target_name}{installed}", source.project_name)).size(14),
+            row![
+                button(text(format!("Drop {}", source.project_name)).size(12)).on_press(ModDownMsg::DropQueuedConflictPressed(conflict.source)).style(button::secondary),
+                button(text(format!("Drop {target_name}")).size(12)).on_press(drop_target).style(button::secondary),
+            ].spacing(5)
+        ].spacing(5)).padding(8).width(iced::Fill).style(|_: &iced::Theme| container::Style {
+            background: Some(Color::from_rgba8(200, 0, 0, 0.2).into()),
+            border: Border::default().rounded(4).color(Color::from_rgb8(200, 0, 0)).width(1),
+            ..Default::default()
+        }).into()
+    }
+
+    fn _unqueue_project(&mut self, provider: ModProvider, project_id: &str) {
+        self.selected_mod_versions.retain(|v| v.provider != provider || v.project_id != project_id);
+        if let Some(current_mod) = &mut self.current_mod && provider == self.current_provider && current_mod.id == project_id {
+            current_mod.is_in_selected_mod_list = false;
+        }
+        if let Some(m) = self.cached_mods.get_mut(&(provider, project_id.to_string())) {
+            m.is_in_selected_mod_list = false;
+        }
+    }
+
     fn _download_markup_images(&mut self) -> Task<ModDownMsg> {
         Task::batch(self.markup_state.find_image_links().into_iter().filter_map(|url| {
             if self.images_queued.insert(url.clone()) {
@@ -976,6 +1235,7 @@ impl ModDownloaderState {
         self.search_fetching_sequence_number += 1;
         self.mods_search_results.clear();
         self.mods_search_offset = 0;
+        self.mods_search_total = None;
 
         self.current_mod = None;
         Task::batch([
@@ -988,164 +1248,51 @@ impl ModDownloaderState {
     }
 
     fn _search_and_append(&mut self) -> Task<Message> {
-        let mut args: Vec<String> = Vec::new();
-        if self.server_sided_mods_only { args.push("\"server_side!=unsupported\"".to_string())}
-        if !self.selected_filter_versions.is_empty() {
-            let a = self.selected_filter_versions.iter().map(|(v,_)|format!("\"versions:{}\"",v.as_ref().unwrap().id)).join(",");
-            args.push(a);
-        }
-        if !self.selected_filter_loaders.is_empty() {
-            let a = self.selected_filter_loaders.iter().map(|(l,_)|format!("\"categories:{}\"",l.unwrap().to_string())).join(",");
-            args.push(a);
-        }
-        if !self.selected_filter_categories.is_empty() {
-            let a = self.selected_filter_categories.iter().map(|(c,_)|format!("\"categories:{}\"",c.as_ref().unwrap()));
-            args.extend(a);
-        }
+        let query = SearchQuery {
+            query: self.current_query.clone(),
+            offset: self.mods_search_offset,
+            loaders: self.selected_filter_loaders.iter().filter_map(|(l,_)| *l).collect(),
+            game_versions: self.selected_filter_versions.iter().filter_map(|(v,_)| v.as_ref().map(|v| v.id.clone())).collect(),
+            categories: self.selected_filter_categories.iter().filter_map(|(c,_)| c.clone()).collect(),
+            server_side_only: self.server_sided_mods_only,
+        };
+
+        let sequence_number = self.search_fetching_sequence_number;
+        Task::perform(self.backend().search(query), move |r| ModDownMsg::SearchReceived(sequence_number, r)).map(SuperMsg)
+    }
+
+    fn backend(&self) -> Box<dyn Provider> {
+        self.current_provider.backend(&self.curseforge)
+    }
+
+    fn _resolve_dependencies(&self, version_id: String, name: String) -> Task<Message> {
+        if self.current_provider != ModProvider::Modrinth {return Task::none()}
+        let (Some(loader), Some(game_version)) = (self.server_loader, self.server_version.clone()) else {return Task::none()};
+        let seen = self.selected_mod_versions.iter().map(|v| v.project_id.clone()).collect();
 
         Task::perform(
-            reqwests::search_mods(
-                self.mods_search_offset,
-                Some(self.current_query.clone()),
-                args,
-            ),
-            ModDownMsg::ModsSearchReceived,
-        ).map(|m|SuperMsg(m))
+            modrinth::resolve_required_dependencies(version_id.clone(), name, loader, game_version, seen),
+            move |res| ModDownMsg::DependenciesResolved(version_id.clone(), res)
+        ).map(SuperMsg)
     }
 
     fn _get_mod_versions(&mut self, id:String) -> Task<Message> {
-        let loaders = self.selected_filter_loaders.iter().map(|(l,_)|l.unwrap().to_string().to_ascii_lowercase()).collect();
-        let game_versions = self.selected_filter_versions.iter().map(|(v,_)|v.as_ref().unwrap().id.clone()).collect();
+        let loaders = self.selected_filter_loaders.iter().filter_map(|(l,_)| *l).collect();
+        let game_versions = self.selected_filter_versions.iter().filter_map(|(v,_)| v.as_ref().map(|v| v.id.clone())).collect();
 
-        Task::perform(reqwests::get_available_mod_versions(id, loaders, game_versions), ModDownMsg::ModVersionsReceived).map(|m|SuperMsg(m))
+        let versions = self.backend().versions(id.clone(), loaders, game_versions);
+        Task::perform(async move { Ok((id, versions.await?)) }, ModDownMsg::VersionsReceived).map(SuperMsg)
     }
 }
 
 #[derive(Default, Debug)]
 struct ModInfo {
     id: String,
-    cached_versions: Vec<ModVersion>,
-    selected_version: Option<ModVersion>,
+    cached_versions: Vec<Version>,
+    selected_version: Option<Version>,
     is_in_selected_mod_list: bool,
 }
 
-#[derive(Debug, serde::Deserialize)]
-struct ModrinthMod {
-    #[serde(skip_deserializing)]
-    is_in_selected_mod_list: bool,
-
-    slug: String,
-    title: String,
-    id: String,
-    body: String,
-    description: String,
-    versions: Vec<String>,
-    game_versions: Vec<String>,
-    loaders: Vec<String>,
-    categories: Vec<String>,
-    additional_categories: Vec<String>,
-    issues_url: Option<String>,
-    source_url: Option<String>,
-    wiki_url: Option<String>,
-    discord_url: Option<String>,
-    client_side: String,
-    server_side: String,
-    project_type: String,
-    downloads: i64,
-    icon_url: Option<String>,
-    #[serde(deserialize_with = "mmod_color_handler")]
-    color: Option<Color>,
-    followers: i64,
-    published: String,
-    updated: String,
-    // gallery: Vec<String>,
-}
-
-struct SearchListing {
-    title: String,
-    description: String,
-    icon_url: Option<String>,
-    project_id: String,
-    author: String,
-    downloads: i64,
-}
-
-#[derive(Debug, serde::Deserialize)]
-struct ModrinthSearchResult {
-    slug: String,
-    title: String,
-    project_id: String,
-    author: String,
-    description: String,
-    versions: Vec<String>,
-    categories: Vec<String>,
-    client_side: String,
-    server_side: String,
-    project_type: String,
-    downloads: i64,
-    icon_url: Option<String>,
-    #[serde(deserialize_with = "mmod_color_handler", default)]
-    color: Option<Color>,
-    follows: i64,
-    date_created: String,
-    date_modified: String,
-    latest_version: String,
-    license: String,
-    gallery: Vec<String>,
-    featured_gallery: Option<String>,
-}
-
-#[derive(Debug, serde::Deserialize)]
-struct ModrinthSearch {
-    hits: Vec<ModrinthSearchResult>,
-    offset: i64,
-    limit: i64,
-    total_hits: i64,
-}
-
-#[derive(Debug, serde::Deserialize)]
-struct ModrinthVersionDownload { // used for the list of versions (and dependencies) when "download" is pressed.
-    name: String,
-    id: String,
-    project_id: String,
-
-    loaders: Vec<String>,
-
-    version_number: String,
-    changelog: String,
-    dependencies: Vec<ModrinthDependency>,
-    version_type: String,
-}
-
-#[derive(Debug, serde::Deserialize)]
-struct ModrinthVersionMass { // used for the list of versions in a mod listing
-    name: String,
-    id: String,
-    loaders: Vec<String>,
-}
-
-#[derive(Clone, Debug)]
-struct ModVersion {
-    name: String,
-    id: String,
-
-    loaders: Vec<ModLoader>,
-}
-impl Display for ModVersion {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}",self.name)
-    }
-}
-impl PartialEq for ModVersion {
-    fn eq(&self, other: &Self) -> bool {
-        self.id == other.id
-    }
-}
-
-struct ModrinthFile {
-
-}
-
 #[derive(Debug)]
 struct ModVersionQueued { // it's a separate struct so it can have a nice icon and stuff
     icon_url: Option<String>,
@@ -1156,6 +1303,8 @@ struct ModVersionQueued { // it's a separate struct so it can have a nice icon a
     version_id: String,
 
     loaders: Vec<ModLoader>,
+    provider: ModProvider,
+    pulled_in_by: Option<String>, // name of the mod that required this one, if it wasn't picked by hand
 }
 impl Display for ModVersionQueued {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -1168,35 +1317,86 @@ impl PartialEq for ModVersionQueued {
     }
 }
 
-#[derive(Debug, serde::Deserialize)]
-struct ModrinthDependency {
-    version_id: Option<String>,
-    project_id: Option<String>,
-    file_name: Option<String>,
-    dependency_type: String,
+/// a popup over the rest of the window. also used by the main window
+pub(crate) fn dialog<'a, M: 'a>(title: &'static str, body: Element<'a, M>, buttons: Element<'a, M>) -> Element<'a, M> {
+    opaque(center(container(column![
+        iced_selection::text(title).size(20).font(Font {weight: font::Weight::Bold, ..Default::default()}),
+        row![space().width(15), body],
+        right(buttons)
+    ].padding(25).spacing(8)
+    ).width(600)
+        .style(|t: &iced::Theme| container::Style {
+            background: Some(deviate(t.palette().background,-0.0).into()),
+            border: Border::default().rounded(10).color(t.extended_palette().background.stronger.color).width(1.5),
+            ..Default::default()
+        })
+    ).center(iced::Fill))
 }
 
-fn mmod_color_handler<'de, D>(deserializer: D) -> Result<Option<Color>, D::Error>
-where
-    D: serde::Deserializer<'de>,
-{
-    let Some(v): Option<i64> = Deserialize::deserialize(deserializer)? else {
-        return Ok(None);
-    };
-
-    let b = (v % 256).try_into().map_err(serde::de::Error::custom)?;
-    let g = ((v >> 8) % 256)
-        .try_into()
-        .map_err(serde::de::Error::custom)?;
-    let r = ((v >> 16) % 256)
-        .try_into()
-        .map_err(serde::de::Error::custom)?;
-
-    Ok(Some(Color::from_rgb8(r, g, b)))
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn incompatible(project_id: &str) -> Dependency {
+        Dependency { version_id: None, project_id: Some(project_id.to_string()), file_name: None, kind: DependencyKind::Incompatible }
+    }
+
+    fn queued(provider: ModProvider, project_id: &str, incompatible: Vec<Dependency>) -> FileDownload {
+        FileDownload {
+            provider,
+            project_id: project_id.to_string(),
+            project_name: project_id.to_string(),
+            version_id: format!("{project_id}-v"),
+            version_name: String::new(),
+            file: None,
+            incompatible,
+            state: FileDownloadState::Waiting,
+        }
+    }
+
+    fn installed(file_name: &str, project_id: Option<&str>, dependencies: Vec<Dependency>) -> InstalledJar {
+        InstalledJar {
+            file_name: file_name.to_string(),
+            version: project_id.map(|p| Version {
+                id: format!("{p}-v"),
+                project_id: p.to_string(),
+                name: String::new(),
+                loaders: vec![],
+                dependencies,
+                files: vec![],
+            }),
+        }
+    }
 
-#[derive(Debug, serde::Deserialize)]
-struct ModrinthCategory {
-    name: String,
-    project_type: String,
+    fn conflicts(files: Vec<FileDownload>, installed: Vec<InstalledJar>) -> Vec<(usize, String)> {
+        let mut data = DownloadVerData { files, installed, conflicts: vec![], started: false };
+        data.find_conflicts();
+        data.conflicts.iter().map(|c| (c.source, match &c.target {
+            ConflictTarget::Queued(j) => format!("queued {j}"),
+            ConflictTarget::Installed(f) => f.clone(),
+        })).collect()
+    }
+
+    #[test]
+    fn installed_jars_conflict_by_project_both_ways() {
+        let found = conflicts(
+            vec![queued(ModProvider::Modrinth, "sodium", vec![incompatible("optifine")]), queued(ModProvider::Modrinth, "lithium", vec![])],
+            vec![
+                // renamed by hand, so only its sha1 gives it away
+                installed("OptiFine_renamed.jar", Some("optifine"), vec![]),
+                installed("old-lithium-fork.jar", Some("fork"), vec![incompatible("lithium")]),
+                installed("unknown.jar", None, vec![]),
+            ],
+        );
+        assert_eq!(found, vec![(0, "OptiFine_renamed.jar".to_string()), (1, "old-lithium-fork.jar".to_string())]);
+    }
+
+    #[test]
+    fn ids_of_other_providers_are_not_compared() {
+        let found = conflicts(
+            vec![queued(ModProvider::Curseforge, "1234", vec![incompatible("optifine")]), queued(ModProvider::Modrinth, "optifine", vec![])],
+            vec![installed("optifine.jar", Some("optifine"), vec![incompatible("1234")])],
+        );
+        assert!(found.is_empty(), "{found:?}");
+    }
 }
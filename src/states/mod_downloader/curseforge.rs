@@ -0,0 +1,327 @@
+use std::collections::HashMap;
+
+use iced::futures::{future::BoxFuture, FutureExt};
+use serde::de::DeserializeOwned;
+
+use crate::{reqwests, CurseforgeConfig, ModLoader};
+use super::{modrinth, provider::{Dependency, DependencyKind, File, Project, Provider, SearchPage, SearchQuery, Version}};
+
+const MINECRAFT_GAME_ID: u32 = 432;
+const CLASS_MODS: u32 = 6;
+const CLASS_BUKKIT_PLUGINS: u32 = 5;
+const CLASS_RESOURCE_PACKS: u32 = 12;
+const CLASS_SHADERS: u32 = 6552;
+
+#[derive(Debug, serde::Deserialize)]
+struct CfResponse<T> {
+    data: T,
+    pagination: Option<CfPagination>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CfPagination {
+    total_count: u64,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CfMod {
+    id: u64,
+    class_id: Option<u32>,
+    name: String,
+    slug: String,
+    summary: String,
+    logo: Option<CfAsset>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CfAsset {
+    thumbnail_url: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CfFile {
+    id: u64,
+    mod_id: u64,
+    display_name: String,
+    file_name: String,
+    download_url: Option<String>,
+    file_length: u64,
+    hashes: Vec<CfHash>,
+    game_versions: Vec<String>,
+    dependencies: Vec<CfDependency>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct CfHash {
+    value: String,
+    algo: u32,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CfDependency {
+    mod_id: u64,
+    relation_type: u32,
+}
+
+/// plugin servers search the bukkit plugins class, everything else searches mods filtered by loader
+fn class_and_loader_type(loader: Option<ModLoader>) -> (u32, Option<u32>) {
+    match loader {
+        Some(ModLoader::Forge) => (CLASS_MODS, Some(1)),
+        Some(ModLoader::Fabric) => (CLASS_MODS, Some(4)),
+        Some(ModLoader::NeoForge) => (CLASS_MODS, Some(6)),
+        Some(ModLoader::Paper | ModLoader::Purpur | ModLoader::Folia | ModLoader::Velocity) => (CLASS_BUKKIT_PLUGINS, None),
+        None => (CLASS_MODS, None),
+    }
+}
+
+fn parse<T: DeserializeOwned>(b: &[u8], what: &str) -> Result<CfResponse<T>, String> {
+    let des = &mut serde_json::Deserializer::from_slice(b);
+    serde_path_to_error::deserialize(des)
+        .map_err(|e| format!("error parsing curseforge {what} at {}: {e}", e.path()))
+}
+
+/// curseforge's api wants a key, which lives in the `[curseforge]` table of the program data
+pub(crate) struct Curseforge(pub(crate) CurseforgeConfig);
+
+impl Provider for Curseforge {
+    fn search(&self, query: SearchQuery) -> BoxFuture<'static, Result<SearchPage, String>> {
+        // curseforge only takes a single loader and game version
+        let (class_id, loader_type) = class_and_loader_type(query.loaders.first().copied());
+        let mut args: Vec<(&'static str, String)> = vec![
+            ("gameId", MINECRAFT_GAME_ID.to_string()),
+            ("classId", class_id.to_string()),
+            ("index", query.offset.to_string()),
+            ("pageSize", "20".to_string()),
+            ("sortField", "2".to_string()), // popularity
+            ("sortOrder", "desc".to_string()),
+        ];
+        if !query.query.is_empty() {
+            args.push(("searchFilter", query.query));
+        }
+        if let Some(t) = loader_type {
+            args.push(("modLoaderType", t.to_string()));
+        }
+        if let Some(v) = query.game_versions.into_iter().next() {
+            args.push(("gameVersion", v));
+        }
+
+        let config = self.0.clone();
+        async move {
+            let b = reqwests::curseforge_get(config, "/v1/mods/search".to_string(), args).await?;
+            let r: CfResponse<Vec<CfMod>> = parse(&b, "search")?;
+            Ok(SearchPage {
+                projects: r.data.into_iter().map(|m| to_project(m, String::new())).collect(),
+                total: r.pagination.map(|p| p.total_count),
+            })
+        }.boxed()
+    }
+
+    fn project(&self, id: String) -> BoxFuture<'static, Result<Project, String>> {
+        let config = self.0.clone();
+        async move {
+            let b = reqwests::curseforge_get(config.clone(), format!("/v1/mods/{id}"), vec![]).await?;
+            let m: CfMod = parse(&b, "mod")?.data;
+            let b = reqwests::curseforge_get(config, format!("/v1/mods/{id}/description"), vec![]).await?;
+            let body: String = parse(&b, "description")?.data;
+            Ok(to_project(m, body))
+        }.boxed()
+    }
+
+    fn versions(&self, project_id: String, loaders: Vec<ModLoader>, game_versions: Vec<String>) -> BoxFuture<'static, Result<Vec<Version>, String>> {
+        let mut args: Vec<(&'static str, String)> = vec![("pageSize", "50".to_string())];
+        if let (_, Some(t)) = class_and_loader_type(loaders.first().copied()) {
+            args.push(("modLoaderType", t.to_string()));
+        }
+        if let Some(v) = game_versions.into_iter().next() {
+            args.push(("gameVersion", v));
+        }
+
+        let config = self.0.clone();
+        async move {
+            let b = reqwests::curseforge_get(config, format!("/v1/mods/{project_id}/files"), args).await?;
+            let files: Vec<CfFile> = parse(&b, "files")?.data;
+            Ok(files.into_iter().map(to_version).collect())
+        }.boxed()
+    }
+
+    fn download_versions(&self, versions: Vec<(String, String)>, _loader: Option<ModLoader>) -> BoxFuture<'static, Result<Vec<Version>, String>> {
+        let config = self.0.clone();
+        async move {
+            let ids: Vec<u64> = versions.iter().map(|(_, id)| id.parse().map_err(|_| format!("{id} isn't a curseforge file id"))).collect::<Result<_, _>>()?;
+            let b = reqwests::curseforge_post(config, "/v1/mods/files".to_string(), serde_json::json!({ "fileIds": ids })).await?;
+            let files: Vec<CfFile> = parse(&b, "files")?.data;
+            Ok(files.into_iter().map(to_version).collect())
+        }.boxed()
+    }
+}
+
+/// a file of a curseforge modpack, resolved to a download
+pub(crate) struct PackFileDownload {
+    pub(crate) file_name: String,
+    pub(crate) url: String,
+    pub(crate) hashes: HashMap<String, String>,
+    pub(crate) manual_url: Option<String>,
+    pub(crate) client_only: bool,
+}
+
+/// looks up every file id of a pack manifest. resource packs, shaders and files only tagged for the client are marked client only
+pub(crate) async fn resolve_pack_files(config: CurseforgeConfig, file_ids: Vec<u64>) -> Result<Vec<PackFileDownload>, String> {
+    let b = reqwests::curseforge_post(config.clone(), "/v1/mods/files".to_string(), serde_json::json!({ "fileIds": file_ids })).await?;
+    let files: Vec<CfFile> = parse(&b, "pack files")?.data;
+    let missing = file_ids.iter().filter(|id| !files.iter().any(|f| f.id == **id)).map(u64::to_string).collect::<Vec<_>>();
+    if !missing.is_empty() {
+        return Err(format!("curseforge doesn't have the pack's files {}", missing.join(", ")));
+    }
+
+    let mut mod_ids = files.iter().map(|f| f.mod_id).collect::<Vec<_>>();
+    mod_ids.sort();
+    mod_ids.dedup();
+    let b = reqwests::curseforge_post(config, "/v1/mods".to_string(), serde_json::json!({ "modIds": mod_ids })).await?;
+    let classes: HashMap<u64, Option<u32>> = parse::<Vec<CfMod>>(&b, "pack projects")?.data.into_iter().map(|m| (m.id, m.class_id)).collect();
+
+    Ok(files.into_iter().map(|f| {
+        let class = classes.get(&f.mod_id).copied().flatten();
+        let tagged_client_only = f.game_versions.iter().any(|v| v == "Client") && !f.game_versions.iter().any(|v| v == "Server");
+        let file = to_version(f).files.into_iter().next().expect("to_version always gives one file");
+        PackFileDownload {
+            client_only: tagged_client_only || matches!(class, Some(CLASS_RESOURCE_PACKS | CLASS_SHADERS)),
+            file_name: file.filename,
+            url: file.url,
+            hashes: file.hashes,
+            manual_url: file.manual_url,
+        }
+    }).collect())
+}
+
+fn to_project(m: CfMod, body: String) -> Project {
+    let section = if m.class_id == Some(CLASS_BUKKIT_PLUGINS) {"bukkit-plugins"} else {"mc-mods"};
+    Project {
+        is_in_selected_mod_list: false,
+        page_url: format!("https://www.curseforge.com/minecraft/{section}/{}", m.slug),
+        id: m.id.to_string(),
+        title: m.name,
+        description: m.summary,
+        body,
+        icon_url: m.logo.map(|l| l.thumbnail_url),
+        color: None,
+    }
+}
+
+fn to_version(f: CfFile) -> Version {
+    let loaders = file_loaders(&f);
+    // authors can opt out of third party downloads, then the api hands back no url and the file has to be fetched by hand
+    let manual_url = f.download_url.is_none().then(|| format!("https://www.curseforge.com/projects/{}", f.mod_id));
+    let hashes: HashMap<String, String> = f.hashes.iter().filter_map(|h| match h.algo {
+        1 => Some(("sha1".to_string(), h.value.clone())),
+        _ => None, // 2 is md5, which we don't check
+    }).collect();
+
+    Version {
+        id: f.id.to_string(),
+        project_id: f.mod_id.to_string(),
+        loaders,
+        dependencies: f.dependencies.iter().map(|d| Dependency {
+            version_id: None,
+            project_id: Some(d.mod_id.to_string()),
+            file_name: None,
+            kind: match d.relation_type {
+                3 => DependencyKind::Required,
+                5 => DependencyKind::Incompatible,
+                1 => DependencyKind::Embedded,
+                _ => DependencyKind::Optional,
+            },
+        }).collect(),
+        files: vec![File {
+            url: f.download_url.unwrap_or_default(),
+            manual_url,
+            filename: f.file_name,
+            primary: true,
+            size: f.file_length,
+            hashes,
+        }],
+        name: f.display_name,
+    }
+}
+
+fn file_loaders(f: &CfFile) -> Vec<ModLoader> {
+    // curseforge mixes loader names into the game versions list
+    f.game_versions.iter()
+        .filter_map(|v| modrinth::parse_loader(&v.to_ascii_lowercase()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::mock_http;
+
+    const FILES: &str = r#"{"data": [
+        {"id": 100, "modId": 1, "displayName": "A 1.0", "fileName": "a-1.0.jar", "downloadUrl": "https://mediafilez.forgecdn.net/files/a-1.0.jar",
+         "fileLength": 5, "hashes": [{"value": "aaaa", "algo": 1}, {"value": "bbbb", "algo": 2}], "gameVersions": ["1.20.1", "Forge"],
+         "dependencies": [{"modId": 2, "relationType": 3}, {"modId": 3, "relationType": 5}]},
+        {"id": 2000, "modId": 2, "displayName": "B 2.0", "fileName": "b-2.0.jar", "downloadUrl": null,
+         "fileLength": 5, "hashes": [], "gameVersions": ["1.20.1", "NeoForge"], "dependencies": []}
+    ]}"#;
+
+    fn provider(base: String) -> Curseforge {
+        Curseforge(CurseforgeConfig { api_key: Some("test-key".to_string()), api_url: Some(base) })
+    }
+
+    #[test]
+    fn download_versions_uses_api_url_and_key() {
+        let (base, requests) = mock_http::serve(|r| (r.method == "POST" && r.path == "/v1/mods/files").then(|| FILES.as_bytes().to_vec()));
+        let versions = tokio::runtime::Runtime::new().unwrap()
+            .block_on(provider(base).download_versions(vec![("1".to_string(), "100".to_string()), ("2".to_string(), "2000".to_string())], None))
+            .unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].header("x-api-key"), Some("test-key"));
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&requests[0].body).unwrap(), serde_json::json!({"fileIds": [100, 2000]}));
+
+        let a = &versions[0];
+        assert_eq!((a.id.as_str(), a.project_id.as_str()), ("100", "1"));
+        assert_eq!(a.loaders, vec![ModLoader::Forge]);
+        assert_eq!(a.files[0].hashes, HashMap::from([("sha1".to_string(), "aaaa".to_string())]));
+        assert_eq!(a.files[0].manual_url, None);
+        assert_eq!(a.dependencies.iter().map(|d| (d.project_id.as_deref(), d.kind)).collect::<Vec<_>>(),
+            vec![(Some("2"), DependencyKind::Required), (Some("3"), DependencyKind::Incompatible)]);
+    }
+
+    #[test]
+    fn opted_out_files_need_a_manual_download() {
+        let (base, _) = mock_http::serve(|r| (r.path == "/v1/mods/files").then(|| FILES.as_bytes().to_vec()));
+        let versions = tokio::runtime::Runtime::new().unwrap()
+            .block_on(provider(base).download_versions(vec![("2".to_string(), "2000".to_string())], None))
+            .unwrap();
+        let b = &versions[1].files[0];
+        assert!(b.url.is_empty(), "{}", b.url);
+        assert_eq!(b.manual_url.as_deref(), Some("https://www.curseforge.com/projects/2"));
+    }
+
+    #[test]
+    fn search_reads_pagination() {
+        let (base, requests) = mock_http::serve(|r| (r.method == "GET" && r.path == "/v1/mods/search").then(|| {
+            br#"{"data": [{"id": 7, "classId": 6, "name": "Seven", "slug": "seven", "summary": "a mod"}], "pagination": {"totalCount": 41}}"#.to_vec()
+        }));
+        let query = SearchQuery { query: "sev".to_string(), loaders: vec![ModLoader::Fabric], ..Default::default() };
+        let page = tokio::runtime::Runtime::new().unwrap().block_on(provider(base).search(query)).unwrap();
+        assert_eq!(page.total, Some(41));
+        assert_eq!(page.projects[0].page_url, "https://www.curseforge.com/minecraft/mc-mods/seven");
+        assert_eq!(requests.lock().unwrap()[0].header("x-api-key"), Some("test-key"));
+    }
+
+    #[test]
+    fn missing_key_fails_before_any_request() {
+        let (base, requests) = mock_http::serve(|_| None);
+        let config = CurseforgeConfig { api_key: None, api_url: Some(base) };
+        let err = tokio::runtime::Runtime::new().unwrap().block_on(Curseforge(config).project("1".to_string())).unwrap_err();
+        assert!(err.contains("API key"), "{err}");
+        assert!(requests.lock().unwrap().is_empty());
+    }
+}
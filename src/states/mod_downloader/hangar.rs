@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+
+use iced::futures::{future::BoxFuture, FutureExt};
+use serde::de::DeserializeOwned;
+
+use crate::{reqwests, ModLoader};
+use super::{modrinth, provider::{File, Project, Provider, SearchPage, SearchQuery, Version}};
+
+// hangar projects are addressed by slug and versions by name, so those double as the ids the downloader passes around
+
+#[derive(Debug, serde::Deserialize)]
+struct HangarPage<T> {
+    pagination: HangarPagination,
+    result: Vec<T>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct HangarPagination {
+    count: u64,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HangarProject {
+    name: String,
+    namespace: HangarNamespace,
+    description: String,
+    avatar_url: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct HangarNamespace {
+    owner: String,
+    slug: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HangarVersion {
+    name: String,
+    downloads: HashMap<String, HangarDownload>,
+    #[serde(default)]
+    platform_dependencies: HashMap<String, Vec<String>>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HangarDownload {
+    file_info: Option<HangarFileInfo>,
+    external_url: Option<String>,
+    download_url: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HangarFileInfo {
+    name: String,
+    size_bytes: u64,
+    sha256_hash: String,
+}
+
+/// hangar's platform names. waterfall has no `ModLoader` of its own so it's never asked for
+fn platform(loader: ModLoader) -> Option<&'static str> {
+    match loader {
+        ModLoader::Paper | ModLoader::Purpur | ModLoader::Folia => Some("PAPER"),
+        ModLoader::Velocity => Some("VELOCITY"),
+        _ => None,
+    }
+}
+
+fn parse<T: DeserializeOwned>(b: &[u8], what: &str) -> Result<T, String> {
+    let des = &mut serde_json::Deserializer::from_slice(b);
+    serde_path_to_error::deserialize(des)
+        .map_err(|e| format!("error parsing hangar {what} at {}: {e}", e.path()))
+}
+
+pub(crate) struct Hangar;
+
+impl Provider for Hangar {
+    fn search(&self, query: SearchQuery) -> BoxFuture<'static, Result<SearchPage, String>> {
+        let mut args: Vec<(&'static str, String)> = vec![
+            ("offset", query.offset.to_string()),
+            ("limit", "20".to_string()),
+        ];
+        if query.query.is_empty() {
+            args.push(("sort", "-downloads".to_string()));
+        } else {
+            args.push(("query", query.query));
+        }
+        if let Some(p) = query.loaders.first().copied().and_then(platform) {
+            args.push(("platform", p.to_string()));
+            // the version filter is ignored by hangar unless a platform is given too
+            if let Some(v) = query.game_versions.into_iter().next() {
+                args.push(("version", v));
+            }
+        }
+
+        async move {
+            let b = reqwests::hangar_get("/projects".to_string(), args).await?;
+            let page: HangarPage<HangarProject> = parse(&b, "search")?;
+            Ok(SearchPage {
+                projects: page.result.into_iter().map(|p| to_project(p, String::new())).collect(),
+                total: Some(page.pagination.count),
+            })
+        }.boxed()
+    }
+
+    fn project(&self, id: String) -> BoxFuture<'static, Result<Project, String>> {
+        async move {
+            let b = reqwests::hangar_get(format!("/projects/{id}"), vec![]).await?;
+            let p: HangarProject = parse(&b, "project")?;
+            // the main page is served as plain markdown
+            let b = reqwests::hangar_get(format!("/pages/main/{id}"), vec![]).await?;
+            Ok(to_project(p, String::from_utf8_lossy(&b).into_owned()))
+        }.boxed()
+    }
+
+    fn versions(&self, project_id: String, loaders: Vec<ModLoader>, game_versions: Vec<String>) -> BoxFuture<'static, Result<Vec<Version>, String>> {
+        let mut args: Vec<(&'static str, String)> = vec![("limit", "25".to_string())];
+        let wanted = loaders.first().copied().and_then(platform);
+        if let Some(p) = wanted {
+            args.push(("platform", p.to_string()));
+            if let Some(v) = game_versions.into_iter().next() {
+                args.push(("platformVersion", v));
+            }
+        }
+
+        async move {
+            let b = reqwests::hangar_get(format!("/projects/{project_id}/versions"), args).await?;
+            let page: HangarPage<HangarVersion> = parse(&b, "versions")?;
+            Ok(page.result.into_iter().map(|v| to_version(&project_id, v, wanted)).collect())
+        }.boxed()
+    }
+
+    fn download_versions(&self, versions: Vec<(String, String)>, loader: Option<ModLoader>) -> BoxFuture<'static, Result<Vec<Version>, String>> {
+        let wanted = loader.and_then(platform);
+        async move {
+            let mut downloads = vec![];
+            for (slug, name) in versions {
+                let b = reqwests::hangar_get(format!("/projects/{slug}/versions/{name}"), vec![]).await?;
+                let v: HangarVersion = parse(&b, "version")?;
+                downloads.push(to_version(&slug, v, wanted));
+            }
+            Ok(downloads)
+        }.boxed()
+    }
+}
+
+fn to_project(p: HangarProject, body: String) -> Project {
+    Project {
+        is_in_selected_mod_list: false,
+        page_url: format!("https://hangar.papermc.io/{}/{}", p.namespace.owner, p.namespace.slug),
+        id: p.namespace.slug.clone(),
+        title: p.name,
+        description: p.description,
+        body,
+        icon_url: p.avatar_url,
+        color: None,
+    }
+}
+
+/// `wanted` picks which platform's jar gets downloaded, falling back to whichever comes first
+fn to_version(slug: &str, v: HangarVersion, wanted: Option<&str>) -> Version {
+    let download = wanted.and_then(|p| v.downloads.get(p)).or_else(|| v.downloads.values().next());
+    let file = download.and_then(|d| Some(match (&d.download_url, &d.file_info) {
+        (Some(url), Some(info)) => File {
+            url: url.clone(),
+            filename: info.name.clone(),
+            primary: true,
+            size: info.size_bytes,
+            hashes: HashMap::from([("sha256".to_string(), info.sha256_hash.clone())]),
+            manual_url: None,
+        },
+        // externally hosted jars come without hashes, so they're handed to the user as a link instead of failing verification
+        _ => {
+            let page = d.external_url.clone().or_else(|| d.download_url.clone())?;
+            File {
+                filename: page.rsplit('/').next().filter(|n| n.ends_with(".jar")).unwrap_or(slug).to_string(),
+                url: String::new(),
+                primary: true,
+                size: 0,
+                hashes: HashMap::new(),
+                manual_url: Some(page),
+            }
+        }
+    }));
+
+    Version {
+        loaders: version_loaders(&v),
+        id: v.name.clone(),
+        project_id: slug.to_string(),
+        dependencies: vec![],
+        files: file.into_iter().collect(),
+        name: v.name,
+    }
+}
+
+fn version_loaders(v: &HangarVersion) -> Vec<ModLoader> {
+    // downloads can also hold external links, so prefer the platforms the version declares
+    let platforms = if v.platform_dependencies.is_empty() {v.downloads.keys().collect::<Vec<_>>()} else {v.platform_dependencies.keys().collect()};
+    platforms.into_iter().filter_map(|p| modrinth::parse_loader(&p.to_ascii_lowercase())).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(downloads: &str) -> HangarVersion {
+        serde_json::from_str(&format!(r#"{{"name": "1.0", "downloads": {downloads}, "platformDependencies": {{"PAPER": ["1.21"], "WATERFALL": ["1.21"]}}}}"#)).unwrap()
+    }
+
+    #[test]
+    fn hosted_downloads_are_verified() {
+        let v = to_version("thing", version(r#"{"PAPER": {"fileInfo": {"name": "thing.jar", "sizeBytes": 3, "sha256Hash": "abc"}, "downloadUrl": "https://hangarcdn.papermc.io/thing.jar"}}"#), Some("PAPER"));
+        let file = &v.files[0];
+        assert_eq!(file.url, "https://hangarcdn.papermc.io/thing.jar");
+        assert_eq!(file.hashes.get("sha256").map(String::as_str), Some("abc"));
+        assert_eq!(file.manual_url, None);
+        // no server here runs waterfall, so only paper is left
+        assert_eq!(v.loaders, vec![ModLoader::Paper]);
+    }
+
+    #[test]
+    fn external_downloads_become_links() {
+        let v = to_version("thing", version(r#"{"WATERFALL": {"externalUrl": "https://github.com/someone/thing/releases"}}"#), Some("WATERFALL"));
+        let file = &v.files[0];
+        assert!(file.url.is_empty());
+        assert_eq!(file.manual_url.as_deref(), Some("https://github.com/someone/thing/releases"));
+        assert_eq!(file.filename, "thing");
+    }
+
+    #[test]
+    fn loaders_map_to_their_platform() {
+        assert_eq!(platform(ModLoader::Velocity), Some("VELOCITY"));
+        assert_eq!(platform(ModLoader::Purpur), Some("PAPER"));
+        assert_eq!(platform(ModLoader::Fabric), None);
+    }
+}
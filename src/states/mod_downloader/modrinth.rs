@@ -1,54 +1,343 @@
+use std::collections::{HashMap, HashSet};
+
+use iced::{Color, futures::{future::BoxFuture, FutureExt}};
 use itertools::Itertools;
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::{reqwests, MinecraftVersion, ModLoader};
+use super::provider::{Dependency, DependencyKind, File, Project, Provider, SearchPage, SearchQuery, Version};
+
+pub(crate) struct Modrinth;
+
+impl Provider for Modrinth {
+    fn search(&self, query: SearchQuery) -> BoxFuture<'static, Result<SearchPage, String>> {
+        let mut facets: Vec<String> = Vec::new();
+        if query.server_side_only { facets.push("\"server_side!=unsupported\"".to_string())}
+        if !query.game_versions.is_empty() {
+            facets.push(query.game_versions.iter().map(|v| format!("\"versions:{v}\"")).join(","));
+        }
+        if !query.loaders.is_empty() {
+            facets.push(query.loaders.iter().map(|l| format!("\"categories:{l}\"")).join(","));
+        }
+        facets.extend(query.categories.iter().map(|c| format!("\"categories:{c}\"")));
+
+        async move {
+            let b = reqwests::search_mods(query.offset, Some(query.query), facets).await?;
+            let search: ModrinthSearch = parse(&b, "search")?;
+            Ok(SearchPage {
+                projects: search.hits.into_iter().map(|h| Project {
+                    is_in_selected_mod_list: false,
+                    page_url: format!("https://modrinth.com/mod/{}", h.slug),
+                    id: h.project_id,
+                    title: h.title,
+                    description: h.description,
+                    body: String::new(),
+                    icon_url: h.icon_url,
+                    color: h.color,
+                }).collect(),
+                total: u64::try_from(search.total_hits).ok(),
+            })
+        }.boxed()
+    }
+
+    fn project(&self, id: String) -> BoxFuture<'static, Result<Project, String>> {
+        async move { Ok(fetch_project(&id).await?.into()) }.boxed()
+    }
+
+    fn versions(&self, project_id: String, loaders: Vec<ModLoader>, game_versions: Vec<String>) -> BoxFuture<'static, Result<Vec<Version>, String>> {
+        let loaders = loaders.iter().map(|l| l.to_string().to_ascii_lowercase()).collect();
+        async move {
+            let (id, b) = reqwests::get_available_mod_versions(project_id, loaders, game_versions).await?;
+            let versions: Vec<ModrinthVersionDownload> = parse(&b, &format!("versions of {id}"))?;
+            Ok(versions.into_iter().map(Version::from).collect())
+        }.boxed()
+    }
+
+    fn download_versions(&self, versions: Vec<(String, String)>, _loader: Option<ModLoader>) -> BoxFuture<'static, Result<Vec<Version>, String>> {
+        async move {
+            let b = reqwests::get_mod_versions(versions.iter().map(|(_, v)| format!("\"{v}\"")).collect()).await?;
+            let result: Vec<ModrinthVersionDownload> = parse(&b, "download versions")?;
+            Ok(result.into_iter().map(Version::from).collect())
+        }.boxed()
+    }
+
+    fn categories(&self) -> BoxFuture<'static, Result<Vec<String>, String>> {
+        async {
+            let b = reqwests::get_categories().await?;
+            let categories: Vec<ModrinthCategory> = parse(&b, "categories")?;
+            Ok(categories.into_iter().filter(|c| c.project_type == "mod").map(|c| c.name).collect())
+        }.boxed()
+    }
+}
+
+fn parse<T: DeserializeOwned>(b: &[u8], what: &str) -> Result<T, String> {
+    let des = &mut serde_json::Deserializer::from_slice(b);
+    serde_path_to_error::deserialize(des)
+        .map_err(|e| format!("error parsing modrinth {what} at {}: {e}", e.path()))
+}
+
+#[derive(Debug, Clone)]
+pub struct ResolvedDependency {
+    pub(crate) project: Project,
+    pub(crate) version: Version,
+    pub(crate) pulled_in_by: String,
+}
+
+pub(super) fn parse_loader(s: &str) -> Option<ModLoader> {
+    match s {
+        "fabric" => Some(ModLoader::Fabric),
+        "neoforge" => Some(ModLoader::NeoForge),
+        "forge" => Some(ModLoader::Forge),
+        "paper" => Some(ModLoader::Paper),
+        "purpur" => Some(ModLoader::Purpur),
+        "folia" => Some(ModLoader::Folia),
+        "velocity" => Some(ModLoader::Velocity),
+        _ => None
+    }
+}
+
+/// the modrinth loader tags a server running `loader` can load. paper forks also run plain bukkit/spigot plugins
+pub(crate) fn compatible_loader_tags(loader: ModLoader) -> Vec<String> {
+    match loader {
+        ModLoader::Paper => vec!["paper", "spigot", "bukkit"],
+        ModLoader::Purpur => vec!["purpur", "paper", "spigot", "bukkit"],
+        l => return vec![l.to_string().to_ascii_lowercase()],
+    }.into_iter().map(str::to_string).collect()
+}
+
+/// the versions files with these sha1 hashes belong to, keyed by hash. files modrinth doesn't know are left out
+pub(crate) async fn versions_from_sha1s(hashes: Vec<String>) -> Result<HashMap<String, Version>, String> {
+    if hashes.is_empty() {return Ok(HashMap::new())}
+    let b = reqwests::get_versions_from_hashes(hashes, "sha1").await?;
+    let versions: HashMap<String, ModrinthVersionDownload> = parse(&b, "versions of installed files")?;
+    Ok(versions.into_iter().map(|(hash, v)| (hash, Version::from(v))).collect())
+}
+
+/// walks the `required` dependencies of `root_version_id` (and their dependencies, and so on),
+/// picking the newest version of each that runs on `loader` and `game_version`. a pinned version is used if it runs there too.
+/// projects in `seen_projects` are assumed to already be taken care of and get skipped
+pub(crate) async fn resolve_required_dependencies(
+    root_version_id: String,
+    root_name: String,
+    loader: ModLoader,
+    game_version: MinecraftVersion,
+    mut seen_projects: HashSet<String>,
+) -> Result<Vec<ResolvedDependency>, String> {
+    let mut resolved = vec![];
+    let mut stack = vec![(root_version_id, root_name)];
+
+    while let Some((version_id, name)) = stack.pop() {
+        let version = fetch_version(&version_id).await?;
+        for dep in version.dependencies.iter().filter(|d| d.dependency_type == "required") {
+            if dep.project_id.as_ref().is_some_and(|p| seen_projects.contains(p)) {continue}
+            let pinned = match &dep.version_id {
+                Some(id) => Some(fetch_version(id).await?),
+                None => None,
+            };
+            let Some(project_id) = dep.project_id.clone().or_else(|| pinned.as_ref().map(|v| v.project_id.clone())) else {continue};
+            if seen_projects.contains(&project_id) {continue}
+            let dep_version = match pinned {
+                Some(v) if _runs_on(&v, loader, &game_version) => v,
+                _ => {
+                    let Some(v) = fetch_compatible_versions(&project_id, loader, &game_version).await?.into_iter().next() else {
+                        return Err(format!("{name} requires {project_id}, but it has no version for {loader} {game_version}"));
+                    };
+                    v
+                }
+            };
+            if !seen_projects.insert(dep_version.project_id.clone()) {continue}
+
+            let project = fetch_project(&dep_version.project_id).await?;
+            stack.push((dep_version.id.clone(), project.title.clone()));
+            resolved.push(ResolvedDependency {
+                project: project.into(),
+                version: dep_version.into(),
+                pulled_in_by: name.clone(),
+            });
+        }
+    }
+    Ok(resolved)
+}
+
+fn _runs_on(v: &ModrinthVersionDownload, loader: ModLoader, game_version: &MinecraftVersion) -> bool {
+    let tags = compatible_loader_tags(loader);
+    v.loaders.iter().any(|l| tags.contains(l)) && v.game_versions.contains(&game_version.id)
+}
+
+async fn fetch_version(id: &str) -> Result<ModrinthVersionDownload, String> {
+    let b = reqwests::get_mod_versions(vec![format!("\"{id}\"")]).await?;
+    let versions: Vec<ModrinthVersionDownload> = parse(&b, &format!("version {id}"))?;
+    versions.into_iter().next().ok_or_else(|| format!("version {id} doesn't exist"))
+}
+
+pub(super) async fn fetch_compatible_versions(project_id: &str, loader: ModLoader, game_version: &MinecraftVersion) -> Result<Vec<ModrinthVersionDownload>, String> {
+    let (_, b) = reqwests::get_available_mod_versions(project_id.to_string(), compatible_loader_tags(loader), vec![game_version.id.clone()]).await?;
+    parse(&b, &format!("versions of {project_id}"))
+}
+
+async fn fetch_project(id: &str) -> Result<ModrinthMod, String> {
+    let b = reqwests::fetch_mod(id.to_string()).await?;
+    parse(&b, &format!("project {id}"))
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub(crate) struct ModrinthMod {
+    pub(crate) slug: String,
+    pub(crate) title: String,
+    pub(crate) id: String,
+    pub(crate) body: String,
+    pub(crate) description: String,
+    pub(crate) versions: Vec<String>,
+    pub(crate) game_versions: Vec<String>,
+    pub(crate) loaders: Vec<String>,
+    pub(crate) categories: Vec<String>,
+    pub(crate) additional_categories: Vec<String>,
+    pub(crate) issues_url: Option<String>,
+    pub(crate) source_url: Option<String>,
+    pub(crate) wiki_url: Option<String>,
+    pub(crate) discord_url: Option<String>,
+    pub(crate) client_side: String,
+    pub(crate) server_side: String,
+    pub(crate) project_type: String,
+    pub(crate) downloads: i64,
+    pub(crate) icon_url: Option<String>,
+    #[serde(deserialize_with = "mmod_color_handler")]
+    pub(crate) color: Option<Color>,
+    pub(crate) followers: i64,
+    pub(crate) published: String,
+    pub(crate) updated: String,
+    // gallery: Vec<String>,
+}
+impl From<ModrinthMod> for Project {
+    fn from(m: ModrinthMod) -> Self {
+        Project {
+            is_in_selected_mod_list: false,
+            page_url: format!("https://modrinth.com/mod/{}", m.slug),
+            id: m.id,
+            title: m.title,
+            description: m.description,
+            body: m.body,
+            icon_url: m.icon_url,
+            color: m.color,
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ModrinthSearchResult {
+    slug: String,
+    title: String,
+    project_id: String,
+    description: String,
+    icon_url: Option<String>,
+    #[serde(deserialize_with = "mmod_color_handler", default)]
+    color: Option<Color>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ModrinthSearch {
+    hits: Vec<ModrinthSearchResult>,
+    total_hits: i64,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub(crate) struct ModrinthVersionDownload { // used for the list of versions (and dependencies) when "download" is pressed.
+    pub(crate) name: String,
+    pub(crate) id: String,
+    pub(crate) project_id: String,
+
+    pub(crate) loaders: Vec<String>,
+    pub(crate) game_versions: Vec<String>,
 
-pub fn search_recieved(data: Vec<u8>) {
-    
-}
-
-pub async fn search_mods(
-    offset: u64,
-    query: Option<String>,
-    facets: Vec<String>,
-    sequence_number: usize,
-) -> (usize, Result<Vec<u8>, String>) {
-    (sequence_number, (|| async { // closure hackiness to get the ? operator to work when we need to bundle the sequence_number
-        let mut args: Vec<(&'static str, String)> =
-            vec![("limit", "20".to_string()), ("offset", offset.to_string())];
-
-        if let Some(q) = query {
-            args.push(("query", q))
-        };
-
-        args.push((
-            "facets",
-            format!(
-                "[{}]",
-                facets.iter()
-                    .map(|f| format!("[{f}]"))
-                    .join(",")
-            ),
-        ));
-
-        let get = crate::REQ_CLIENT
-            .get("https://api.modrinth.com/v2/search")
-            .query(&args);
-
-        let response = get.send().await.map_err(|err| err.to_string())?;
-
-        println!("{}", response.url());
-
-        if !response.status().is_success() {
-            Err(format!(
-                "Error {} from search with args {:?}",
-                response.status(),
-                args
-            ))
-        } else {
-            let bytes = response
-                .bytes()
-                .await
-                .map_err(|err| err.to_string())?
-                .to_vec();
-            Ok(bytes)
+    pub(crate) version_number: String,
+    pub(crate) changelog: Option<String>,
+    pub(crate) dependencies: Vec<ModrinthDependency>,
+    pub(crate) files: Vec<ModrinthFile>,
+}
+impl From<ModrinthVersionDownload> for Version {
+    fn from(v: ModrinthVersionDownload) -> Self {
+        Version {
+            loaders: v.loaders.iter().filter_map(|l| parse_loader(l)).collect(),
+            dependencies: v.dependencies.into_iter().map(|d| Dependency {
+                kind: match d.dependency_type.as_str() {
+                    "required" => DependencyKind::Required,
+                    "incompatible" => DependencyKind::Incompatible,
+                    "embedded" => DependencyKind::Embedded,
+                    _ => DependencyKind::Optional,
+                },
+                version_id: d.version_id,
+                project_id: d.project_id,
+                file_name: d.file_name,
+            }).collect(),
+            files: v.files.into_iter().map(|f| File {
+                url: f.url,
+                filename: f.filename,
+                primary: f.primary,
+                size: f.size,
+                hashes: f.hashes,
+                manual_url: None,
+            }).collect(),
+            id: v.id,
+            project_id: v.project_id,
+            name: v.name,
         }
-    })().await)
-}
\ No newline at end of file
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub(crate) struct ModrinthFile {
+    pub(crate) url: String,
+    pub(crate) filename: String,
+    pub(crate) primary: bool,
+    pub(crate) size: u64,
+    pub(crate) hashes: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub(crate) struct ModrinthDependency {
+    pub(crate) version_id: Option<String>,
+    pub(crate) project_id: Option<String>,
+    pub(crate) file_name: Option<String>,
+    pub(crate) dependency_type: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ModrinthCategory {
+    name: String,
+    project_type: String,
+}
+
+fn mmod_color_handler<'de, D>(deserializer: D) -> Result<Option<Color>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let Some(v): Option<i64> = Deserialize::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+
+    let b = (v % 256).try_into().map_err(serde::de::Error::custom)?;
+    let g = ((v >> 8) % 256)
+        .try_into()
+        .map_err(serde::de::Error::custom)?;
+    let r = ((v >> 16) % 256)
+        .try_into()
+        .map_err(serde::de::Error::custom)?;
+
+    Ok(Some(Color::from_rgb8(r, g, b)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VersionKind;
+
+    #[test]
+    fn pinned_versions_have_to_run_on_the_server() {
+        let v: ModrinthVersionDownload = serde_json::from_str(r#"{"name": "1.0", "id": "v", "project_id": "p", "loaders": ["bukkit"], "game_versions": ["1.20.1"],
+            "version_number": "1.0", "changelog": null, "dependencies": [], "files": []}"#).unwrap();
+        let version = |id: &str| MinecraftVersion { id: id.to_string(), kind: VersionKind::Release };
+        assert!(_runs_on(&v, ModLoader::Purpur, &version("1.20.1")));
+        assert!(!_runs_on(&v, ModLoader::Purpur, &version("1.21")));
+        assert!(!_runs_on(&v, ModLoader::Fabric, &version("1.20.1")));
+    }
+}
@@ -0,0 +1,118 @@
+use std::{collections::HashMap, fmt::Display};
+
+use iced::{Color, futures::{future::BoxFuture, FutureExt}};
+
+use crate::{CurseforgeConfig, ModLoader, ModProvider};
+use super::{curseforge::Curseforge, hangar::Hangar, modrinth::Modrinth};
+
+// everything the downloader draws comes through these, so a new mod site only needs a `Provider` impl
+
+#[derive(Debug, Clone)]
+pub(crate) struct Project {
+    pub(crate) is_in_selected_mod_list: bool,
+
+    pub(crate) id: String,
+    pub(crate) title: String,
+    pub(crate) description: String,
+    pub(crate) body: String, // markdown/html. empty for search hits
+    pub(crate) icon_url: Option<String>,
+    pub(crate) color: Option<Color>,
+    pub(crate) page_url: String,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct SearchPage {
+    pub(crate) projects: Vec<Project>,
+    pub(crate) total: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Version {
+    pub(crate) id: String,
+    pub(crate) project_id: String,
+    pub(crate) name: String,
+    pub(crate) loaders: Vec<ModLoader>,
+    pub(crate) dependencies: Vec<Dependency>,
+    pub(crate) files: Vec<File>,
+}
+impl Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct File {
+    pub(crate) url: String,
+    pub(crate) filename: String,
+    pub(crate) primary: bool,
+    pub(crate) size: u64,
+    pub(crate) hashes: HashMap<String, String>,
+    pub(crate) manual_url: Option<String>, // the page to get the file from by hand when the author doesn't allow it to be downloaded here. `url` is empty then
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Dependency {
+    pub(crate) version_id: Option<String>,
+    pub(crate) project_id: Option<String>,
+    pub(crate) file_name: Option<String>,
+    pub(crate) kind: DependencyKind,
+}
+
+impl Dependency {
+    pub(crate) fn points_at(&self, project_id: &str, version_id: &str) -> bool {
+        self.project_id.as_deref() == Some(project_id) || self.version_id.as_deref() == Some(version_id)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum DependencyKind {
+    Required,
+    Optional,
+    Incompatible,
+    Embedded,
+}
+
+/// what the search bar and filters currently ask for. providers ignore the parts they can't filter by
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SearchQuery {
+    pub(crate) query: String,
+    pub(crate) offset: u64,
+    pub(crate) loaders: Vec<ModLoader>,
+    pub(crate) game_versions: Vec<String>,
+    pub(crate) categories: Vec<String>,
+    pub(crate) server_side_only: bool,
+}
+
+pub(crate) trait Provider {
+    fn search(&self, query: SearchQuery) -> BoxFuture<'static, Result<SearchPage, String>>;
+
+    /// the full project, with its body
+    fn project(&self, id: String) -> BoxFuture<'static, Result<Project, String>>;
+
+    /// versions of `project_id` for the version picker, newest first
+    fn versions(&self, project_id: String, loaders: Vec<ModLoader>, game_versions: Vec<String>) -> BoxFuture<'static, Result<Vec<Version>, String>>;
+
+    /// full versions for `(project_id, version_id)` pairs, right before they get downloaded.
+    /// `loader` is the server's, for providers that ship a different jar per platform
+    fn download_versions(&self, versions: Vec<(String, String)>, loader: Option<ModLoader>) -> BoxFuture<'static, Result<Vec<Version>, String>>;
+
+    fn categories(&self) -> BoxFuture<'static, Result<Vec<String>, String>> {
+        async { Ok(vec![]) }.boxed()
+    }
+}
+
+impl ModProvider {
+    pub(crate) fn backend(self, curseforge: &CurseforgeConfig) -> Box<dyn Provider> {
+        match self {
+            ModProvider::Modrinth => Box::new(Modrinth),
+            ModProvider::Curseforge => Box::new(Curseforge(curseforge.clone())),
+            ModProvider::Hangar => Box::new(Hangar),
+        }
+    }
+}
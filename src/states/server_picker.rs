@@ -0,0 +1,122 @@
+use std::{env, path::PathBuf};
+
+use iced::{
+    Alignment::Center, Color, Element, Length::Fill, Task, widget::{button, column, container, row, scrollable, space, text, text_input}
+};
+
+use crate::{Message, ProgramData, bold, loader_icon};
+use crate::util::server_registry::ServerRegistry;
+
+#[derive(Clone, Debug)]
+pub enum PickerMessage {
+    PathTyped(String),
+    AddPressed,
+    RemovePressed(PathBuf),
+    OpenPressed(PathBuf),
+}
+
+/// the first window: every server folder opened before, plus a box to add another
+pub struct PickerState {
+    registry: ServerRegistry,
+    program_data: Vec<Result<Option<ProgramData>, String>>, // for each entry of the registry, read once when the picker opens
+    new_path: String,
+    error: String,
+}
+impl Default for PickerState {
+    fn default() -> Self {
+        let mut registry = ServerRegistry::load();
+        // relaunching from a server folder still finds it, even if it was set up before the registry existed
+        if let Ok(cwd) = env::current_dir() && cwd.join(".mcservermodgui").is_dir() && !registry.servers.iter().any(|s| s.root == cwd) {
+            registry.touch(&cwd);
+        }
+        PickerState {
+            program_data: registry.servers.iter().map(|s| s.program_data()).collect(),
+            registry,
+            new_path: env::current_dir().map(|p| p.display().to_string()).unwrap_or_default(),
+            error: String::new(),
+        }
+    }
+}
+impl PickerState {
+    pub fn update(&mut self, message: PickerMessage) -> Task<Message> {
+        match message {
+            PickerMessage::PathTyped(s) => self.new_path = s,
+            PickerMessage::AddPressed => {
+                let path = PathBuf::from(self.new_path.trim());
+                if !path.is_dir() {
+                    self.error = format!("{} isn't a folder", path.display());
+                    return Task::none();
+                }
+                let path = path.canonicalize().unwrap_or(path);
+                return Task::done(Message::PickerMessage(PickerMessage::OpenPressed(path)));
+            }
+            PickerMessage::RemovePressed(root) => {
+                // only forgets it, the folder itself is left alone
+                if let Some(i) = self.registry.servers.iter().position(|s| s.root == root) {
+                    self.program_data.remove(i);
+                }
+                self.registry.remove(&root);
+                if let Err(err) = self.registry.save() {
+                    eprintln!("{err}");
+                    self.error = err;
+                }
+            }
+            PickerMessage::OpenPressed(_) => unreachable!(), // handled by the app, which swaps this window for init
+        }
+        Task::none()
+    }
+
+    pub fn couldnt_open(&mut self, err: String) {
+        self.error = err;
+    }
+
+    /// remembers `root` as the most recently opened server
+    pub fn opened(&mut self, root: &std::path::Path) {
+        self.registry.touch(root);
+        if let Err(err) = self.registry.save() {
+            eprintln!("{err}");
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, PickerMessage> {
+        let gray = Color::from_rgb8(150, 150, 150);
+        let servers: Element<PickerMessage> = if self.registry.servers.is_empty() {
+            text("No servers yet. Add a folder below, an empty one to set up a new server").color(gray).into()
+        } else {
+            scrollable(column(self.registry.servers.iter().zip(&self.program_data).map(|(entry, data)| {
+                let missing = !entry.root.is_dir();
+                let folder_name = || entry.root.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                let (name, details): (String, String) = match data {
+                    Ok(Some(d)) => (d.name.clone(), format!("{} {}", d.loader, d.version)),
+                    Ok(None) if missing => (folder_name(), "folder not found".to_string()),
+                    Ok(None) => (folder_name(), "not set up yet".to_string()),
+                    Err(err) => (folder_name(), err.clone()),
+                };
+                container(row![
+                    match data {
+                        Ok(Some(d)) => Element::from(loader_icon(d.loader).width(30).height(30)),
+                        _ => space().width(30).into(),
+                    },
+                    column![
+                        bold(name),
+                        text(details).size(13).color(gray),
+                        text(entry.root.display().to_string()).size(12).color(gray),
+                    ].spacing(2).width(Fill),
+                    button("Remove").on_press(PickerMessage::RemovePressed(entry.root.clone())).style(button::secondary),
+                    button("Open").on_press_maybe((!missing && data.is_ok()).then(|| PickerMessage::OpenPressed(entry.root.clone()))),
+                ].spacing(10).align_y(Center)).padding(8).style(container::bordered_box).into()
+            })).spacing(6)).height(Fill).spacing(5).into()
+        };
+
+        container(column![
+            text("Servers").size(30),
+            servers,
+            text(&self.error).color(Color::from_rgb8(200, 0, 0)),
+            row![
+                text_input("Server folder", &self.new_path).on_input(PickerMessage::PathTyped).on_submit(PickerMessage::AddPressed),
+                button("Open folder").on_press(PickerMessage::AddPressed),
+            ].spacing(10).align_y(Center),
+        ].max_width(600).spacing(10)).padding(40).center_x(Fill)
+        .into()
+    }
+}
@@ -1,14 +1,15 @@
-use std::{sync::OnceLock, time::Duration};
+use std::{path::PathBuf, sync::OnceLock, time::Duration};
 use std::iter::Iterator;
 use std::sync::LazyLock;
 use iced::{
-    Alignment::Center, Border, Color, Element, Font, Length::Fill, Task, Theme, alignment::Horizontal::Right, widget::{Space, button, checkbox, column, container, pick_list, right, row, text, text_input}
+    Alignment::Center, Border, Color, Element, Font, Length::Fill, Task, widget::{Space, button, checkbox, column, container, pick_list, progress_bar, rich_text, right, row, scrollable, span, text, text_input}
 };
 use iced::advanced::svg::Svg;
 use strum::{IntoEnumIterator, VariantArray};
 
-use crate::{Message, MinecraftVersion, ModLoader, ProgramData, VersionKind, circular::Circular, MC_VERSIONS, STATIC_IMAGES, SVG_MOD_LOADERS};
+use crate::{CurseforgeConfig, Message, MinecraftVersion, ModLoader, ProgramData, VersionKind, circular::Circular, MC_VERSIONS, SVG_MOD_LOADERS};
 use crate::util::icon_pick_list::icon_pick_list;
+use crate::util::{java::{self, JavaRuntime}, modpack::{self, Modpack, PackEvent}, reqwests::{self, DownloadEvent}, server_installer::{self, InstallPlan, InstallerEvent}};
 
 #[derive(Clone, Debug)]
 pub enum SetupMessage {
@@ -16,11 +17,38 @@ pub enum SetupMessage {
     McVersionSelected(MinecraftVersion),
     ShowSnapshotsToggled(bool),
     LoaderSelected(ModLoader),
+    InstallServerToggled(bool),
+    EulaToggled(bool),
+    OpenLink(String),
     DoneButtonPressed,
+    InstallPlanned(Result<InstallPlan, String>),
+    InstallDownloadEvent(usize, DownloadEvent),
+    InstallerEvent(InstallerEvent),
+    SkipInstallPressed,
+    JavaRuntimesFound(Vec<JavaRuntime>),
+    JavaRequirementFound(String, Result<u32, String>),
+    JavaSelected(JavaRuntime),
+    PackPathTyped(String),
+    CurseforgeKeyTyped(String),
+    LoadPackPressed,
+    PackLoaded(Result<Modpack, String>),
+    ClearPackPressed,
+    PackEvent(PackEvent),
     DoNothing,
     SetupConcluded,
 }
 
+#[derive(Default)]
+enum InstallPhase {
+    #[default]
+    NotStarted,
+    Planning,
+    Downloading { plan: InstallPlan, current: usize, downloaded: u64, total: Option<u64> },
+    RunningInstaller { installer: String },
+    InstallingPack { server_jar: Option<String>, progress: PackEvent },
+    Failed(String),
+}
+
 #[derive(Default)]
 pub struct SetupState {
     pub name: String,
@@ -30,15 +58,37 @@ pub struct SetupState {
     version: String,
     error: String,
 
+    pub skip_install: bool,
+    eula_accepted: bool,
+    install_phase: InstallPhase,
+    install_log: Vec<String>,
+
+    java_runtimes: Option<Vec<JavaRuntime>>, // None while still looking
+    required_java: Option<Result<u32, String>>, // for the selected version
+    selected_java: Option<JavaRuntime>,
+
+    pack_path: String,
+    modpack: Option<Modpack>, // installed over the server once it's in place
+    is_loading_pack: bool,
+    curseforge: CurseforgeConfig, // needed to look up the files of curseforge packs, and kept for the mod downloader
+
+    pub root: PathBuf, // the server folder being set up
     pub program_data: OnceLock<ProgramData>,
 }
 impl SetupState {
     pub fn update(&mut self, _message: SetupMessage) -> Task<Message> {
         match _message {
             SetupMessage::NameTyped(s) => self.name = s,
-            SetupMessage::McVersionSelected(s) => self.selected_version = Some(s),
+            SetupMessage::McVersionSelected(s) => {
+                if self.selected_version.as_ref() == Some(&s) {return Task::none()}
+                self.selected_version = Some(s);
+                return self.find_java_requirement();
+            }
             SetupMessage::ShowSnapshotsToggled(b) => self.show_snapshots = b,
             SetupMessage::LoaderSelected(s) => self.selected_loader = Some(s),
+            SetupMessage::InstallServerToggled(b) => self.skip_install = !b,
+            SetupMessage::EulaToggled(b) => self.eula_accepted = b,
+            SetupMessage::OpenLink(url) => return Task::done(Message::OpenLink(url)),
             SetupMessage::DoneButtonPressed => {
                 if self.name.trim().is_empty() {
                     self.error = "Enter a name".to_string();
@@ -58,17 +108,228 @@ impl SetupState {
                 //         return  Task::none()
                 //     }
                 // };
-                self.program_data.set(ProgramData {
-                    name: self.name.clone(),
-                    loader,
-                    version
-                }).expect("Attempted to write to program_data twice");
-                return Task::done(Message::SetupMessage(SetupMessage::SetupConcluded))
+                if self.skip_install {
+                    return self._conclude(None);
+                }
+                if loader != ModLoader::Velocity && !self.eula_accepted {
+                    self.error = "Accept the Minecraft EULA to install the server".to_string();
+                    return Task::none();
+                }
+                self.error.clear();
+                self.install_log.clear();
+                self.install_phase = InstallPhase::Planning;
+                // a pack is built against one loader build, so that one gets installed unless something else was picked since
+                let loader_version = self.modpack.as_ref()
+                    .filter(|p| p.loader == loader && p.minecraft == version.id)
+                    .and_then(|p| p.loader_version.clone());
+                return Task::perform(server_installer::plan_install(loader, version, loader_version), |r| Message::SetupMessage(SetupMessage::InstallPlanned(r)));
             },
+            SetupMessage::InstallPlanned(res) => match res {
+                Ok(plan) => {
+                    self.install_phase = InstallPhase::Downloading { plan, current: 0, downloaded: 0, total: None };
+                    return self._download_jar(0);
+                }
+                Err(err) => {
+                    eprintln!("Couldn't find server software: {err}");
+                    self.install_phase = InstallPhase::Failed(err);
+                }
+            }
+            SetupMessage::InstallDownloadEvent(i, event) => {
+                let InstallPhase::Downloading { plan, current, downloaded, total } = &mut self.install_phase else {return Task::none()};
+                if i != *current {return Task::none()}
+                match event {
+                    DownloadEvent::Progressed { downloaded: d, total: t } => {
+                        *downloaded = d;
+                        *total = t;
+                    }
+                    DownloadEvent::Finished(Ok(_)) => {
+                        if i + 1 < plan.jars.len() {
+                            *current = i + 1;
+                            *downloaded = 0;
+                            *total = None;
+                            return self._download_jar(i + 1);
+                        }
+                        let launch_jar = plan.launch_jar.clone();
+                        if self.selected_loader != Some(ModLoader::Velocity) && let Err(err) = server_installer::write_eula(&self.root) {
+                            self.install_phase = InstallPhase::Failed(err);
+                            return Task::none();
+                        }
+                        if let Some(installer) = plan.installer.clone() {
+                            self.install_phase = InstallPhase::RunningInstaller { installer: installer.clone() };
+                            return Task::run(
+                                server_installer::run_installer(
+                                    self.root.clone(),
+                                    installer,
+                                    self.selected_java.as_ref().map_or_else(|| "java".to_string(), |j| j.path.clone()),
+                                    self.selected_version.as_ref().map(|v| v.id.clone()).unwrap_or_default(),
+                                ),
+                                |e| Message::SetupMessage(SetupMessage::InstallerEvent(e))
+                            );
+                        }
+                        return self._conclude(launch_jar);
+                    }
+                    DownloadEvent::Finished(Err(err)) => {
+                        eprintln!("Couldn't install server: {err}");
+                        self.install_phase = InstallPhase::Failed(err);
+                    }
+                }
+            }
+            SetupMessage::InstallerEvent(event) => {
+                let InstallPhase::RunningInstaller { .. } = &self.install_phase else {return Task::none()};
+                match event {
+                    InstallerEvent::Output(line) => self.install_log.push(line),
+                    InstallerEvent::Finished(Ok(launch_jar)) => return self._conclude(Some(launch_jar)),
+                    InstallerEvent::Finished(Err(err)) => {
+                        eprintln!("Couldn't install server: {err}");
+                        self.install_phase = InstallPhase::Failed(err);
+                    }
+                }
+            }
+            SetupMessage::SkipInstallPressed => return self._conclude(None),
+            SetupMessage::JavaRuntimesFound(runtimes) => {
+                self.java_runtimes = Some(runtimes);
+                self._pick_java();
+            }
+            SetupMessage::JavaRequirementFound(id, res) => {
+                // the version could have been changed again while this was loading
+                if self.selected_version.as_ref().map(|v| &v.id) != Some(&id) {return Task::none()}
+                if let Err(err) = &res {
+                    eprintln!("Couldn't find which java {id} needs: {err}");
+                }
+                self.required_java = Some(res);
+                self._pick_java();
+            }
+            SetupMessage::JavaSelected(j) => self.selected_java = Some(j),
+            SetupMessage::PackPathTyped(s) => self.pack_path = s,
+            SetupMessage::CurseforgeKeyTyped(s) => self.curseforge.api_key = Some(s).filter(|s| !s.trim().is_empty()),
+            SetupMessage::LoadPackPressed => {
+                self.is_loading_pack = true;
+                self.error.clear();
+                return Task::perform(
+                    modpack::load(PathBuf::from(self.pack_path.trim()), self.curseforge.clone()),
+                    |r| Message::SetupMessage(SetupMessage::PackLoaded(r))
+                );
+            }
+            SetupMessage::PackLoaded(res) => {
+                self.is_loading_pack = false;
+                let pack = match res {
+                    Ok(p) => p,
+                    Err(err) => {
+                        eprintln!("{err}");
+                        self.error = err;
+                        return Task::none();
+                    }
+                };
+                let Some(version) = MC_VERSIONS.get().unwrap().iter().find(|v| v.id == pack.minecraft).cloned() else {
+                    self.error = format!("{} is for Minecraft {}, which isn't a known version", pack.name, pack.minecraft);
+                    return Task::none();
+                };
+                self.error.clear();
+                if self.name.trim().is_empty() {
+                    self.name = pack.name.clone();
+                }
+                self.selected_loader = Some(pack.loader);
+                self.show_snapshots |= version.kind != VersionKind::Release;
+                self.modpack = Some(pack);
+                return self.update(SetupMessage::McVersionSelected(version));
+            }
+            SetupMessage::ClearPackPressed => self.modpack = None,
+            SetupMessage::PackEvent(event) => {
+                let InstallPhase::InstallingPack { server_jar, progress } = &mut self.install_phase else {return Task::none()};
+                match event {
+                    PackEvent::Finished(Ok(())) => {
+                        let server_jar = server_jar.clone();
+                        self.modpack = None;
+                        return self._conclude(server_jar);
+                    }
+                    PackEvent::Finished(Err(err)) => {
+                        eprintln!("Couldn't install modpack: {err}");
+                        self.install_phase = InstallPhase::Failed(err);
+                    }
+                    event => *progress = event,
+                }
+            }
             SetupMessage::SetupConcluded | SetupMessage::DoNothing => ()
         }
         Task::none()
     }
+
+    /// looks for installed java and, if a version is already selected, what it needs
+    pub fn detect_java(&mut self) -> Task<Message> {
+        Task::batch([
+            Task::perform(java::discover_in_background(), |r| Message::SetupMessage(SetupMessage::JavaRuntimesFound(r))),
+            self.find_java_requirement(),
+        ])
+    }
+
+    fn find_java_requirement(&mut self) -> Task<Message> {
+        self.required_java = None;
+        let Some(version) = &self.selected_version else {return Task::none()};
+        let id = version.id.clone();
+        Task::perform(async move {
+            let res = java::required_major(&id).await;
+            (id, res)
+        }, |(id, res)| Message::SetupMessage(SetupMessage::JavaRequirementFound(id, res)))
+    }
+
+    /// keeps the selected java if it can run the version, otherwise picks one that can. exact matches go first
+    fn _pick_java(&mut self) {
+        let (Some(runtimes), Some(Ok(required))) = (&self.java_runtimes, &self.required_java) else {
+            if self.selected_java.is_none() {
+                self.selected_java = self.java_runtimes.as_ref().and_then(|r| r.first().cloned());
+            }
+            return;
+        };
+        if self.selected_java.as_ref().is_some_and(|j| j.runs(*required)) {return}
+        self.selected_java = runtimes.iter().find(|j| j.major == *required)
+            .or_else(|| runtimes.iter().find(|j| j.runs(*required)))
+            .or(self.selected_java.as_ref())
+            .cloned();
+    }
+
+    /// builds the program data, saves it next to the server and hands over to the main window. a loaded modpack is installed first
+    fn _conclude(&mut self, server_jar: Option<String>) -> Task<Message> {
+        let (Some(loader), Some(version)) = (self.selected_loader, self.selected_version.clone()) else {return Task::none()};
+        if let Some(pack) = self.modpack.clone() {
+            self.error.clear();
+            self.install_phase = InstallPhase::InstallingPack {
+                server_jar,
+                progress: PackEvent::Downloading { current: 0, total: pack.files.len(), path: String::new() },
+            };
+            return Task::run(modpack::install(self.root.clone(), pack), |e| Message::SetupMessage(SetupMessage::PackEvent(e)));
+        }
+        let program_data = ProgramData {
+            name: self.name.clone(),
+            loader,
+            version,
+            curseforge: self.curseforge.clone(),
+            server_jar,
+            java: self.selected_java.as_ref().map(|j| j.path.clone()),
+        };
+        if let Err(err) = program_data.save(&self.root) {
+            eprintln!("{err}");
+            self.error = err;
+            return Task::none();
+        }
+        self.program_data.set(program_data).expect("Attempted to write to program_data twice");
+        Task::done(Message::SetupMessage(SetupMessage::SetupConcluded))
+    }
+
+    fn _download_jar(&self, i: usize) -> Task<Message> {
+        let InstallPhase::Downloading { plan, .. } = &self.install_phase else {return Task::none()};
+        let jar = plan.jars[i].clone();
+        let root = self.root.clone();
+        Task::run(
+            reqwests::download_verified(
+                jar.url,
+                root.join(&jar.file_name),
+                root.join(".mcservermodgui").join("quarantine"),
+                move |bytes| server_installer::verify_server_jar(bytes, &jar.hashes),
+            ),
+            move |e| Message::SetupMessage(SetupMessage::InstallDownloadEvent(i, e))
+        )
+    }
+
     pub fn view(&self) -> Element<'_, SetupMessage> {
         let filter = if self.show_snapshots {
             |m: &&MinecraftVersion| {
@@ -119,8 +380,118 @@ impl SetupState {
                     }
                 ).size(13).text_size(15).spacing(7)]
             ].spacing(6),
-            right(button(text("Ok").align_x(Center).align_y(Center)).width(100).height(35).on_press(SetupMessage::DoneButtonPressed))
+            self._java_row(SEPARATION_SPACING),
+            self._modpack_row(SEPARATION_SPACING),
+            row![Space::new().width(SEPARATION_SPACING), column![
+                checkbox(!self.skip_install).label("Download the server software into this folder").on_toggle(SetupMessage::InstallServerToggled),
+                if !self.skip_install && self.selected_loader != Some(ModLoader::Velocity) {
+                    Element::from(row![
+                        checkbox(self.eula_accepted).on_toggle(SetupMessage::EulaToggled),
+                        rich_text![
+                            span("I accept the "),
+                            span("Minecraft EULA").link("https://aka.ms/MinecraftEULA".to_string()).color(Color::from_rgb8(175, 200, 240)),
+                        ].on_link_click(SetupMessage::OpenLink),
+                    ].spacing(0))
+                } else {
+                    Space::new().into()
+                },
+            ].spacing(6)],
+            self._install_status(),
+            right(button(text("Ok").align_x(Center).align_y(Center)).width(100).height(35)
+                .on_press_maybe(matches!(self.install_phase, InstallPhase::NotStarted | InstallPhase::Failed(_)).then_some(SetupMessage::DoneButtonPressed)))
         ].max_width(500).spacing(10)).padding(40).align_x(Center)
         .into()
     }
+
+    fn _java_row(&self, label_width: u32) -> Element<'_, SetupMessage> {
+        let picker: Element<SetupMessage> = match &self.java_runtimes {
+            None => row![Circular::new().size(20.0), text("Looking for Java...")].spacing(10).align_y(Center).into(),
+            Some(runtimes) => pick_list(runtimes.as_slice(), self.selected_java.clone(), SetupMessage::JavaSelected)
+                .placeholder("No Java found")
+                .width(Fill)
+                .into(),
+        };
+        let version = self.selected_version.as_ref().map_or("", |v| v.id.as_str());
+        let warning = match (&self.java_runtimes, &self.required_java) {
+            (Some(runtimes), Some(Ok(required))) if !runtimes.iter().any(|j| j.runs(*required)) =>
+                format!("Minecraft {version} needs Java {required}, and none of the Java installs found can run it. Install Java {required} to start the server"),
+            (_, Some(Ok(required))) if self.selected_java.as_ref().is_some_and(|j| !j.runs(*required)) =>
+                format!("Minecraft {version} needs Java {required}, the server probably won't start with this one"),
+            (_, Some(Err(_))) => "Couldn't find out which Java this version needs".to_string(),
+            _ => String::new(),
+        };
+        column![
+            row![text("Java: ").width(label_width), picker].align_y(Center),
+            row![Space::new().width(label_width), text(warning).size(14).color(Color::from_rgb8(230, 180, 50))],
+        ].spacing(4).into()
+    }
+
+    fn _modpack_row(&self, label_width: u32) -> Element<'_, SetupMessage> {
+        let loaded: Element<SetupMessage> = match &self.modpack {
+            None if self.is_loading_pack => row![Circular::new().size(16.0), text("Reading the pack...").size(14)].spacing(8).align_y(Center).into(),
+            None => text("Optional, a .mrpack or a CurseForge pack zip. Sets the loader and version, then downloads the pack's mods and files with the server")
+                .size(14).color(Color::from_rgb8(150, 150, 150)).into(),
+            Some(pack) => row![
+                text(match pack.left_out {
+                    0 => format!("{} {} ({} files)", pack.name, pack.version, pack.files.len()),
+                    n => format!("{} {} ({} files, {n} client only ones left out)", pack.name, pack.version, pack.files.len()),
+                }).size(14).width(Fill),
+                button(text("Clear").size(14)).on_press(SetupMessage::ClearPackPressed).style(button::secondary),
+            ].spacing(8).align_y(Center).into(),
+        };
+        column![
+            row![
+                text("Modpack: ").width(label_width),
+                text_input(".mrpack or .zip file", &self.pack_path).on_input(SetupMessage::PackPathTyped).on_submit(SetupMessage::LoadPackPressed),
+                button("Load").on_press_maybe((!self.is_loading_pack).then_some(SetupMessage::LoadPackPressed)),
+            ].spacing(8).align_y(Center),
+            // curseforge packs only list file ids, and their api won't answer without a key
+            if self.pack_path.trim().ends_with(".zip") {
+                Element::from(row![
+                    text("CurseForge key: ").width(label_width),
+                    text_input("API key", self.curseforge.api_key.as_deref().unwrap_or("")).on_input(SetupMessage::CurseforgeKeyTyped).secure(true),
+                ].align_y(Center))
+            } else {
+                Space::new().into()
+            },
+            row![Space::new().width(label_width), loaded],
+        ].spacing(4).into()
+    }
+
+    fn _install_status(&self) -> Element<'_, SetupMessage> {
+        let status: Element<'_, SetupMessage> = match &self.install_phase {
+            InstallPhase::NotStarted => Space::new().into(),
+            InstallPhase::Planning => row![Circular::new().size(20.0), text("Finding server software...")].spacing(10).align_y(Center).into(),
+            InstallPhase::Downloading { plan, current, downloaded, total } => {
+                let total = total.unwrap_or(0).max(1);
+                column![
+                    text(format!("Downloading {} ({}/{})", plan.jars[*current].file_name, current + 1, plan.jars.len())),
+                    progress_bar(0.0..=1.0, *downloaded as f32 / total as f32).girth(8),
+                ].spacing(5).into()
+            }
+            InstallPhase::RunningInstaller { installer, .. } => row![Circular::new().size(20.0), text(format!("Running {installer}..."))].spacing(10).align_y(Center).into(),
+            InstallPhase::InstallingPack { progress, .. } => match progress {
+                PackEvent::Downloading { current, total, path } => column![
+                    text(format!("Downloading {path} ({}/{total})", current + 1)),
+                    progress_bar(0.0..=1.0, *current as f32 / (*total).max(1) as f32).girth(8),
+                ].spacing(5).into(),
+                _ => row![Circular::new().size(20.0), text("Copying the pack's files...")].spacing(10).align_y(Center).into(),
+            },
+            InstallPhase::Failed(err) => column![
+                text(err).color(Color::from_rgb8(200, 0, 0)),
+                row![
+                    text("Press Ok to try again, or").color(Color::from_rgb8(150, 150, 150)),
+                    button(text("skip installing").size(14)).on_press(SetupMessage::SkipInstallPressed).style(button::secondary),
+                ].spacing(8).align_y(Center),
+            ].spacing(5).into(),
+        };
+        if self.install_log.is_empty() {
+            return status;
+        }
+        // kept around after a failure so the installer's own error can be read
+        let log = scrollable(
+            column(self.install_log.iter().map(|l| text(l).size(12).font(Font::MONOSPACE).into())).padding(6)
+        ).anchor_bottom().height(180).width(Fill);
+        column![status, container(log).style(container::bordered_box)].spacing(8).into()
+    }
 }
@@ -0,0 +1,218 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::util::supervisor::ServerState;
+
+// binary search for the jar that makes the server crash. this only decides which jars to turn off for each run,
+// actually moving them and running the server is up to whoever drives it
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Phase {
+    Baseline, // everything on, to make sure it still crashes
+    Clean, // every candidate off, to make sure it's a mod at all
+    Halving,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// run the server again with these jars disabled
+    Test(Vec<String>),
+    /// these are to blame. more than one when they can't be told apart because of their dependencies
+    Found(Vec<String>),
+    NoCrash,
+    NotAMod,
+}
+
+#[derive(Debug, Clone)]
+pub struct Bisection {
+    jars: Vec<String>,
+    required_by: HashMap<String, Vec<String>>,
+    candidates: Vec<String>,
+    phase: Phase,
+    disabled: Vec<String>, // for the run in progress
+    pub rounds: usize,
+}
+impl Bisection {
+    /// `jars` are the enabled jars to search through, `requires` maps a jar to the jars it can't run without.
+    /// the first test is always a run with nothing disabled
+    pub fn new(jars: Vec<String>, requires: &HashMap<String, Vec<String>>) -> Self {
+        let mut required_by: HashMap<String, Vec<String>> = HashMap::new();
+        for (jar, deps) in requires {
+            for dep in deps {
+                required_by.entry(dep.clone()).or_default().push(jar.clone());
+            }
+        }
+        Bisection { candidates: jars.clone(), jars, required_by, phase: Phase::Baseline, disabled: vec![], rounds: 0 }
+    }
+
+    pub fn candidates(&self) -> &[String] {
+        &self.candidates
+    }
+
+    /// takes the result of the run with the jars from the last `Test` off (nothing, at first) and works out the next one
+    pub fn record(&mut self, crashed: bool) -> Outcome {
+        self.rounds += 1;
+        match self.phase {
+            Phase::Baseline if !crashed => return Outcome::NoCrash,
+            Phase::Baseline => {
+                self.phase = Phase::Clean;
+                self.disabled = self.jars.clone();
+                return Outcome::Test(self.disabled.clone());
+            }
+            Phase::Clean if crashed => return Outcome::NotAMod,
+            Phase::Clean => self.phase = Phase::Halving,
+            Phase::Halving => {
+                let off: HashSet<&String> = self.disabled.iter().collect();
+                // a crash with them off means the culprit is still on, and the other way round
+                self.candidates.retain(|c| off.contains(c) != crashed);
+            }
+        }
+
+        if self.candidates.len() <= 1 {
+            return Outcome::Found(self.candidates.clone());
+        }
+        match self.split() {
+            Some(disabled) => {
+                self.disabled = disabled;
+                Outcome::Test(self.disabled.clone())
+            }
+            None => Outcome::Found(self.candidates.clone()),
+        }
+    }
+
+    /// about half the candidates, plus everything that needs them so no jar is left on without its dependencies.
+    /// None if the candidates can't be split at all
+    fn split(&self) -> Option<Vec<String>> {
+        let target = self.candidates.len() / 2;
+        let mut disabled: HashSet<String> = HashSet::new();
+        for c in &self.candidates {
+            let mut with = disabled.clone();
+            with.insert(c.clone());
+            let with = self.close_over_dependents(with);
+            // taking every candidate out wouldn't tell us anything
+            if self.candidates.iter().all(|c| with.contains(c)) {continue}
+            disabled = with;
+            if self.candidates.iter().filter(|c| disabled.contains(*c)).count() >= target {break}
+        }
+        if disabled.is_empty() {return None}
+        Some(self.jars.iter().filter(|j| disabled.contains(*j)).cloned().collect())
+    }
+
+    fn close_over_dependents(&self, mut set: HashSet<String>) -> HashSet<String> {
+        let mut stack: Vec<String> = set.iter().cloned().collect();
+        while let Some(jar) = stack.pop() {
+            for dependent in self.required_by.get(&jar).into_iter().flatten() {
+                if set.insert(dependent.clone()) {
+                    stack.push(dependent.clone());
+                }
+            }
+        }
+        set
+    }
+}
+
+/// what to do about a state change during a test run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RunStep {
+    Wait,
+    /// it got up, which is all the run needed to know
+    StopServer,
+    /// the run is over, record it
+    Finished { crashed: bool },
+}
+
+/// follows the server through one test run. only getting to "Done" counts as starting fine,
+/// so exiting before that, even with code 0, or not starting in time counts as a crash
+#[derive(Debug, Default)]
+pub struct TestRun {
+    passed: bool,
+}
+impl TestRun {
+    pub fn state_changed(&mut self, state: &ServerState) -> RunStep {
+        match state {
+            ServerState::Running => {
+                self.passed = true;
+                RunStep::StopServer
+            }
+            ServerState::Stopped | ServerState::Crashed(_) => RunStep::Finished { crashed: !self.passed },
+            ServerState::Starting | ServerState::Stopping => RunStep::Wait,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, time::Duration};
+
+    use iced::futures::StreamExt;
+
+    use super::*;
+    use crate::util::supervisor::{self, LaunchConfig, SupervisorEvent};
+
+    const JARS: [&str; 4] = ["a.jar", "b.jar", "c.jar", "d.jar"];
+
+    /// bisects `JARS` with `script` standing in for the server. it's run in a folder with the jars that are on in mods/
+    fn bisect_fake_server(name: &str, script: &str) -> Outcome {
+        let dir = std::env::temp_dir().join(format!("mcservermodgui-bisect-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let config = LaunchConfig {
+            program: "sh".into(),
+            args: vec!["-c".into(), script.into()],
+            dir: dir.clone(),
+            path_prefix: None,
+            stop_command: "stop".to_string(),
+            stop_timeout: Duration::from_secs(5),
+            start_timeout: Some(Duration::from_secs(1)),
+        };
+        let turn_off = |disabled: &[String]| {
+            let _ = fs::remove_dir_all(dir.join("mods"));
+            fs::create_dir_all(dir.join("mods")).unwrap();
+            for jar in JARS.iter().filter(|j| !disabled.iter().any(|d| d == *j)) {
+                fs::write(dir.join("mods").join(jar), "").unwrap();
+            }
+        };
+
+        let mut bisection = Bisection::new(JARS.map(str::to_string).to_vec(), &HashMap::new());
+        let outcome = tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let mut events = Box::pin(supervisor::run());
+            let Some(SupervisorEvent::Ready(supervisor)) = events.next().await else {panic!("the first event wasn't Ready")};
+            turn_off(&[]);
+            supervisor.start(config.clone());
+            let mut test = TestRun::default();
+            while let Some(event) = events.next().await {
+                let SupervisorEvent::StateChanged(state) = event else {continue};
+                match test.state_changed(&state) {
+                    RunStep::Wait => (),
+                    RunStep::StopServer => supervisor.stop(),
+                    RunStep::Finished { crashed } => match bisection.record(crashed) {
+                        Outcome::Test(disabled) => {
+                            turn_off(&disabled);
+                            test = TestRun::default();
+                            supervisor.start(config.clone());
+                        }
+                        outcome => return outcome,
+                    },
+                }
+            }
+            panic!("the supervisor went away");
+        });
+        let _ = fs::remove_dir_all(&dir);
+        outcome
+    }
+
+    #[test]
+    fn a_clean_exit_before_done_counts_as_a_crash() {
+        let outcome = bisect_fake_server("exits", "[ -e mods/c.jar ] && exit 0; echo 'Done (1s)!'; read line");
+        assert_eq!(outcome, Outcome::Found(vec!["c.jar".to_string()]));
+    }
+
+    #[test]
+    fn a_server_that_hangs_while_starting_times_out() {
+        let outcome = bisect_fake_server("hangs", "if [ -e mods/b.jar ]; then while read line; do :; done; fi; echo 'Done (1s)!'; read line");
+        assert_eq!(outcome, Outcome::Found(vec!["b.jar".to_string()]));
+    }
+
+    #[test]
+    fn nothing_to_find_without_a_crash() {
+        assert_eq!(bisect_fake_server("fine", "echo 'Done (1s)!'; read line"), Outcome::NoCrash);
+    }
+}
@@ -0,0 +1,116 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{ModLoader, util::{installed_mods, server_properties::ServerProperties}};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+    Json5,
+    Yaml,
+    Properties,
+}
+impl ConfigFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "toml" => Some(ConfigFormat::Toml),
+            "json" => Some(ConfigFormat::Json),
+            "json5" => Some(ConfigFormat::Json5),
+            "yml" | "yaml" => Some(ConfigFormat::Yaml),
+            "properties" => Some(ConfigFormat::Properties),
+            _ => None,
+        }
+    }
+
+    /// the token the highlighter looks the syntax up by
+    pub fn syntax(self) -> &'static str {
+        match self {
+            ConfigFormat::Toml => "toml",
+            ConfigFormat::Json => "json",
+            ConfigFormat::Json5 => "js", // there's no json5 syntax, javascript copes with the comments and bare keys
+            ConfigFormat::Yaml => "yaml",
+            ConfigFormat::Properties => "properties",
+        }
+    }
+
+    /// parses `text` just to see if the game will be able to
+    pub fn validate(self, text: &str) -> Result<(), String> {
+        match self {
+            ConfigFormat::Toml => toml::from_str::<toml::Table>(text).map(|_| ()).map_err(|e| e.to_string()),
+            ConfigFormat::Json => serde_json::from_str::<serde_json::Value>(text).map(|_| ()).map_err(|e| e.to_string()),
+            ConfigFormat::Json5 => json5::from_str::<serde_json::Value>(text).map(|_| ()).map_err(|e| e.to_string()),
+            ConfigFormat::Yaml => serde_yaml::from_str::<serde_yaml::Value>(text).map(|_| ()).map_err(|e| e.to_string()),
+            ConfigFormat::Properties => {
+                ServerProperties::parse(text); // anything goes in a .properties file
+                Ok(())
+            }
+        }
+    }
+}
+
+/// a file or folder in the config tree. `path` is relative to the server root
+#[derive(Debug, Clone)]
+pub struct ConfigNode {
+    pub path: PathBuf,
+    pub depth: usize,
+    pub is_dir: bool,
+}
+
+/// `config/` for mod loaders, `plugins/` (each plugin keeps its config in its own folder there) for plugin servers
+pub fn config_dir(loader: ModLoader) -> &'static str {
+    if installed_mods::is_plugin_server(loader) {"plugins"} else {"config"}
+}
+
+/// every config file we know how to edit under `config_dir`, depth first with folders before files.
+/// folders without any such files are left out
+pub fn list_tree(root: &Path, loader: ModLoader) -> Vec<ConfigNode> {
+    let mut nodes = vec![];
+    _walk(root, Path::new(config_dir(loader)), 0, &mut nodes);
+    nodes
+}
+
+fn _walk(root: &Path, rel: &Path, depth: usize, nodes: &mut Vec<ConfigNode>) {
+    let Ok(entries) = fs::read_dir(root.join(rel)) else {return};
+    let mut entries: Vec<(bool, String)> = entries.filter_map(|e| {
+        let e = e.ok()?;
+        Some((e.file_type().ok()?.is_dir(), e.file_name().to_string_lossy().into_owned()))
+    }).collect();
+    entries.sort_by_key(|(is_dir, name)| (!is_dir, name.to_lowercase()));
+
+    for (is_dir, name) in entries {
+        let path = rel.join(&name);
+        if is_dir {
+            let at = nodes.len();
+            _walk(root, &path, depth + 1, nodes);
+            if nodes.len() > at {
+                nodes.insert(at, ConfigNode { path, depth, is_dir: true });
+            }
+        } else if ConfigFormat::from_path(&path).is_some() {
+            nodes.push(ConfigNode { path, depth, is_dir: false });
+        }
+    }
+}
+
+/// where the version before the last save of `rel` is kept
+pub fn backup_path(root: &Path, rel: &Path) -> PathBuf {
+    root.join(".mcservermodgui").join("config_backups").join(rel)
+}
+
+/// checks `text` and writes it to `rel`, moving what was there into the backups first
+pub fn save(root: &Path, rel: &Path, text: &str) -> Result<(), String> {
+    if let Some(format) = ConfigFormat::from_path(rel) {
+        format.validate(text).map_err(|e| format!("Not saved, {} has an error: {e}", rel.display()))?;
+    }
+    let path = root.join(rel);
+    if path.exists() {
+        let backup = backup_path(root, rel);
+        if let Some(parent) = backup.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Couldn't create {}: {e}", parent.display()))?;
+        }
+        fs::copy(&path, &backup).map_err(|e| format!("Couldn't back up {}: {e}", rel.display()))?;
+    }
+    fs::write(&path, text).map_err(|e| format!("Couldn't write {}: {e}", rel.display()))
+}
@@ -0,0 +1,306 @@
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use zip::ZipArchive;
+
+use crate::util::installed_mods::{self, InstalledMod};
+use crate::util::mod_backups;
+
+/// how much of logs/latest.log gets looked at when there's no crash report, or as well as it
+const LOG_TAIL_LINES: usize = 300;
+
+/// an installed jar that the crash points at, and why
+#[derive(Debug, Clone)]
+pub struct Suspect {
+    pub file_name: String,
+    pub name: String,
+    pub reasons: Vec<String>,
+    pub backup: Option<String>, // an older jar of the same mod in .mcservermodgui/backups, to roll back to
+}
+
+#[derive(Debug, Clone)]
+pub struct CrashTriage {
+    pub report: Option<PathBuf>,
+    pub summary: String,
+    pub suspects: Vec<Suspect>, // most evidence first
+}
+
+/// what a crash report or log mentions that could lead back to a mod
+#[derive(Debug, Default, PartialEq)]
+pub struct CrashHints {
+    pub mod_files: Vec<String>,
+    pub mod_ids: Vec<String>,
+    pub classes: Vec<String>, // `com/example/Thing.class`, from stacktrace frames
+}
+
+/// looks for a crash report newer than `since` plus the end of the latest log, and works out which installed jars are to blame
+pub fn triage(root: &Path, mods_dir: &Path, installed: &[InstalledMod], since: SystemTime) -> CrashTriage {
+    let report = find_crash_report(&root.join("crash-reports"), since);
+    let report_text = report.as_ref().and_then(|p| fs::read_to_string(p).ok()).unwrap_or_default();
+    let log = fs::read_to_string(root.join("logs").join("latest.log")).unwrap_or_default();
+    let log_tail = log.lines().rev().take(LOG_TAIL_LINES).collect::<Vec<_>>().into_iter().rev().collect::<Vec<_>>().join("\n");
+
+    let mut hints = parse_hints(&report_text);
+    let log_hints = parse_hints(&log_tail);
+    hints.mod_files.extend(log_hints.mod_files);
+    hints.mod_ids.extend(log_hints.mod_ids);
+    hints.classes.extend(log_hints.classes);
+
+    let mut suspects = match_suspects(&hints, mods_dir, installed);
+    let backups = root.join(".mcservermodgui").join("backups");
+    for s in &mut suspects {
+        s.backup = find_backup(&backups, installed.iter().find(|m| m.file_name == s.file_name));
+    }
+
+    CrashTriage {
+        summary: summarize(&report_text).or_else(|| summarize_log(&log_tail)).unwrap_or_else(|| "The server stopped unexpectedly".to_string()),
+        report,
+        suspects,
+    }
+}
+
+/// the newest `crash-*.txt` written after `since`
+pub fn find_crash_report(dir: &Path, since: SystemTime) -> Option<PathBuf> {
+    fs::read_dir(dir).ok()?
+        .filter_map(|e| {
+            let e = e.ok()?;
+            let modified = e.metadata().ok()?.modified().ok()?;
+            let name = e.file_name().to_string_lossy().into_owned();
+            (name.starts_with("crash-") && name.ends_with(".txt") && modified >= since).then_some((modified, e.path()))
+        })
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path)
+}
+
+pub fn parse_hints(text: &str) -> CrashHints {
+    let mut hints = CrashHints::default();
+    let mut in_suspects = false;
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+
+        // forge/neoforge: "Mod File: /srv/mods/foo-1.0.jar"
+        if let Some(path) = trimmed.strip_prefix("Mod File:") {
+            let path = path.trim();
+            let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+            if name.ends_with(".jar") {
+                hints.mod_files.push(name.to_string());
+            }
+        }
+
+        // "Suspected Mod(s): Foo (foo), Version: 1.0" or "Suspected Mods:" followed by indented lines
+        if let Some(rest) = trimmed.strip_prefix("Suspected Mod").and_then(|r| r.split_once(':')).map(|(_, r)| r) {
+            in_suspects = true;
+            hints.mod_ids.extend(parenthesized(rest));
+            continue;
+        }
+        if in_suspects {
+            if trimmed.is_empty() || !line.starts_with([' ', '\t']) {
+                in_suspects = false;
+            } else if !trimmed.contains(':') || trimmed.contains("), Version") {
+                hints.mod_ids.extend(parenthesized(trimmed));
+            }
+        }
+
+        if let Some(frame) = trimmed.strip_prefix("at ") {
+            // "at com.example.Foo.bar(Foo.java:10)" or "at TRANSFORMER/foo@1.0/com.example.Foo.bar(...)"
+            let frame = frame.split('(').next().unwrap_or(frame);
+            let frame = frame.rsplit('/').next().unwrap_or(frame);
+            let Some((class, method)) = frame.rsplit_once('.') else {continue};
+            let class = class.split('$').next().unwrap_or(class);
+            hints.classes.push(format!("{}.class", class.replace('.', "/")));
+            // mixin handlers are named like `handler$zza000$modid$method`, which gives away the mod that injected them
+            let parts: Vec<&str> = method.split('$').collect();
+            if parts.len() >= 4 && !parts[2].is_empty() {
+                hints.mod_ids.push(parts[2].to_string());
+            }
+        }
+    }
+    hints.mod_ids.retain(|id| !matches!(id.as_str(), "minecraft" | "forge" | "neoforge" | "fabricloader" | "java" | "mixin"));
+    hints
+}
+
+fn parenthesized(s: &str) -> Vec<String> {
+    s.split('(').skip(1).filter_map(|p| p.split_once(')')).map(|(id, _)| id.trim().to_string()).filter(|id| !id.is_empty()).collect()
+}
+
+/// turns hints into installed jars, ranked by how much points at them
+pub fn match_suspects(hints: &CrashHints, mods_dir: &Path, installed: &[InstalledMod]) -> Vec<Suspect> {
+    let mut suspects: Vec<Suspect> = vec![];
+    let mut blame = |m: &InstalledMod, reason: String| {
+        match suspects.iter_mut().find(|s| s.file_name == m.file_name) {
+            Some(s) => if !s.reasons.contains(&reason) {s.reasons.push(reason)},
+            None => suspects.push(Suspect { file_name: m.file_name.clone(), name: m.name.clone(), reasons: vec![reason], backup: None }),
+        }
+    };
+
+    for file in &hints.mod_files {
+        if let Some(m) = installed.iter().find(|m| &m.file_name == file) {
+            blame(m, "named as the mod file in the crash report".to_string());
+        }
+    }
+    for id in &hints.mod_ids {
+        if let Some(m) = installed.iter().find(|m| m.id.as_deref().is_some_and(|i| i.eq_ignore_ascii_case(id))) {
+            blame(m, format!("suspected by the loader ({id})"));
+        }
+    }
+
+    // a library class is shaded into lots of jars, so only the jar owning the topmost frame of the mod's own code gets blamed
+    let mut seen = HashSet::new();
+    let frames = hints.classes.iter().filter(|c| seen.insert(*c) && !is_library_class(c)).collect::<Vec<_>>();
+    if !frames.is_empty() {
+        let mut archives = installed.iter().filter_map(|m| {
+            let archive = ZipArchive::new(File::open(mods_dir.join(&m.file_name)).ok()?).ok()?;
+            Some((m, archive))
+        }).collect::<Vec<_>>();
+        let owners = frames.iter().map(|class| {
+            let owners = archives.iter_mut().filter_map(|(m, a)| a.by_name(class).is_ok().then_some(*m)).collect::<Vec<_>>();
+            (class, owners)
+        }).filter(|(_, owners)| !owners.is_empty()).collect::<Vec<_>>();
+        // a class only one jar has is that mod's own code. if every frame is shared, blame whoever has the top one
+        if let Some((class, owners)) = owners.iter().find(|(_, o)| o.len() == 1).or(owners.first()) {
+            for m in owners {
+                blame(m, format!("{} is at the top of the stacktrace", class.trim_end_matches(".class").replace('/', ".")));
+            }
+        }
+    }
+
+    suspects.sort_by_key(|s| std::cmp::Reverse(s.reasons.len()));
+    suspects
+}
+
+/// classes of java, the game, the loaders and the usual libraries, which show up in every stacktrace
+fn is_library_class(class: &str) -> bool {
+    const LIBRARIES: [&str; 22] = [
+        "java/", "javax/", "jdk/", "sun/", "com/sun/", "net/minecraft/", "com/mojang/", "net/minecraftforge/", "net/neoforged/",
+        "cpw/mods/", "net/fabricmc/", "org/quiltmc/", "org/spongepowered/", "org/bukkit/", "io/papermc/", "com/velocitypowered/",
+        "io/netty/", "com/google/", "org/apache/", "org/slf4j/", "kotlin/", "it/unimi/",
+    ];
+    !class.contains('/') || LIBRARIES.iter().any(|l| class.starts_with(l))
+}
+
+/// the newest backup of the same mod in `backups_dir` that isn't the jar installed now
+fn find_backup(backups_dir: &Path, current: Option<&InstalledMod>) -> Option<String> {
+    let current = current?;
+    fs::read_dir(backups_dir).ok()?.filter_map(|e| e.ok()).filter_map(|e| {
+        let name = e.file_name().to_string_lossy().into_owned();
+        let original = mod_backups::original_name(&name);
+        if original == current.file_name {return None}
+        let same_mod = match (&current.id, installed_mods::read_jar_metadata(&e.path()).ok()?.id) {
+            (Some(current), Some(id)) => *current == id,
+            _ => file_stem(original) == file_stem(&current.file_name),
+        };
+        if !same_mod {return None}
+        let modified = e.metadata().ok()?.modified().ok()?.duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_millis());
+        let backed_up_at = name.strip_suffix(original).and_then(|s| s.trim_end_matches('-').parse().ok()).unwrap_or(modified);
+        Some((backed_up_at, name))
+    }).max_by_key(|(at, _)| *at).map(|(_, name)| name)
+}
+
+/// a jar's name without its version, `sodium-fabric-0.5.8+mc1.20.1.jar` gives `sodium-fabric`
+fn file_stem(file_name: &str) -> String {
+    let name = file_name.trim_end_matches(".jar");
+    name.split(['-', '_'])
+        .take_while(|part| !part.trim_start_matches('v').starts_with(|c: char| c.is_ascii_digit()))
+        .collect::<Vec<_>>().join("-").to_ascii_lowercase()
+}
+
+/// swaps `current` in the mods folder for `backup`. the replaced jar goes into the backups so this can be undone the same way
+pub fn roll_back(root: &Path, mods_dir: &Path, current: &str, backup: &str) -> Result<(), String> {
+    let old = fs::read(mod_backups::backups_dir(root).join(backup)).map_err(|e| format!("Couldn't read backup {backup}: {e}"))?;
+    mod_backups::back_up(root, &mods_dir.join(current))?;
+    let restored = mod_backups::original_name(backup);
+    fs::write(mods_dir.join(restored), old).map_err(|e| format!("Couldn't restore {restored}: {e}"))?;
+    if restored != current {
+        fs::remove_file(mods_dir.join(current)).map_err(|e| format!("Couldn't remove {current}: {e}"))?;
+    }
+    Ok(())
+}
+
+/// "Description: ..." and the exception under it
+fn summarize(report: &str) -> Option<String> {
+    let mut lines = report.lines().skip_while(|l| !l.starts_with("Description:"));
+    let description = lines.next()?.trim_start_matches("Description:").trim();
+    let exception = lines.find(|l| !l.trim().is_empty()).unwrap_or_default().trim();
+    Some(format!("{description}\n{exception}"))
+}
+
+/// the last error in the log, and the exception right after it if there is one
+fn summarize_log(log: &str) -> Option<String> {
+    let lines: Vec<&str> = log.lines().collect();
+    let i = lines.iter().rposition(|l| l.contains("/ERROR]") || l.contains("/FATAL]") || l.contains(" ERROR]"))?;
+    let next = lines.get(i + 1).filter(|l| !l.starts_with('[')).map_or("", |l| l.trim());
+    Some(format!("{}\n{next}", lines[i]).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mcservermodgui-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_jar(path: &Path, entries: &[(&str, &str)]) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        for (name, contents) in entries {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    fn fabric_mod(id: &str) -> String {
+        format!(r#"{{"id": "{id}", "name": "{id}", "version": "1.0"}}"#)
+    }
+
+    #[test]
+    fn the_top_frame_of_mod_code_gets_blamed_not_the_shading_jars() {
+        let dir = temp_dir("triage-frames");
+        let shaded = "org/shaded/lib/Thing.class";
+        write_jar(&dir.join("a.jar"), &[("fabric.mod.json", &fabric_mod("a")), (shaded, ""), ("com/a/Main.class", "")]);
+        write_jar(&dir.join("b.jar"), &[("fabric.mod.json", &fabric_mod("b")), (shaded, ""), ("com/b/Main.class", "")]);
+        let installed = ["a.jar", "b.jar"].map(|f| installed_mods::read_jar_metadata(&dir.join(f)).unwrap());
+
+        let hints = parse_hints("java.lang.NullPointerException\n\
+            \tat org.shaded.lib.Thing.parse(Thing.java:5)\n\
+            \tat com.a.Main.load(Main.java:10)\n\
+            \tat com.b.Main.onInit(Main.java:3)\n\
+            \tat net.minecraft.server.Main.main(Main.java:1)");
+        let suspects = match_suspects(&hints, &dir, &installed);
+        assert_eq!(suspects.iter().map(|s| s.file_name.as_str()).collect::<Vec<_>>(), vec!["a.jar"]);
+        assert!(suspects[0].reasons[0].starts_with("com.a.Main"), "{:?}", suspects[0].reasons);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn the_newest_backup_of_the_same_mod_is_picked() {
+        let dir = temp_dir("triage-backups");
+        let current = dir.join("a-3.0.jar");
+        write_jar(&current, &[("fabric.mod.json", &fabric_mod("a"))]);
+        let current = installed_mods::read_jar_metadata(&current).unwrap();
+        let backups = dir.join("backups");
+        fs::create_dir_all(&backups).unwrap();
+        write_jar(&backups.join("1700000000000-a-1.0.jar"), &[("fabric.mod.json", &fabric_mod("a"))]);
+        write_jar(&backups.join("1700000005000-a-2.0.jar"), &[("fabric.mod.json", &fabric_mod("a"))]);
+        write_jar(&backups.join("1700000009000-b-1.0.jar"), &[("fabric.mod.json", &fabric_mod("b"))]);
+        write_jar(&backups.join("1700000009500-a-3.0.jar"), &[("fabric.mod.json", &fabric_mod("a"))]);
+        // jars without metadata fall back to the file name
+        write_jar(&backups.join("1700000002000-plain-0.9.jar"), &[]);
+        write_jar(&backups.join("1700000001000-plain-0.8.jar"), &[]);
+
+        assert_eq!(find_backup(&backups, Some(&current)).as_deref(), Some("1700000005000-a-2.0.jar"));
+        let plain = InstalledMod { file_name: "plain-1.0.jar".to_string(), ..current };
+        assert_eq!(find_backup(&backups, Some(&InstalledMod { id: None, ..plain })).as_deref(), Some("1700000002000-plain-0.9.jar"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use md5::Md5;
+use sha1::{Digest, Sha1};
+use sha2::{Sha256, Sha512};
+
+pub fn md5_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Md5::digest(bytes))
+}
+
+pub fn sha1_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha1::digest(bytes))
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+pub fn sha512_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha512::digest(bytes))
+}
+
+/// checks `bytes` against every hash the provider gave us that we know how to compute.
+/// errors if none of them are usable, since an unverifiable jar is as bad as a corrupt one.
+/// when sha1 or sha512 is offered one of those has to be checked, md5 alone only does for sources that give nothing better
+pub fn verify_hashes(bytes: &[u8], hashes: &HashMap<String, String>) -> Result<(), String> {
+    let mut checked = false;
+    let mut strong_checked = false;
+    let mut strong_offered = false;
+    for (algorithm, expected) in hashes {
+        // providers aren't consistent about `SHA-1` vs `sha1`
+        let normalized = algorithm.to_ascii_lowercase().replace('-', "");
+        let strong = matches!(normalized.as_str(), "sha1" | "sha512");
+        strong_offered |= strong;
+        let actual = match normalized.as_str() {
+            "md5" => md5_hex(bytes),
+            "sha1" => sha1_hex(bytes),
+            "sha256" => sha256_hex(bytes),
+            "sha512" => sha512_hex(bytes),
+            _ => continue,
+        };
+        if !actual.eq_ignore_ascii_case(expected.trim()) {
+            return Err(format!("{algorithm} mismatch: expected {expected}, got {actual}"));
+        }
+        checked = true;
+        strong_checked |= strong;
+    }
+    if strong_offered && !strong_checked {
+        Err(format!("sha1 or sha512 was offered but couldn't be checked (got {:?})", hashes.keys().collect::<Vec<_>>()))
+    } else if checked {
+        Ok(())
+    } else {
+        Err(format!("no supported hashes to verify against (got {:?})", hashes.keys().collect::<Vec<_>>()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, fs};
+
+    use iced::futures::StreamExt;
+
+    use super::*;
+    use crate::util::{mock_http, reqwests::{self, DownloadEvent}};
+
+    const JAR: &[u8] = b"not really a jar";
+
+    fn hashes(pairs: &[(&str, String)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.clone())).collect()
+    }
+
+    #[test]
+    fn matching_hashes_pass() {
+        assert!(verify_hashes(JAR, &hashes(&[("sha1", sha1_hex(JAR)), ("sha512", sha512_hex(JAR))])).is_ok());
+        assert!(verify_hashes(JAR, &hashes(&[("SHA-1", sha1_hex(JAR).to_uppercase())])).is_ok());
+        // purpur only publishes md5
+        assert!(verify_hashes(JAR, &hashes(&[("md5", md5_hex(JAR))])).is_ok());
+    }
+
+    #[test]
+    fn any_mismatch_fails() {
+        let err = verify_hashes(JAR, &hashes(&[("sha1", sha1_hex(JAR)), ("sha512", sha512_hex(b"something else"))])).unwrap_err();
+        assert!(err.contains("sha512 mismatch"), "{err}");
+        assert!(verify_hashes(JAR, &hashes(&[("md5", md5_hex(JAR)), ("sha1", sha1_hex(b"other"))])).is_err());
+    }
+
+    #[test]
+    fn no_known_hash_fails() {
+        assert!(verify_hashes(JAR, &HashMap::new()).is_err());
+        assert!(verify_hashes(JAR, &hashes(&[("crc32", "1234".to_string())])).is_err());
+    }
+
+    #[test]
+    fn mismatched_download_is_quarantined() {
+        let (base, _) = mock_http::serve(|r| (r.path == "/mod.jar").then(|| JAR.to_vec()));
+        let dir = std::env::temp_dir().join(format!("mcservermodgui-hashes-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let (mods, quarantine) = (dir.join("mods"), dir.join("quarantine"));
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let download = |expected: &[u8]| {
+            let events = reqwests::download_file(format!("{base}/mod.jar"), mods.join("mod.jar"), hashes(&[("sha1", sha1_hex(expected))]), quarantine.clone());
+            match runtime.block_on(events.collect::<Vec<_>>()).pop() {
+                Some(DownloadEvent::Finished(result)) => result,
+                other => panic!("download didn't finish: {other:?}"),
+            }
+        };
+
+        let err = download(b"what modrinth said").unwrap_err();
+        assert!(err.contains("quarantined"), "{err}");
+        assert!(!mods.join("mod.jar").exists());
+        assert_eq!(fs::read(quarantine.join("mod.jar")).unwrap(), JAR);
+
+        assert_eq!(download(JAR).unwrap(), mods.join("mod.jar"));
+        assert_eq!(fs::read(mods.join("mod.jar")).unwrap(), JAR);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,278 @@
+use std::{
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use zip::ZipArchive;
+
+use crate::{ModLoader, util::hashes};
+
+/// a jar sitting in the server's mods/plugins folder, with whatever we could read out of its metadata
+#[derive(Debug, Clone)]
+pub struct InstalledMod {
+    pub file_name: String,
+    pub sha1: String,
+
+    pub id: Option<String>,
+    pub name: String,
+    pub version: String,
+    pub loader: Option<ModLoader>,
+    pub minecraft_range: String,
+    pub enabled: bool,
+}
+
+/// reads every jar of the server, disabled ones included. jars that can't be opened still get listed by file name
+pub fn scan_mods_folder(root: &Path, loader: ModLoader) -> Vec<InstalledMod> {
+    if let Err(err) = _reconcile(root, loader) {
+        eprintln!("{err}");
+    }
+    let mut mods = _scan_dir(&root.join(loader.mods_folder()), ".jar", true);
+    mods.extend(_scan_dir(&_disabled_dir(root, loader), if is_plugin_server(loader) {".jar"} else {".jar.disabled"}, false));
+    mods.sort_by_key(|m| m.name.to_lowercase());
+    mods
+}
+
+fn _scan_dir(dir: &Path, suffix: &str, enabled: bool) -> Vec<InstalledMod> {
+    let Ok(entries) = fs::read_dir(dir) else {return vec![]};
+    entries.filter_map(|e| {
+        let path = e.ok()?.path();
+        if !path.file_name()?.to_string_lossy().ends_with(suffix) {return None}
+        let mut m = read_jar_metadata(&path).unwrap_or_else(|err| {
+            eprintln!("couldn't read metadata of {}: {err}", path.display());
+            _unknown_jar(&path)
+        });
+        m.sha1 = fs::read(&path).map(|b| hashes::sha1_hex(&b)).unwrap_or_default();
+        m.enabled = enabled;
+        Some(m)
+    }).collect()
+}
+
+pub fn is_plugin_server(loader: ModLoader) -> bool {
+    loader.mods_folder() == "plugins"
+}
+
+/// where `file_name` sits while it's disabled. mod loaders skip `foo.jar.disabled`,
+/// plugin servers get the jar moved out to `.mcservermodgui/disabled/` instead
+pub fn disabled_path(root: &Path, loader: ModLoader, file_name: &str) -> PathBuf {
+    if is_plugin_server(loader) {
+        _disabled_dir(root, loader).join(file_name)
+    } else {
+        _disabled_dir(root, loader).join(format!("{file_name}.disabled"))
+    }
+}
+
+fn _disabled_dir(root: &Path, loader: ModLoader) -> PathBuf {
+    if is_plugin_server(loader) {root.join(".mcservermodgui").join("disabled")} else {root.join(loader.mods_folder())}
+}
+
+/// moves a jar between its enabled and disabled places and writes it down in disabled.toml
+pub fn set_enabled(root: &Path, loader: ModLoader, file_name: &str, enabled: bool) -> Result<(), String> {
+    _move_jar(root, loader, file_name, enabled)?;
+    let mut disabled = DisabledMods::load(root);
+    disabled.files.retain(|f| f != file_name);
+    if !enabled {
+        disabled.files.push(file_name.to_string());
+    }
+    disabled.save(root)
+}
+
+fn _move_jar(root: &Path, loader: ModLoader, file_name: &str, enabled: bool) -> Result<(), String> {
+    let (on, off) = (root.join(loader.mods_folder()).join(file_name), disabled_path(root, loader, file_name));
+    let (from, to) = if enabled {(off, on)} else {(on, off)};
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Couldn't create {}: {e}", parent.display()))?;
+    }
+    fs::rename(&from, &to).map_err(|e| format!("Couldn't {} {file_name}: {e}", if enabled {"enable"} else {"disable"}))
+}
+
+/// brings the folder and disabled.toml back in line. jars that disabled.toml has turned off go back to their disabled place
+/// if something put them in the mods folder, jars found disabled get written down, and jars that are gone get forgotten
+fn _reconcile(root: &Path, loader: ModLoader) -> Result<(), String> {
+    let mut disabled = DisabledMods::load(root);
+    let before = disabled.files.clone();
+    let on_dir = root.join(loader.mods_folder());
+
+    let mut files = vec![];
+    for file_name in disabled.files.drain(..) {
+        if disabled_path(root, loader, &file_name).is_file() {
+            files.push(file_name);
+        } else if on_dir.join(&file_name).is_file() {
+            _move_jar(root, loader, &file_name, false)?;
+            files.push(file_name);
+        }
+    }
+    let suffix = if is_plugin_server(loader) {".jar"} else {".jar.disabled"};
+    for entry in fs::read_dir(_disabled_dir(root, loader)).into_iter().flatten().flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some(file_name) = name.strip_suffix(suffix).map(|n| format!("{n}.jar")) else {continue};
+        if !files.contains(&file_name) {
+            files.push(file_name);
+        }
+    }
+
+    disabled.files = files;
+    if disabled.files != before {disabled.save(root)} else {Ok(())}
+}
+
+/// the jars that are turned off, in `.mcservermodgui/disabled.toml`
+#[derive(serde::Serialize, serde::Deserialize, Default, Debug)]
+pub struct DisabledMods {
+    #[serde(default)]
+    pub files: Vec<String>,
+}
+impl DisabledMods {
+    fn path(root: &Path) -> PathBuf {
+        root.join(".mcservermodgui").join("disabled.toml")
+    }
+
+    pub fn load(root: &Path) -> Self {
+        let Ok(s) = fs::read_to_string(Self::path(root)) else {return Self::default()};
+        toml::from_str(&s).unwrap_or_else(|e| {
+            eprintln!("couldn't read disabled.toml: {e}");
+            Self::default()
+        })
+    }
+
+    pub fn save(&self, root: &Path) -> Result<(), String> {
+        let s = toml::to_string_pretty(self).map_err(|e| format!("Couldn't serialize disabled mods: {e}"))?;
+        let path = Self::path(root);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Couldn't create {}: {e}", parent.display()))?;
+        }
+        fs::write(path, s).map_err(|e| format!("Couldn't write disabled.toml: {e}"))
+    }
+}
+
+pub fn read_jar_metadata(path: &Path) -> Result<InstalledMod, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut archive = ZipArchive::new(file).map_err(|e| e.to_string())?;
+    let mut m = _unknown_jar(path);
+
+    if let Some(s) = _read_entry(&mut archive, "fabric.mod.json") {
+        let v: serde_json::Value = serde_json::from_str(&s).map_err(|e| format!("bad fabric.mod.json: {e}"))?;
+        m.loader = Some(ModLoader::Fabric);
+        m.id = v["id"].as_str().map(str::to_string);
+        m.name = v["name"].as_str().or(v["id"].as_str()).unwrap_or(&m.name).to_string();
+        m.version = v["version"].as_str().unwrap_or_default().to_string();
+        m.minecraft_range = match &v["depends"]["minecraft"] {
+            serde_json::Value::String(s) => s.clone(),
+            serde_json::Value::Array(a) => a.iter().filter_map(|s| s.as_str()).collect::<Vec<_>>().join(" || "),
+            _ => String::new(),
+        };
+    } else if let Some((s, loader)) = _read_entry(&mut archive, "META-INF/neoforge.mods.toml").map(|s| (s, ModLoader::NeoForge))
+        .or_else(|| _read_entry(&mut archive, "META-INF/mods.toml").map(|s| (s, ModLoader::Forge))) {
+        let v: toml::Table = toml::from_str(&s).map_err(|e| format!("bad mods.toml: {e}"))?;
+        let first = v.get("mods").and_then(|m| m.as_array()).and_then(|a| a.first());
+        let mod_id = first.and_then(|f| f.get("modId")).and_then(|s| s.as_str()).unwrap_or_default();
+        let deps = v.get("dependencies").and_then(|d| d.get(mod_id)).and_then(|d| d.as_array());
+        let dep_range = |dep_id: &str| deps.and_then(|d| d.iter().find(|d| d.get("modId").and_then(|s| s.as_str()) == Some(dep_id)))
+            .and_then(|d| d.get("versionRange")).and_then(|s| s.as_str()).map(str::to_string);
+
+        // old neoforge versions still used mods.toml, but they depend on "neoforge" instead of "forge"
+        m.loader = Some(if dep_range("neoforge").is_some() {ModLoader::NeoForge} else {loader});
+        m.id = Some(mod_id.to_string()).filter(|s| !s.is_empty());
+        m.name = first.and_then(|f| f.get("displayName")).and_then(|s| s.as_str()).unwrap_or(mod_id).to_string();
+        m.version = first.and_then(|f| f.get("version")).and_then(|s| s.as_str()).unwrap_or_default().to_string();
+        if m.version.contains("${") {
+            m.version = _read_entry(&mut archive, "META-INF/MANIFEST.MF")
+                .and_then(|s| s.lines().find_map(|l| l.strip_prefix("Implementation-Version:").map(|v| v.trim().to_string())))
+                .unwrap_or_default();
+        }
+        m.minecraft_range = dep_range("minecraft").unwrap_or_default();
+    } else if let Some(s) = _read_entry(&mut archive, "velocity-plugin.json") {
+        let v: serde_json::Value = serde_json::from_str(&s).map_err(|e| format!("bad velocity-plugin.json: {e}"))?;
+        m.loader = Some(ModLoader::Velocity);
+        m.id = v["id"].as_str().map(str::to_string);
+        m.name = v["name"].as_str().or(v["id"].as_str()).unwrap_or(&m.name).to_string();
+        m.version = v["version"].as_str().unwrap_or_default().to_string();
+    } else if let Some(s) = _read_entry(&mut archive, "paper-plugin.yml").or_else(|| _read_entry(&mut archive, "plugin.yml")) {
+        let v: serde_yaml::Value = serde_yaml::from_str(&s).map_err(|e| format!("bad plugin.yml: {e}"))?;
+        let field = |key: &str| match &v[key] {
+            serde_yaml::Value::String(s) => Some(s.clone()),
+            serde_yaml::Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        };
+        m.loader = Some(ModLoader::Paper);
+        m.id = field("name");
+        m.name = field("name").unwrap_or(m.name);
+        m.version = field("version").unwrap_or_default();
+        m.minecraft_range = field("api-version").map(|v| format!(">={v}")).unwrap_or_default();
+    }
+    Ok(m)
+}
+
+fn _unknown_jar(path: &Path) -> InstalledMod {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy().trim_end_matches(".disabled").to_string();
+    InstalledMod {
+        name: file_name.trim_end_matches(".jar").to_string(),
+        file_name,
+        sha1: String::new(),
+        id: None,
+        version: String::new(),
+        loader: None,
+        minecraft_range: String::new(),
+        enabled: true,
+    }
+}
+
+fn _read_entry(archive: &mut ZipArchive<File>, name: &str) -> Option<String> {
+    let mut f = archive.by_name(name).ok()?;
+    let mut s = String::new();
+    f.read_to_string(&mut s).ok()?;
+    Some(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("mcservermodgui-installed-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("mods")).unwrap();
+        root
+    }
+
+    fn enabled(root: &Path, loader: ModLoader) -> Vec<(String, bool)> {
+        scan_mods_folder(root, loader).into_iter().map(|m| (m.file_name, m.enabled)).collect()
+    }
+
+    #[test]
+    fn disabled_jars_stay_disabled_when_moved_back_by_hand() {
+        let root = root("mods");
+        fs::write(root.join("mods/a.jar"), "a").unwrap();
+        fs::write(root.join("mods/b.jar"), "b").unwrap();
+        set_enabled(&root, ModLoader::Fabric, "a.jar", false).unwrap();
+        assert!(root.join("mods/a.jar.disabled").is_file());
+        assert_eq!(DisabledMods::load(&root).files, vec!["a.jar"]);
+
+        // something renames it back and disables b by hand
+        fs::rename(root.join("mods/a.jar.disabled"), root.join("mods/a.jar")).unwrap();
+        fs::rename(root.join("mods/b.jar"), root.join("mods/b.jar.disabled")).unwrap();
+        assert_eq!(enabled(&root, ModLoader::Fabric), vec![("a.jar".to_string(), false), ("b.jar".to_string(), false)]);
+        assert!(root.join("mods/a.jar.disabled").is_file());
+        assert_eq!(DisabledMods::load(&root).files, vec!["a.jar", "b.jar"]);
+
+        // and jars that are gone get forgotten
+        fs::remove_file(root.join("mods/a.jar.disabled")).unwrap();
+        set_enabled(&root, ModLoader::Fabric, "b.jar", true).unwrap();
+        assert_eq!(enabled(&root, ModLoader::Fabric), vec![("b.jar".to_string(), true)]);
+        assert!(DisabledMods::load(&root).files.is_empty());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn plugins_get_moved_out_of_the_folder() {
+        let root = root("plugins");
+        fs::create_dir_all(root.join("plugins")).unwrap();
+        fs::write(root.join("plugins/p.jar"), "p").unwrap();
+        set_enabled(&root, ModLoader::Paper, "p.jar", false).unwrap();
+        assert!(root.join(".mcservermodgui/disabled/p.jar").is_file());
+        fs::copy(root.join(".mcservermodgui/disabled/p.jar"), root.join("plugins/p.jar")).unwrap();
+        fs::remove_file(root.join(".mcservermodgui/disabled/p.jar")).unwrap();
+        assert_eq!(enabled(&root, ModLoader::Paper), vec![("p.jar".to_string(), false)]);
+        assert!(!root.join("plugins/p.jar").exists());
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,176 @@
+use std::{
+    collections::HashSet,
+    env, fmt, fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+};
+
+use iced::futures::channel::oneshot;
+
+use crate::util::server_installer;
+
+const JAVA_BIN: &str = if cfg!(windows) {"java.exe"} else {"java"};
+
+/// a java install found on this machine
+#[derive(Debug, Clone, PartialEq)]
+pub struct JavaRuntime {
+    pub path: String, // the java binary
+    pub version: String, // as `java -version` prints it, e.g. `21.0.2` or `1.8.0_392`
+    pub major: u32,
+}
+impl fmt::Display for JavaRuntime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Java {} ({})", self.version, self.path)
+    }
+}
+impl JavaRuntime {
+    /// old versions only run on exactly the java they were made for (forge breaks past 8),
+    /// from 16 (what mojang lists for 1.17) on newer java is fine
+    pub fn runs(&self, required: u32) -> bool {
+        self.major == required || (required >= 16 && self.major > required)
+    }
+}
+
+/// every java binary we can find, newest first. blocks while running `java -version` on each one
+pub fn discover() -> Vec<JavaRuntime> {
+    let mut seen = HashSet::new();
+    let mut runtimes: Vec<JavaRuntime> = _candidates().into_iter()
+        .filter(|p| p.is_file())
+        // the same install is usually reachable through JAVA_HOME, PATH and /usr/lib/jvm at once
+        .filter(|p| seen.insert(fs::canonicalize(p).unwrap_or_else(|_| p.clone())))
+        .filter_map(|p| probe(&p))
+        .collect();
+    runtimes.sort_by_key(|r| std::cmp::Reverse(r.major));
+    runtimes
+}
+
+/// `discover` on its own thread, so the ui doesn't wait on it
+pub async fn discover_in_background() -> Vec<JavaRuntime> {
+    let (tx, rx) = oneshot::channel();
+    thread::spawn(move || {let _ = tx.send(discover());});
+    rx.await.unwrap_or_default()
+}
+
+fn _candidates() -> Vec<PathBuf> {
+    let mut candidates = vec![];
+    if let Some(home) = env::var_os("JAVA_HOME") {
+        candidates.push(Path::new(&home).join("bin").join(JAVA_BIN));
+    }
+    if let Some(path) = env::var_os("PATH") {
+        candidates.extend(env::split_paths(&path).map(|dir| dir.join(JAVA_BIN)));
+    }
+
+    // folders that hold one jdk per subfolder
+    let mut roots: Vec<(PathBuf, &[&str])> = vec![
+        ("/usr/lib/jvm".into(), &["bin"]),
+        ("/usr/java".into(), &["bin"]),
+        ("/opt/java".into(), &["bin"]),
+        ("/Library/Java/JavaVirtualMachines".into(), &["Contents", "Home", "bin"]),
+    ];
+    if let Some(home) = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")) {
+        let home = PathBuf::from(home);
+        roots.push((home.join(".sdkman").join("candidates").join("java"), &["bin"]));
+        roots.push((home.join(".jdks"), &["bin"]));
+    }
+    if let Some(sdkman) = env::var_os("SDKMAN_CANDIDATES_DIR") {
+        roots.push((Path::new(&sdkman).join("java"), &["bin"]));
+    }
+    for var in ["ProgramFiles", "ProgramFiles(x86)"] {
+        if let Some(dir) = env::var_os(var) {
+            for vendor in ["Java", "Eclipse Adoptium", "Microsoft", "Zulu", "Amazon Corretto"] {
+                roots.push((Path::new(&dir).join(vendor), &["bin"]));
+            }
+        }
+    }
+
+    for (root, bin) in roots {
+        let Ok(entries) = fs::read_dir(&root) else {continue};
+        let mut dirs: Vec<PathBuf> = entries.filter_map(|e| e.ok().map(|e| e.path())).collect();
+        dirs.sort();
+        candidates.extend(dirs.into_iter().map(|d| bin.iter().fold(d, |p, b| p.join(b)).join(JAVA_BIN)));
+    }
+    candidates
+}
+
+/// runs `java -version` and reads the version out of what it prints (to stderr, for some reason)
+pub fn probe(java: &Path) -> Option<JavaRuntime> {
+    let output = Command::new(java).arg("-version").stdin(Stdio::null()).output().ok()?;
+    let text = String::from_utf8_lossy(&output.stderr);
+    let version = parse_version(&text)?;
+    Some(JavaRuntime { path: java.to_string_lossy().into_owned(), major: parse_major(&version)?, version })
+}
+
+/// `openjdk version "21.0.2" 2024-01-16` -> `21.0.2`
+pub fn parse_version(output: &str) -> Option<String> {
+    let line = output.lines().find(|l| l.contains(" version "))?;
+    let start = line.find('"')? + 1;
+    let end = start + line[start..].find('"')?;
+    Some(line[start..end].to_string())
+}
+
+/// `1.8.0_392` -> 8, `17.0.9` -> 17, `21` -> 21, `22-ea` -> 22
+pub fn parse_major(version: &str) -> Option<u32> {
+    let mut parts = version.split(['.', '_', '-', '+']);
+    match parts.next()?.parse().ok()? {
+        1 => parts.next()?.parse().ok(),
+        major => Some(major),
+    }
+}
+
+/// the java major version mojang's launcher would run `version` with. versions from before the launcher
+/// recorded it are all java 8
+pub async fn required_major(version: &str) -> Result<u32, String> {
+    Ok(_major_for(&server_installer::piston_version(version).await?))
+}
+
+fn _major_for(version: &server_installer::PistonVersion) -> u32 {
+    version.java_version.as_ref().map_or(8, |j| j.major_version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_from_java_version_output() {
+        let java8 = "openjdk version \"1.8.0_392\"\nOpenJDK Runtime Environment (Temurin)(build 1.8.0_392-b08)\n";
+        let java17 = "openjdk version \"17.0.9\" 2023-10-17\nOpenJDK Runtime Environment Temurin-17.0.9+9 (build 17.0.9+9)\n";
+        let java21 = "openjdk version \"21\" 2023-09-19\nOpenJDK Runtime Environment (build 21+35-2513)\n";
+        let oracle = "Picked up JAVA_TOOL_OPTIONS: -Dfile.encoding=UTF8\njava version \"22-ea\" 2024-03-19\n";
+        assert_eq!(parse_version(java8).as_deref(), Some("1.8.0_392"));
+        assert_eq!(parse_version(java17).as_deref(), Some("17.0.9"));
+        assert_eq!(parse_version(java21).as_deref(), Some("21"));
+        assert_eq!(parse_version(oracle).as_deref(), Some("22-ea"));
+        assert_eq!(parse_version("bash: java: command not found"), None);
+    }
+
+    #[test]
+    fn majors_from_versions() {
+        assert_eq!(parse_major("1.8.0_392"), Some(8));
+        assert_eq!(parse_major("17.0.9"), Some(17));
+        assert_eq!(parse_major("21"), Some(21));
+        assert_eq!(parse_major("22-ea"), Some(22));
+        assert_eq!(parse_major("1"), None);
+        assert_eq!(parse_major("openjdk"), None);
+    }
+
+    #[test]
+    fn required_major_comes_from_the_version_json() {
+        let version = |json: &str| _major_for(&serde_json::from_str(json).unwrap());
+        assert_eq!(version(r#"{"downloads": {}, "javaVersion": {"component": "java-runtime-delta", "majorVersion": 21}}"#), 21);
+        assert_eq!(version(r#"{"downloads": {}, "javaVersion": {"component": "jre-legacy", "majorVersion": 8}}"#), 8);
+        // versions from before the launcher recorded it
+        assert_eq!(version(r#"{"downloads": {"server": {"sha1": "a", "url": "b"}}}"#), 8);
+    }
+
+    #[test]
+    fn newer_java_only_runs_versions_that_need_16_or_more() {
+        let java = |major: u32| JavaRuntime { path: "java".to_string(), version: major.to_string(), major };
+        assert!(java(8).runs(8));
+        assert!(!java(17).runs(8));
+        assert!(java(17).runs(16));
+        assert!(java(21).runs(17));
+        assert!(!java(17).runs(21));
+    }
+}
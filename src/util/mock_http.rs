@@ -0,0 +1,65 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+/// a request the mock server got
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    pub path: String, // without the query
+    pub headers: Vec<(String, String)>, // names lowercased
+    pub body: String,
+}
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+}
+
+/// a tiny http server on localhost for tests. `handler` gets each request and answers with a body, or None for a 404.
+/// gives back the base url and every request made so far
+pub fn serve(handler: impl Fn(&Request) -> Option<Vec<u8>> + Send + 'static) -> (String, Arc<Mutex<Vec<Request>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let seen = requests.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else {continue};
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            if reader.read_line(&mut line).is_err() {continue}
+            let mut headers = Vec::new();
+            loop {
+                let mut header = String::new();
+                if reader.read_line(&mut header).is_err() || header == "\r\n" || header.is_empty() {break}
+                if let Some((name, value)) = header.split_once(':') {
+                    headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+                }
+            }
+            let length = headers.iter().find(|(n, _)| n == "content-length").and_then(|(_, v)| v.parse().ok()).unwrap_or(0);
+            let mut body = vec![0; length];
+            let _ = reader.read_exact(&mut body);
+
+            let mut parts = line.split_whitespace();
+            let request = Request {
+                method: parts.next().unwrap_or_default().to_string(),
+                path: parts.next().unwrap_or_default().split('?').next().unwrap_or_default().to_string(),
+                headers,
+                body: String::from_utf8_lossy(&body).into_owned(),
+            };
+            let response = handler(&request);
+            seen.lock().unwrap().push(request);
+            let (status, body) = match response {
+                Some(b) => ("200 OK", b),
+                None => ("404 Not Found", vec![]),
+            };
+            let _ = stream.write_all(format!("HTTP/1.1 {status}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n", body.len()).as_bytes());
+            let _ = stream.write_all(&body);
+        }
+    });
+    (base, requests)
+}
@@ -1,3 +1,18 @@
+pub mod bisect;
 pub mod circular;
+pub mod config_files;
+pub mod crash_triage;
+pub mod hashes;
+pub mod installed_mods;
+pub mod java;
+#[cfg(test)]
+pub mod mock_http;
+pub mod mod_backups;
+pub mod modpack;
 pub mod reqwests;
+pub mod server_installer;
+pub mod server_properties;
+pub mod server_registry;
+pub mod supervisor;
+pub mod updater;
 pub mod icon_pick_list;
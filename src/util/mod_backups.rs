@@ -0,0 +1,81 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// replaced and removed jars go to .mcservermodgui/backups as `<unix millis>-<file name>`,
+// so backing the same file up twice keeps both copies
+
+pub fn backups_dir(root: &Path) -> PathBuf {
+    root.join(".mcservermodgui").join("backups")
+}
+
+/// copies `path` into the backups, giving back the name of the copy
+pub fn back_up(root: &Path, path: &Path) -> Result<String, String> {
+    let (dest, name) = _new_backup(root, path)?;
+    fs::copy(path, dest).map_err(|e| format!("Couldn't back up {}: {e}", path.display()))?;
+    Ok(name)
+}
+
+/// moves `path` into the backups, giving back the name it got there
+pub fn move_to_backups(root: &Path, path: &Path) -> Result<String, String> {
+    let (dest, name) = _new_backup(root, path)?;
+    fs::rename(path, dest).map_err(|e| format!("Couldn't move {} to the backups: {e}", path.display()))?;
+    Ok(name)
+}
+
+/// the file name a backup was made from. backups from before they were timestamped are just the file name
+pub fn original_name(backup: &str) -> &str {
+    match backup.split_once('-') {
+        Some((stamp, name)) if stamp.len() >= 13 && stamp.bytes().all(|b| b.is_ascii_digit()) => name,
+        _ => backup,
+    }
+}
+
+fn _new_backup(root: &Path, path: &Path) -> Result<(PathBuf, String), String> {
+    let dir = backups_dir(root);
+    fs::create_dir_all(&dir).map_err(|e| format!("Couldn't create {}: {e}", dir.display()))?;
+    let file_name = path.file_name().ok_or_else(|| format!("{} isn't a file", path.display()))?.to_string_lossy();
+    let mut stamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis());
+    loop {
+        let name = format!("{stamp}-{file_name}");
+        if !dir.join(&name).exists() {
+            return Ok((dir.join(&name), name));
+        }
+        stamp += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backing_up_twice_keeps_both() {
+        let root = std::env::temp_dir().join(format!("mcservermodgui-backups-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let jar = root.join("foo-1.0.jar");
+
+        fs::write(&jar, "first").unwrap();
+        let first = back_up(&root, &jar).unwrap();
+        fs::write(&jar, "second").unwrap();
+        let second = move_to_backups(&root, &jar).unwrap();
+
+        assert_ne!(first, second);
+        assert!(!jar.exists());
+        assert_eq!(fs::read_to_string(backups_dir(&root).join(&first)).unwrap(), "first");
+        assert_eq!(fs::read_to_string(backups_dir(&root).join(&second)).unwrap(), "second");
+        assert_eq!((original_name(&first), original_name(&second)), ("foo-1.0.jar", "foo-1.0.jar"));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn old_backups_keep_their_name() {
+        assert_eq!(original_name("foo-1.0.jar"), "foo-1.0.jar");
+        assert_eq!(original_name("1760000000000-foo-1.0.jar"), "foo-1.0.jar");
+        assert_eq!(original_name("-foo.jar"), "-foo.jar");
+        assert_eq!(original_name("2-in-1.jar"), "2-in-1.jar");
+    }
+}
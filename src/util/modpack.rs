@@ -0,0 +1,578 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{Read, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use iced::futures::{SinkExt, Stream, StreamExt};
+use zip::{ZipArchive, ZipWriter, write::SimpleFileOptions};
+
+use crate::{CurseforgeConfig, ModLoader, ProgramData, util::reqwests::{self, DownloadEvent}};
+use crate::states::mod_downloader::curseforge;
+use crate::util::server_installer;
+
+/// a modpack read far enough to set up a server from it
+#[derive(Debug, Clone)]
+pub struct Modpack {
+    pub archive: PathBuf,
+    pub name: String,
+    pub version: String,
+    pub loader: ModLoader,
+    pub minecraft: String,
+    pub loader_version: Option<String>,
+    pub files: Vec<PackFile>, // only the ones the server needs
+    pub left_out: usize, // client only files that aren't installed
+    pub override_dirs: Vec<String>, // folders in the archive that get copied over the server, later ones win
+}
+
+/// a file the pack downloads rather than ships
+#[derive(Debug, Clone)]
+pub struct PackFile {
+    pub path: String, // relative to the server root
+    pub urls: Vec<String>, // tried in order
+    pub hashes: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum PackEvent {
+    Downloading { current: usize, total: usize, path: String },
+    Extracting,
+    Finished(Result<(), String>),
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MrpackIndex {
+    format_version: u32,
+    game: String,
+    version_id: String,
+    name: String,
+    files: Vec<MrpackFile>,
+    dependencies: HashMap<String, String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MrpackFile {
+    path: String,
+    hashes: HashMap<String, String>,
+    env: Option<MrpackEnv>,
+    downloads: Vec<String>,
+    #[serde(default)]
+    file_size: u64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct MrpackEnv {
+    #[serde(default)]
+    client: String,
+    server: String,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CurseforgeManifest {
+    minecraft: CurseforgeMinecraft,
+    name: String,
+    #[serde(default)]
+    version: String,
+    files: Vec<CurseforgeFile>,
+    #[serde(default = "_default_overrides")]
+    overrides: String,
+}
+
+fn _default_overrides() -> String {
+    "overrides".to_string()
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CurseforgeMinecraft {
+    version: String,
+    mod_loaders: Vec<CurseforgeModLoader>,
+}
+
+#[derive(serde::Deserialize)]
+struct CurseforgeModLoader {
+    id: String, // like `forge-47.2.0`
+    #[serde(default)]
+    primary: bool,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CurseforgeFile {
+    #[serde(rename = "fileID")]
+    file_id: u64,
+}
+
+/// reads a .mrpack or a curseforge pack zip. curseforge packs only list file ids, so those are looked up with `curseforge`
+pub async fn load(path: PathBuf, curseforge: CurseforgeConfig) -> Result<Modpack, String> {
+    let file = File::open(&path).map_err(|e| format!("Couldn't open {}: {e}", path.display()))?;
+    let archive = ZipArchive::new(file).map_err(|e| format!("{} isn't a modpack: {e}", path.display()))?;
+    if archive.index_for_name("modrinth.index.json").is_some() {
+        read_mrpack(&path)
+    } else if archive.index_for_name("manifest.json").is_some() {
+        read_curseforge(&path, curseforge).await
+    } else {
+        Err(format!("{} has neither a modrinth.index.json nor a curseforge manifest.json", path.display()))
+    }
+}
+
+fn _read_json<T: serde::de::DeserializeOwned>(path: &Path, name: &str) -> Result<T, String> {
+    let file = File::open(path).map_err(|e| format!("Couldn't open {}: {e}", path.display()))?;
+    let mut archive = ZipArchive::new(file).map_err(|e| format!("{} isn't a modpack: {e}", path.display()))?;
+    let mut json = String::new();
+    archive.by_name(name)
+        .map_err(|_| format!("{} has no {name}", path.display()))?
+        .read_to_string(&mut json)
+        .map_err(|e| format!("Couldn't read {name}: {e}"))?;
+    let des = &mut serde_json::Deserializer::from_str(&json);
+    serde_path_to_error::deserialize(des).map_err(|e| format!("error parsing {name} at {}: {e}", e.path()))
+}
+
+/// reads `manifest.json` out of a curseforge pack and looks up where each of its files downloads from
+pub async fn read_curseforge(path: &Path, config: CurseforgeConfig) -> Result<Modpack, String> {
+    let manifest: CurseforgeManifest = _read_json(path, "manifest.json")?;
+    let loader_id = manifest.minecraft.mod_loaders.iter().find(|l| l.primary).or(manifest.minecraft.mod_loaders.first())
+        .map(|l| l.id.as_str())
+        .ok_or("The pack doesn't say which mod loader it needs")?;
+    let (loader, loader_version) = match loader_id.split_once('-') {
+        Some(("fabric", v)) => (ModLoader::Fabric, v),
+        Some(("neoforge", v)) => (ModLoader::NeoForge, v),
+        Some(("forge", v)) => (ModLoader::Forge, v),
+        Some(("quilt", _)) => return Err("Quilt packs aren't supported, there's no quilt server install yet".to_string()),
+        _ => return Err(format!("The pack needs {loader_id}, which can't be installed here")),
+    };
+
+    if config.api_key.is_none() {
+        return Err("CurseForge packs only list their files by id. Enter a CurseForge API key so they can be looked up".to_string());
+    }
+    let ids = manifest.files.iter().map(|f| f.file_id).collect::<Vec<_>>();
+    let resolved = if ids.is_empty() {vec![]} else {curseforge::resolve_pack_files(config, ids).await?};
+    let left_out = resolved.iter().filter(|f| f.client_only).count();
+    let manual = resolved.iter()
+        .filter(|f| !f.client_only)
+        .filter_map(|f| Some(format!("{} ({})", f.file_name, f.manual_url.as_ref()?)))
+        .collect::<Vec<_>>();
+    if !manual.is_empty() {
+        return Err(format!(
+            "These files can't be downloaded here, their authors only allow it from CurseForge. Download them yourself, put them in {}/ and leave them out of the pack:\n{}",
+            loader.mods_folder(),
+            manual.join("\n"),
+        ));
+    }
+    let files = resolved.into_iter()
+        .filter(|f| !f.client_only)
+        .map(|f| PackFile { path: format!("{}/{}", loader.mods_folder(), f.file_name), urls: vec![f.url], hashes: f.hashes })
+        .collect();
+    Ok(Modpack {
+        archive: path.to_path_buf(),
+        name: manifest.name,
+        version: manifest.version,
+        loader,
+        minecraft: manifest.minecraft.version,
+        loader_version: Some(loader_version.to_string()),
+        files,
+        left_out,
+        override_dirs: vec![manifest.overrides],
+    })
+}
+
+/// reads `modrinth.index.json` out of a .mrpack
+pub fn read_mrpack(path: &Path) -> Result<Modpack, String> {
+    let index: MrpackIndex = _read_json(path, "modrinth.index.json")?;
+
+    if index.format_version != 1 {
+        return Err(format!("Unsupported .mrpack format version {}", index.format_version));
+    }
+    if index.game != "minecraft" {
+        return Err(format!("This pack is for {}, not minecraft", index.game));
+    }
+    let minecraft = index.dependencies.get("minecraft").cloned().ok_or("The pack doesn't say which minecraft version it's for")?;
+    let (loader, loader_version) = if let Some(v) = index.dependencies.get("fabric-loader") {
+        (ModLoader::Fabric, v)
+    } else if let Some(v) = index.dependencies.get("neoforge") {
+        (ModLoader::NeoForge, v)
+    } else if let Some(v) = index.dependencies.get("forge") {
+        (ModLoader::Forge, v)
+    } else if index.dependencies.contains_key("quilt-loader") {
+        return Err("Quilt packs aren't supported, there's no quilt server install yet".to_string());
+    } else {
+        return Err("The pack doesn't need a mod loader that can be installed here".to_string());
+    };
+
+    let (files, client_only): (Vec<_>, Vec<_>) = index.files.into_iter()
+        .partition(|f| f.env.as_ref().is_none_or(|e| e.server != "unsupported"));
+    let files = files.into_iter()
+        .map(|f| PackFile { path: f.path, urls: f.downloads, hashes: f.hashes })
+        .collect();
+    Ok(Modpack {
+        archive: path.to_path_buf(),
+        name: index.name,
+        version: index.version_id,
+        loader,
+        minecraft,
+        loader_version: Some(loader_version.clone()),
+        files,
+        left_out: client_only.len(),
+        override_dirs: vec!["overrides".to_string(), "server-overrides".to_string()],
+    })
+}
+
+/// `rel` under `root`, unless it would end up somewhere else
+pub fn safe_join(root: &Path, rel: &str) -> Result<PathBuf, String> {
+    let rel = Path::new(rel);
+    if rel.components().any(|c| !matches!(c, Component::Normal(_) | Component::CurDir)) {
+        return Err(format!("{} points outside the server folder", rel.display()));
+    }
+    Ok(root.join(rel))
+}
+
+/// `name` in `dir`, as long as it's just a file name and not a path
+pub fn join_file_name(dir: &Path, name: &str) -> Result<PathBuf, String> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => Ok(dir.join(name)),
+        _ => Err(format!("{name} isn't a plain file name")),
+    }
+}
+
+/// downloads every file of `pack` into `root`, checking each against its hashes, then copies the overrides over
+pub fn install(root: PathBuf, pack: Modpack) -> impl Stream<Item = PackEvent> {
+    iced::stream::channel(16, async move |mut output| {
+        let result = _install(&root, &pack, &mut output).await;
+        let _ = output.send(PackEvent::Finished(result)).await;
+    })
+}
+
+async fn _install(root: &Path, pack: &Modpack, output: &mut iced::futures::channel::mpsc::Sender<PackEvent>) -> Result<(), String> {
+    let quarantine = root.join(".mcservermodgui").join("quarantine");
+    for (i, file) in pack.files.iter().enumerate() {
+        let _ = output.send(PackEvent::Downloading { current: i, total: pack.files.len(), path: file.path.clone() }).await;
+        let path = safe_join(root, &file.path)?;
+        let mut last_err = format!("{} has no download links", file.path);
+        for url in &file.urls {
+            match _download(url, &path, &file.hashes, &quarantine).await {
+                Ok(()) => {
+                    last_err.clear();
+                    break;
+                }
+                Err(err) => last_err = err,
+            }
+        }
+        if !last_err.is_empty() {
+            return Err(format!("Couldn't download {}: {last_err}", file.path));
+        }
+    }
+    let _ = output.send(PackEvent::Extracting).await;
+    extract_overrides(&pack.archive, root, &pack.override_dirs)
+}
+
+async fn _download(url: &str, path: &Path, hashes: &HashMap<String, String>, quarantine: &Path) -> Result<(), String> {
+    let mut events = Box::pin(reqwests::download_file(url.to_string(), path.to_path_buf(), hashes.clone(), quarantine.to_path_buf()));
+    while let Some(event) = events.next().await {
+        if let DownloadEvent::Finished(result) = event {
+            return result.map(|_| ());
+        }
+    }
+    Err("the download stopped without finishing".to_string())
+}
+
+/// copies everything under each of `dirs` in the archive into `root`, in order
+pub fn extract_overrides(archive: &Path, root: &Path, dirs: &[String]) -> Result<(), String> {
+    let file = File::open(archive).map_err(|e| format!("Couldn't open {}: {e}", archive.display()))?;
+    let mut zip = ZipArchive::new(file).map_err(|e| format!("Couldn't read {}: {e}", archive.display()))?;
+    for dir in dirs {
+        let prefix = format!("{dir}/");
+        for i in 0..zip.len() {
+            let mut entry = zip.by_index(i).map_err(|e| format!("Couldn't read {}: {e}", archive.display()))?;
+            let Some(rel) = entry.name().strip_prefix(&prefix).map(str::to_string) else {continue};
+            if entry.is_dir() || rel.is_empty() {continue}
+            let path = safe_join(root, &rel)?;
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|e| format!("Couldn't create {}: {e}", parent.display()))?;
+            }
+            let mut bytes = Vec::with_capacity(entry.size() as usize);
+            entry.read_to_end(&mut bytes).map_err(|e| format!("Couldn't extract {rel}: {e}"))?;
+            fs::write(&path, bytes).map_err(|e| format!("Couldn't write {}: {e}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// an enabled jar going into an exported pack
+#[derive(Debug, Clone)]
+pub struct ExportJar {
+    pub file_name: String,
+    pub download: Option<ExportDownload>, // None when modrinth doesn't know the jar, so it's shipped in the pack instead
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportDownload {
+    pub url: String,
+    pub hashes: HashMap<String, String>,
+    pub size: u64,
+    pub client_side: String, // as modrinth has them on the project
+    pub server_side: String,
+}
+
+/// writes a .mrpack of the server to `dest` for players to import. gives back how many jars are downloaded and how many are shipped
+pub fn export_mrpack(root: &Path, dest: &Path, program_data: &ProgramData, pack_version: &str, jars: &[ExportJar]) -> Result<(usize, usize), String> {
+    let (loader_key, loader_version) = installed_loader_version(root, program_data)?;
+    let mods_folder = program_data.loader.mods_folder();
+    let mut files = Vec::new();
+    let mut shipped = Vec::new();
+    for jar in jars {
+        // the index needs both of these, anything less has to be shipped
+        match &jar.download {
+            Some(d) if d.hashes.contains_key("sha1") && d.hashes.contains_key("sha512") => files.push(MrpackFile {
+                path: format!("{mods_folder}/{}", jar.file_name),
+                hashes: d.hashes.iter().filter(|(k, _)| matches!(k.as_str(), "sha1" | "sha512")).map(|(k, v)| (k.clone(), v.clone())).collect(),
+                env: Some(MrpackEnv { client: _env_side(&d.client_side), server: _env_side(&d.server_side) }),
+                downloads: vec![d.url.clone()],
+                file_size: d.size,
+            }),
+            _ => shipped.push(jar.file_name.as_str()),
+        }
+    }
+    let index = MrpackIndex {
+        format_version: 1,
+        game: "minecraft".to_string(),
+        version_id: pack_version.to_string(),
+        name: program_data.name.clone(),
+        dependencies: HashMap::from([
+            ("minecraft".to_string(), program_data.version.id.clone()),
+            (loader_key.to_string(), loader_version),
+        ]),
+        files,
+    };
+    let json = serde_json::to_string_pretty(&index).map_err(|e| format!("Couldn't serialize modrinth.index.json: {e}"))?;
+
+    let result = _write_mrpack(root, dest, &json, mods_folder, &shipped);
+    if result.is_err() {
+        let _ = fs::remove_file(dest);
+    }
+    result.map(|_| (index.files.len(), shipped.len()))
+}
+
+fn _write_mrpack(root: &Path, dest: &Path, index: &str, mods_folder: &str, shipped: &[&str]) -> Result<(), String> {
+    let file = File::create(dest).map_err(|e| format!("Couldn't create {}: {e}", dest.display()))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default();
+    let err = |e: zip::result::ZipError| format!("Couldn't write {}: {e}", dest.display());
+    zip.start_file("modrinth.index.json", options).map_err(err)?;
+    zip.write_all(index.as_bytes()).map_err(|e| format!("Couldn't write {}: {e}", dest.display()))?;
+
+    let mut extra = shipped.iter().map(|f| format!("{mods_folder}/{f}")).collect::<Vec<_>>();
+    extra.extend(_files_under(root, Path::new("config")));
+    for rel in extra {
+        let bytes = fs::read(root.join(&rel)).map_err(|e| format!("Couldn't read {rel}: {e}"))?;
+        zip.start_file(format!("overrides/{rel}"), options).map_err(err)?;
+        zip.write_all(&bytes).map_err(|e| format!("Couldn't write {}: {e}", dest.display()))?;
+    }
+    zip.finish().map_err(err)?;
+    Ok(())
+}
+
+/// every file under `rel`, as paths relative to `root` with `/` between folders
+fn _files_under(root: &Path, rel: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(root.join(rel)) else {return vec![]};
+    let mut files = Vec::new();
+    for entry in entries.flatten() {
+        let rel = rel.join(entry.file_name());
+        if entry.path().is_dir() {
+            files.extend(_files_under(root, &rel));
+        } else {
+            files.push(rel.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/"));
+        }
+    }
+    files.sort();
+    files
+}
+
+/// modrinth projects can say "unknown", which a pack can't
+fn _env_side(side: &str) -> String {
+    match side {
+        "required" | "optional" | "unsupported" => side.to_string(),
+        _ => "required".to_string(),
+    }
+}
+
+/// the dependency key and version of the loader set up in `root`, going by what its installer left behind
+pub fn installed_loader_version(root: &Path, program_data: &ProgramData) -> Result<(&'static str, String), String> {
+    let game_version = &program_data.version.id;
+    let found = match program_data.loader {
+        ModLoader::Fabric => program_data.server_jar.as_deref()
+            .and_then(|j| j.split("-loader.").nth(1))
+            .and_then(|rest| rest.split("-launcher.").next())
+            .map(|v| ("fabric-loader", v.to_string())),
+        ModLoader::Forge => _library_versions(root, "net/minecraftforge/forge").into_iter()
+            .find_map(|v| v.strip_prefix(&format!("{game_version}-")).map(str::to_string))
+            .map(|v| ("forge", v)),
+        ModLoader::NeoForge => {
+            let (artifact, prefix) = server_installer::neoforge_artifact(game_version);
+            _library_versions(root, &format!("net/neoforged/{artifact}")).into_iter()
+                .rfind(|v| v.starts_with(&prefix))
+                // packs give 1.20.1's builds without the minecraft version, the way the forge ones are
+                .map(|v| ("neoforge", if artifact == "forge" {v[prefix.len()..].to_string()} else {v}))
+        }
+        _ => return Err(format!("{} servers can't be exported as a modpack, only Fabric, Forge and NeoForge ones", program_data.loader)),
+    };
+    found.ok_or_else(|| format!("Couldn't tell which {} version is installed. Install the server from setup so it can be found", program_data.loader))
+}
+
+/// the versions of a library the forge style installers put under `libraries/`, oldest first
+fn _library_versions(root: &Path, group: &str) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(root.join("libraries").join(group)) else {return vec![]};
+    let mut versions = entries.flatten()
+        .filter(|e| e.path().is_dir())
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    versions.sort_by_key(|v| v.split(|c: char| !c.is_ascii_digit()).filter_map(|n| n.parse::<u32>().ok()).collect::<Vec<_>>());
+    versions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{hashes::sha1_hex, mock_http};
+
+    const MANIFEST: &str = r#"{"minecraft": {"version": "1.20.1", "modLoaders": [{"id": "forge-47.2.0", "primary": true}]},
+        "manifestType": "minecraftModpack", "manifestVersion": 1, "name": "CF Pack", "version": "3.1", "author": "someone",
+        "files": [{"projectID": 1, "fileID": 100, "required": true}, {"projectID": 2, "fileID": 200, "required": true},
+                  {"projectID": 3, "fileID": 300, "required": true}],
+        "overrides": "overrides"}"#;
+
+    /// a curseforge api with three pack files: a server mod, a mod tagged client only and a resource pack.
+    /// `server_url` is the server mod's download url, json null for an author who opted out of third party downloads
+    fn mock_curseforge(server_url: impl Fn(&str) -> String + Send + 'static) -> (String, std::sync::Arc<std::sync::Mutex<Vec<mock_http::Request>>>) {
+        mock_http::serve(move |r| {
+            let base = r.header("host").map(|h| format!("http://{h}")).unwrap_or_default();
+            let body = match (r.method.as_str(), r.path.as_str()) {
+                ("POST", "/v1/mods/files") => format!(r#"{{"data": [
+                    {{"id": 100, "modId": 1, "displayName": "A", "fileName": "a.jar", "downloadUrl": {}, "fileLength": 5,
+                      "hashes": [{{"value": "{}", "algo": 1}}], "gameVersions": ["1.20.1", "Forge", "Server", "Client"], "dependencies": []}},
+                    {{"id": 200, "modId": 2, "displayName": "B", "fileName": "b.jar", "downloadUrl": "{base}/dl/b.jar", "fileLength": 5,
+                      "hashes": [], "gameVersions": ["1.20.1", "Forge", "Client"], "dependencies": []}},
+                    {{"id": 300, "modId": 3, "displayName": "C", "fileName": "c.zip", "downloadUrl": "{base}/dl/c.zip", "fileLength": 5,
+                      "hashes": [], "gameVersions": ["1.20.1"], "dependencies": []}}
+                ]}}"#, server_url(&base), sha1_hex(b"jar-a")),
+                ("POST", "/v1/mods") => r#"{"data": [{"id": 1, "classId": 6, "name": "A", "slug": "a", "summary": ""},
+                    {"id": 2, "classId": 6, "name": "B", "slug": "b", "summary": ""},
+                    {"id": 3, "classId": 12, "name": "C", "slug": "c", "summary": ""}]}"#.to_string(),
+                ("GET", "/dl/a.jar") => "jar-a".to_string(),
+                _ => return None,
+            };
+            Some(body.into_bytes())
+        })
+    }
+
+    fn write_pack(dir: &Path) -> PathBuf {
+        let path = dir.join("pack.zip");
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        let options = SimpleFileOptions::default();
+        for (name, contents) in [("manifest.json", MANIFEST), ("overrides/config/a.toml", "one"), ("other/ignored.txt", "two")] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+        path
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mcservermodgui-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn curseforge_pack_installs_server_files_and_overrides() {
+        let dir = temp_dir("cfpack");
+        let pack_path = write_pack(&dir);
+        let (base, requests) = mock_curseforge(|base| format!(r#""{base}/dl/a.jar""#));
+        let config = CurseforgeConfig { api_key: Some("test-key".to_string()), api_url: Some(base) };
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let pack = runtime.block_on(load(pack_path, config)).unwrap();
+
+        assert_eq!((pack.name.as_str(), pack.version.as_str(), pack.minecraft.as_str()), ("CF Pack", "3.1", "1.20.1"));
+        assert_eq!((pack.loader, pack.loader_version.as_deref()), (ModLoader::Forge, Some("47.2.0")));
+        // the client only mod and the resource pack get left out
+        assert_eq!(pack.left_out, 2);
+        assert_eq!(pack.files.len(), 1);
+        assert_eq!(pack.files[0].path, "mods/a.jar");
+        {
+            let requests = requests.lock().unwrap();
+            let body = |path: &str| serde_json::from_str::<serde_json::Value>(&requests.iter().find(|r| r.path == path).unwrap().body).unwrap();
+            assert_eq!(body("/v1/mods/files"), serde_json::json!({"fileIds": [100, 200, 300]}));
+            assert_eq!(body("/v1/mods"), serde_json::json!({"modIds": [1, 2, 3]}));
+            assert!(requests.iter().all(|r| !r.path.starts_with("/v1/") || r.header("x-api-key") == Some("test-key")));
+        }
+
+        let server = dir.join("server");
+        let events = runtime.block_on(install(server.clone(), pack).collect::<Vec<_>>());
+        assert!(matches!(events.last(), Some(PackEvent::Finished(Ok(())))), "{events:?}");
+        assert_eq!(fs::read(server.join("mods/a.jar")).unwrap(), b"jar-a");
+        assert_eq!(fs::read_to_string(server.join("config/a.toml")).unwrap(), "one");
+        assert!(!server.join("mods/b.jar").exists());
+        assert!(!server.join("other").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn opted_out_server_files_fail_the_import() {
+        let dir = temp_dir("cfpack-manual");
+        let (base, _) = mock_curseforge(|_| "null".to_string());
+        let config = CurseforgeConfig { api_key: Some("test-key".to_string()), api_url: Some(base) };
+        let err = tokio::runtime::Runtime::new().unwrap().block_on(load(write_pack(&dir), config)).unwrap_err();
+        assert!(err.contains("a.jar (https://www.curseforge.com/projects/1)"), "{err}");
+        // client only files don't matter for a server, so they aren't listed
+        assert!(!err.contains("b.jar"), "{err}");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn curseforge_pack_needs_a_key() {
+        let dir = temp_dir("cfpack-nokey");
+        let (base, requests) = mock_curseforge(|_| "null".to_string());
+        let config = CurseforgeConfig { api_key: None, api_url: Some(base) };
+        let err = tokio::runtime::Runtime::new().unwrap().block_on(load(write_pack(&dir), config)).unwrap_err();
+        assert!(err.contains("API key"), "{err}");
+        assert!(requests.lock().unwrap().is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn file_names_stay_in_their_folder() {
+        let dir = Path::new("mods");
+        assert_eq!(join_file_name(dir, "a.jar").unwrap(), dir.join("a.jar"));
+        for name in ["../a.jar", "sub/a.jar", "/etc/passwd", "..", "", "."] {
+            assert!(join_file_name(dir, name).is_err(), "{name}");
+        }
+        assert!(safe_join(dir, "../a.jar").is_err());
+        assert!(safe_join(dir, "config/a.toml").is_ok());
+    }
+
+    #[test]
+    fn neoforge_version_is_the_one_for_the_server() {
+        let dir = temp_dir("neoforge");
+        for lib in ["neoforged/neoforge/21.0.167", "neoforged/neoforge/21.1.77", "neoforged/neoforge/21.1.9", "neoforged/forge/1.20.1-47.1.106"] {
+            fs::create_dir_all(dir.join("libraries/net").join(lib)).unwrap();
+        }
+        let program_data = |version: &str| ProgramData {
+            name: "server".to_string(),
+            loader: ModLoader::NeoForge,
+            version: crate::MinecraftVersion { id: version.to_string(), kind: crate::VersionKind::Release },
+            curseforge: CurseforgeConfig::default(),
+            server_jar: None,
+            java: None,
+        };
+        assert_eq!(installed_loader_version(&dir, &program_data("1.21")).unwrap().1, "21.0.167");
+        assert_eq!(installed_loader_version(&dir, &program_data("1.21.1")).unwrap().1, "21.1.77");
+        assert_eq!(installed_loader_version(&dir, &program_data("1.20.1")).unwrap().1, "47.1.106");
+        assert!(installed_loader_version(&dir, &program_data("1.20.4")).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
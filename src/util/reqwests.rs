@@ -1,5 +1,9 @@
+use std::{collections::HashMap, fs, path::PathBuf};
+use iced::futures::{SinkExt, Stream};
 use itertools::Itertools;
 
+use crate::{CurseforgeConfig, util::hashes};
+
 #[derive(Debug, Clone)]
 pub struct ImageData {
     pub url: String,
@@ -30,6 +34,62 @@ pub async fn download_image(url: String) -> Result<ImageData, String> {
     }
 }
 
+#[derive(Debug, Clone)]
+pub enum DownloadEvent {
+    Progressed { downloaded: u64, total: Option<u64> },
+    Finished(Result<PathBuf, String>),
+}
+
+/// streams `url` into memory, reporting progress as it goes, then checks it against `hashes` and writes it out to `path`.
+/// the file is written to a `.part` sibling first and renamed so a half-written jar never sits in the mods folder.
+/// files that fail verification are written to `quarantine_dir` instead
+pub fn download_file(url: String, path: PathBuf, hashes: HashMap<String, String>, quarantine_dir: PathBuf) -> impl Stream<Item = DownloadEvent> {
+    download_verified(url, path, quarantine_dir, move |bytes| hashes::verify_hashes(bytes, &hashes))
+}
+
+/// `download_file`, but with the check done by `verify` rather than against a set of hashes
+pub fn download_verified(url: String, path: PathBuf, quarantine_dir: PathBuf, verify: impl Fn(&[u8]) -> Result<(), String> + Send + 'static) -> impl Stream<Item = DownloadEvent> {
+    iced::stream::channel(16, async move |mut output| {
+        let result = _download_to_path(&url, &path, &verify, &quarantine_dir, &mut output).await;
+        let _ = output.send(DownloadEvent::Finished(result.map(|_| path))).await;
+    })
+}
+
+async fn _download_to_path(url: &str, path: &PathBuf, verify: &impl Fn(&[u8]) -> Result<(), String>, quarantine_dir: &PathBuf, output: &mut iced::futures::channel::mpsc::Sender<DownloadEvent>) -> Result<(), String> {
+    let mut response = crate::REQ_CLIENT
+        .get(url)
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+
+    if !response.status().is_success() {
+        return Err(format!("Error {} from url: {url}", response.status()));
+    }
+
+    let total = response.content_length();
+    let mut bytes: Vec<u8> = Vec::with_capacity(total.unwrap_or(0) as usize);
+    while let Some(chunk) = response.chunk().await.map_err(|err| err.to_string())? {
+        bytes.extend_from_slice(&chunk);
+        let _ = output.send(DownloadEvent::Progressed { downloaded: bytes.len() as u64, total }).await;
+    }
+
+    if let Err(err) = verify(&bytes) {
+        let file_name = path.file_name().unwrap_or_default();
+        fs::create_dir_all(quarantine_dir).map_err(|e| format!("Couldn't create {}: {e}", quarantine_dir.display()))?;
+        let quarantined = quarantine_dir.join(file_name);
+        fs::write(&quarantined, &bytes).map_err(|e| format!("Couldn't write {}: {e}", quarantined.display()))?;
+        return Err(format!("{} failed verification ({err}), quarantined to {}", file_name.to_string_lossy(), quarantined.display()));
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|err| format!("Couldn't create {}: {err}", parent.display()))?;
+    }
+    let part = path.with_extension("part");
+    fs::write(&part, &bytes).map_err(|err| format!("Couldn't write {}: {err}", part.display()))?;
+    fs::rename(&part, path).map_err(|err| format!("Couldn't move {} into place: {err}", path.display()))?;
+    Ok(())
+}
+
 pub async fn search_mods(
     offset: u64,
     query: Option<String>,
@@ -180,6 +240,96 @@ pub async fn get_mod_versions(version_ids: Vec<String>) -> Result<Vec<u8>, Strin
     }
 }
 
+/// looks up installed files on modrinth by their hash. the response is an object of `hash -> version`,
+/// and hashes modrinth doesn't know about are just left out
+pub async fn get_versions_from_hashes(hashes: Vec<String>, algorithm: &'static str) -> Result<Vec<u8>, String> {
+    let body = serde_json::json!({
+        "hashes": hashes,
+        "algorithm": algorithm,
+    });
+
+    let response = crate::REQ_CLIENT
+        .post("https://api.modrinth.com/v2/version_files")
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body.to_string())
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+
+    if !response.status().is_success() {
+        Err(format!(
+            "Error {} from version_files lookup of {} hashes",
+            response.status(),
+            hashes.len()
+        ))
+    } else {
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|err| err.to_string())?
+            .to_vec();
+        Ok(bytes)
+    }
+}
+
+/// like [`get_versions_from_hashes`], but gives back the newest version of each file's project
+/// that matches `loaders` and `game_versions` instead of the version the file belongs to
+pub async fn get_latest_versions_from_hashes(hashes: Vec<String>, algorithm: &'static str, loaders: Vec<String>, game_versions: Vec<String>) -> Result<Vec<u8>, String> {
+    let body = serde_json::json!({
+        "hashes": hashes,
+        "algorithm": algorithm,
+        "loaders": loaders,
+        "game_versions": game_versions,
+    });
+
+    let response = crate::REQ_CLIENT
+        .post("https://api.modrinth.com/v2/version_files/update")
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body.to_string())
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+
+    if !response.status().is_success() {
+        Err(format!(
+            "Error {} from update check of {} hashes",
+            response.status(),
+            hashes.len()
+        ))
+    } else {
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|err| err.to_string())?
+            .to_vec();
+        Ok(bytes)
+    }
+}
+
+pub async fn fetch_mods(ids: Vec<String>) -> Result<Vec<u8>, String> {
+    let response = crate::REQ_CLIENT
+        .get("https://api.modrinth.com/v2/projects")
+        .query(&[("ids", format!("[{}]", ids.iter().map(|s| format!("\"{s}\"")).join(",")))])
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+
+    if !response.status().is_success() {
+        Err(format!(
+            "Error {} from mods fetch with ids {:?}",
+            response.status(),
+            ids
+        ))
+    } else {
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|err| err.to_string())?
+            .to_vec();
+        Ok(bytes)
+    }
+}
+
 pub async fn get_categories() -> Result<Vec<u8>, String> {
     let response = crate::REQ_CLIENT
         .get("https://api.modrinth.com/v2/tag/category")
@@ -200,4 +350,108 @@ pub async fn get_categories() -> Result<Vec<u8>, String> {
             .to_vec();
         Ok(bytes)
     }
-}
\ No newline at end of file
+}
+
+pub async fn curseforge_get(config: CurseforgeConfig, path: String, args: Vec<(&'static str, String)>) -> Result<Vec<u8>, String> {
+    let Some(key) = &config.api_key else {
+        return Err(NO_CURSEFORGE_KEY.to_string());
+    };
+
+    let response = crate::REQ_CLIENT
+        .get(format!("{}{path}", config.base_url()))
+        .header("x-api-key", key)
+        .query(&args)
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+
+    if !response.status().is_success() {
+        Err(format!(
+            "Error {} from curseforge {path} with args {:?}",
+            response.status(),
+            args
+        ))
+    } else {
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|err| err.to_string())?
+            .to_vec();
+        Ok(bytes)
+    }
+}
+
+pub async fn curseforge_post(config: CurseforgeConfig, path: String, body: serde_json::Value) -> Result<Vec<u8>, String> {
+    let Some(key) = &config.api_key else {
+        return Err(NO_CURSEFORGE_KEY.to_string());
+    };
+
+    let response = crate::REQ_CLIENT
+        .post(format!("{}{path}", config.base_url()))
+        .header("x-api-key", key)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body.to_string())
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+
+    if !response.status().is_success() {
+        Err(format!(
+            "Error {} from curseforge {path}",
+            response.status(),
+        ))
+    } else {
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|err| err.to_string())?
+            .to_vec();
+        Ok(bytes)
+    }
+}
+
+const NO_CURSEFORGE_KEY: &str = "No CurseForge API key configured.\nAdd `api_key` under [curseforge] in .mcservermodgui/mcservermodgui.toml";
+
+pub async fn hangar_get(path: String, args: Vec<(&'static str, String)>) -> Result<Vec<u8>, String> {
+    let response = crate::REQ_CLIENT
+        .get(format!("https://hangar.papermc.io/api/v1{path}"))
+        .query(&args)
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+
+    if !response.status().is_success() {
+        Err(format!(
+            "Error {} from hangar {path} with args {:?}",
+            response.status(),
+            args
+        ))
+    } else {
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|err| err.to_string())?
+            .to_vec();
+        Ok(bytes)
+    }
+}
+
+/// a plain GET, for the apis that don't need anything special
+pub async fn get_bytes(url: String) -> Result<Vec<u8>, String> {
+    let response = crate::REQ_CLIENT
+        .get(&url)
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+
+    if !response.status().is_success() {
+        Err(format!("Error {} from url: {url}", response.status()))
+    } else {
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|err| err.to_string())?
+            .to_vec();
+        Ok(bytes)
+    }
+}
@@ -0,0 +1,408 @@
+use std::{collections::HashMap, fs, io::{BufRead, BufReader, Cursor, Read}, path::{Path, PathBuf}, process::{Command, Stdio}, thread};
+
+use iced::futures::{SinkExt, Stream, StreamExt, channel::mpsc};
+use serde::de::DeserializeOwned;
+use zip::ZipArchive;
+
+use crate::{MinecraftVersion, ModLoader, util::{hashes, reqwests}};
+
+#[derive(Debug, Clone)]
+pub struct ServerJar {
+    pub url: String,
+    pub file_name: String,
+    pub hashes: HashMap<String, String>, // empty when the source doesn't publish any
+}
+
+/// everything that has to be downloaded for a server, plus which of those jars gets launched
+#[derive(Debug, Clone)]
+pub struct InstallPlan {
+    pub jars: Vec<ServerJar>,
+    pub launch_jar: Option<String>, // None when it's only known once the installer has run
+    /// a downloaded jar that has to be run with `--installServer` before the server exists (forge, neoforge)
+    pub installer: Option<String>,
+}
+
+/// `loader_version` pins the loader build (fabric loader, forge build, neoforge version), otherwise the newest stable one is used
+pub async fn plan_install(loader: ModLoader, version: MinecraftVersion, loader_version: Option<String>) -> Result<InstallPlan, String> {
+    let loader_version = loader_version.as_deref();
+    match loader {
+        ModLoader::Fabric => {
+            // the launcher fetches vanilla on first start anyway. getting it here means it's checked against mojang's sha1
+            let launcher = fabric_launcher(&version.id, loader_version).await?;
+            Ok(InstallPlan {
+                launch_jar: Some(launcher.file_name.clone()),
+                jars: vec![launcher, vanilla_server(&version.id, "server.jar").await?],
+                installer: None,
+            })
+        }
+        ModLoader::Paper => single(paper_build("paper", &version.id).await?),
+        ModLoader::Folia => single(paper_build("folia", &version.id).await?),
+        ModLoader::Velocity => single(paper_build("velocity", &latest_velocity().await?).await?),
+        ModLoader::Purpur => single(purpur_build(&version.id).await?),
+        ModLoader::NeoForge => with_installer(neoforge_installer(&version.id, loader_version).await?),
+        ModLoader::Forge => with_installer(forge_installer(&version.id, loader_version).await?),
+    }
+}
+
+fn single(jar: ServerJar) -> Result<InstallPlan, String> {
+    Ok(InstallPlan { launch_jar: Some(jar.file_name.clone()), jars: vec![jar], installer: None })
+}
+
+fn with_installer(jar: ServerJar) -> Result<InstallPlan, String> {
+    Ok(InstallPlan {
+        launch_jar: None,
+        installer: Some(jar.file_name.clone()),
+        jars: vec![jar],
+    })
+}
+
+/// checks `bytes` against `hashes` (when there are any) and makes sure it's actually a runnable jar
+pub fn verify_server_jar(bytes: &[u8], hashes: &HashMap<String, String>) -> Result<(), String> {
+    if !hashes.is_empty() {
+        hashes::verify_hashes(bytes, hashes)?;
+    }
+    let mut archive = ZipArchive::new(Cursor::new(bytes)).map_err(|e| format!("not a jar: {e}"))?;
+    let mut manifest = String::new();
+    archive.by_name("META-INF/MANIFEST.MF")
+        .map_err(|e| format!("jar has no manifest: {e}"))?
+        .read_to_string(&mut manifest)
+        .map_err(|e| format!("couldn't read jar manifest: {e}"))?;
+    if !manifest.lines().any(|l| l.starts_with("Main-Class:")) {
+        return Err("jar has no Main-Class, so it can't be run".to_string());
+    }
+    Ok(())
+}
+
+pub fn write_eula(root: &Path) -> Result<(), String> {
+    let path = root.join("eula.txt");
+    fs::write(&path, "# accepted in mcservermodgui (https://aka.ms/MinecraftEULA)\neula=true\n")
+        .map_err(|e| format!("Couldn't write {}: {e}", path.display()))
+}
+
+async fn get_json<T: DeserializeOwned>(url: String) -> Result<T, String> {
+    let b = reqwests::get_bytes(url.clone()).await?;
+    let des = &mut serde_json::Deserializer::from_slice(&b);
+    serde_path_to_error::deserialize(des).map_err(|e| format!("error parsing {url} at {}: {e}", e.path()))
+}
+
+#[derive(serde::Deserialize)]
+struct PistonManifest {
+    versions: Vec<PistonManifestEntry>,
+}
+
+#[derive(serde::Deserialize)]
+struct PistonManifestEntry {
+    id: String,
+    url: String,
+}
+
+/// the per-version json the launcher manifest points to
+#[derive(serde::Deserialize)]
+pub struct PistonVersion {
+    pub downloads: PistonDownloads,
+    #[serde(rename = "javaVersion")]
+    pub java_version: Option<PistonJavaVersion>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct PistonJavaVersion {
+    #[serde(rename = "majorVersion")]
+    pub major_version: u32,
+}
+
+#[derive(serde::Deserialize)]
+pub struct PistonDownloads {
+    pub server: Option<PistonDownload>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct PistonDownload {
+    pub sha1: String,
+    pub url: String,
+}
+
+pub async fn piston_version(id: &str) -> Result<PistonVersion, String> {
+    let manifest: PistonManifest = get_json("https://piston-meta.mojang.com/mc/game/version_manifest_v2.json".to_string()).await?;
+    let entry = manifest.versions.into_iter().find(|v| v.id == id).ok_or_else(|| format!("{id} isn't in mojang's version manifest"))?;
+    get_json(entry.url).await
+}
+
+async fn vanilla_server(id: &str, file_name: &str) -> Result<ServerJar, String> {
+    let server = piston_version(id).await?.downloads.server.ok_or_else(|| format!("mojang has no server jar for {id}"))?;
+    Ok(ServerJar {
+        url: server.url,
+        file_name: file_name.to_string(),
+        hashes: HashMap::from([("sha1".to_string(), server.sha1)]),
+    })
+}
+
+#[derive(serde::Deserialize)]
+struct FabricLoaderEntry {
+    loader: FabricVersion,
+}
+
+#[derive(serde::Deserialize)]
+struct FabricVersion {
+    version: String,
+    stable: bool,
+}
+
+async fn fabric_launcher(game_version: &str, loader_version: Option<&str>) -> Result<ServerJar, String> {
+    let loaders: Vec<FabricLoaderEntry> = get_json(format!("https://meta.fabricmc.net/v2/versions/loader/{game_version}")).await?;
+    let loader = match loader_version {
+        Some(want) => loaders.iter().map(|l| &l.loader).find(|l| l.version == want)
+            .ok_or_else(|| format!("fabric loader {want} doesn't support {game_version}"))?,
+        None => loaders.iter().map(|l| &l.loader).find(|l| l.stable).or(loaders.first().map(|l| &l.loader))
+            .ok_or_else(|| format!("fabric doesn't support {game_version}"))?,
+    };
+    let installers: Vec<FabricVersion> = get_json("https://meta.fabricmc.net/v2/versions/installer".to_string()).await?;
+    let installer = installers.iter().find(|i| i.stable).or(installers.first())
+        .ok_or_else(|| "fabric has no installer versions".to_string())?;
+
+    Ok(ServerJar {
+        url: format!("https://meta.fabricmc.net/v2/versions/loader/{game_version}/{}/{}/server/jar", loader.version, installer.version),
+        file_name: format!("fabric-server-mc.{game_version}-loader.{}-launcher.{}.jar", loader.version, installer.version),
+        hashes: HashMap::new(), // built on request, so there's nothing published to check against
+    })
+}
+
+#[derive(serde::Deserialize)]
+struct PaperBuild {
+    downloads: HashMap<String, PaperDownload>,
+}
+
+#[derive(serde::Deserialize)]
+struct PaperDownload {
+    name: String,
+    checksums: HashMap<String, String>,
+    url: String,
+}
+
+#[derive(serde::Deserialize)]
+struct PaperProject {
+    versions: HashMap<String, Vec<String>>,
+}
+
+/// the latest build of a papermc project (paper, folia, velocity)
+async fn paper_build(project: &str, version: &str) -> Result<ServerJar, String> {
+    let build: PaperBuild = get_json(format!("https://fill.papermc.io/v3/projects/{project}/versions/{version}/builds/latest")).await?;
+    let download = build.downloads.get("server:default").ok_or_else(|| format!("{project} {version} has no server download"))?;
+    Ok(ServerJar {
+        url: download.url.clone(),
+        file_name: download.name.clone(),
+        hashes: download.checksums.clone(),
+    })
+}
+
+/// velocity versions have nothing to do with minecraft's, so the newest one is used
+async fn latest_velocity() -> Result<String, String> {
+    let project: PaperProject = get_json("https://fill.papermc.io/v3/projects/velocity".to_string()).await?;
+    // groups look like "3.0.0" and each lists its versions newest first
+    project.versions.into_iter()
+        .max_by(|(a, _), (b, _)| version_key(a).cmp(&version_key(b)))
+        .and_then(|(_, versions)| versions.into_iter().next())
+        .ok_or_else(|| "velocity has no versions".to_string())
+}
+
+fn version_key(v: &str) -> Vec<u32> {
+    v.split(|c: char| !c.is_ascii_digit()).filter_map(|n| n.parse().ok()).collect()
+}
+
+#[derive(serde::Deserialize)]
+struct PurpurVersion {
+    builds: PurpurBuilds,
+}
+
+#[derive(serde::Deserialize)]
+struct PurpurBuilds {
+    latest: String,
+}
+
+#[derive(serde::Deserialize)]
+struct PurpurBuild {
+    md5: String,
+}
+
+async fn purpur_build(version: &str) -> Result<ServerJar, String> {
+    let v: PurpurVersion = get_json(format!("https://api.purpurmc.org/v2/purpur/{version}")).await?;
+    let build = v.builds.latest;
+    let info: PurpurBuild = get_json(format!("https://api.purpurmc.org/v2/purpur/{version}/{build}")).await?;
+    Ok(ServerJar {
+        url: format!("https://api.purpurmc.org/v2/purpur/{version}/{build}/download"),
+        file_name: format!("purpur-{version}-{build}.jar"),
+        hashes: HashMap::from([("md5".to_string(), info.md5)]),
+    })
+}
+
+/// the maven publishes a `.sha1` next to every artifact
+async fn maven_sha1(url: &str) -> Result<HashMap<String, String>, String> {
+    let b = reqwests::get_bytes(format!("{url}.sha1")).await?;
+    Ok(HashMap::from([("sha1".to_string(), String::from_utf8_lossy(&b).trim().to_string())]))
+}
+
+#[derive(serde::Deserialize)]
+struct NeoforgeVersions {
+    versions: Vec<String>,
+}
+
+/// neoforge drops minecraft's leading `1.` and always has a patch number, so 1.21 is 21.0.x and 1.21.1 is 21.1.x
+fn neoforge_prefix(game_version: &str) -> String {
+    let v = game_version.strip_prefix("1.").unwrap_or(game_version);
+    if v.matches('.').count() == 0 {format!("{v}.0.")} else {format!("{v}.")}
+}
+
+/// the `net.neoforged` artifact neoforge for `game_version` comes as, and the prefix its versions start with.
+/// the first neoforge, for 1.20.1, still came out as `net.neoforged:forge` with forge style `1.20.1-47.1.x` versions
+pub fn neoforge_artifact(game_version: &str) -> (&'static str, String) {
+    if game_version == "1.20.1" {("forge", format!("{game_version}-"))} else {("neoforge", neoforge_prefix(game_version))}
+}
+
+async fn neoforge_installer(game_version: &str, loader_version: Option<&str>) -> Result<ServerJar, String> {
+    let (artifact, prefix) = neoforge_artifact(game_version);
+    let list: NeoforgeVersions = get_json(format!("https://maven.neoforged.net/api/maven/versions/releases/net/neoforged/{artifact}")).await?;
+    // oldest first, so the newest stable build is the last one without a suffix
+    let mut matching = list.versions.iter().filter(|v| v.starts_with(&prefix));
+    let version = match loader_version {
+        Some(want) => matching.find(|v| *v == want || v.strip_prefix(&prefix) == Some(want) && artifact == "forge")
+            .ok_or_else(|| format!("neoforge {want} doesn't exist for {game_version}"))?,
+        None => matching.clone().rfind(|v| !v.contains('-')).or(matching.next_back())
+            .ok_or_else(|| format!("neoforge doesn't support {game_version}"))?,
+    };
+
+    let url = format!("https://maven.neoforged.net/releases/net/neoforged/{artifact}/{version}/{artifact}-{version}-installer.jar");
+    Ok(ServerJar {
+        hashes: maven_sha1(&url).await?,
+        file_name: format!("{artifact}-{version}-installer.jar"),
+        url,
+    })
+}
+
+#[derive(serde::Deserialize)]
+struct ForgePromotions {
+    promos: HashMap<String, String>,
+}
+
+async fn forge_installer(game_version: &str, loader_version: Option<&str>) -> Result<ServerJar, String> {
+    let build = match loader_version {
+        // packs sometimes give the full `1.20.1-47.2.0` instead of just the build
+        Some(want) => want.strip_prefix(&format!("{game_version}-")).unwrap_or(want).to_string(),
+        None => {
+            let promotions: ForgePromotions = get_json("https://files.minecraftforge.net/net/minecraftforge/forge/promotions_slim.json".to_string()).await?;
+            promotions.promos.get(&format!("{game_version}-recommended"))
+                .or(promotions.promos.get(&format!("{game_version}-latest")))
+                .ok_or_else(|| format!("forge doesn't support {game_version}"))?
+                .clone()
+        }
+    };
+
+    let version = format!("{game_version}-{build}");
+    let url = format!("https://maven.minecraftforge.net/net/minecraftforge/forge/{version}/forge-{version}-installer.jar");
+    Ok(ServerJar {
+        hashes: maven_sha1(&url).await?,
+        file_name: format!("forge-{version}-installer.jar"),
+        url,
+    })
+}
+
+#[derive(Debug, Clone)]
+pub enum InstallerEvent {
+    Output(String),
+    Finished(Result<String, String>), // what to launch the server with
+}
+
+/// runs `java -jar <installer> --installServer` in `root`, streaming its output line by line.
+/// the installer is deleted afterwards, and only counts as successful if it installed `game_version` and left something to launch the server with
+pub fn run_installer(root: PathBuf, installer: String, java: String, game_version: String) -> impl Stream<Item = InstallerEvent> {
+    iced::stream::channel(64, async move |mut output| {
+        let (tx, mut rx) = mpsc::unbounded();
+        thread::spawn(move || {
+            let result = _run_installer(&root, &installer, &java, |line| {let _ = tx.unbounded_send(InstallerEvent::Output(line));})
+                .and_then(|()| {
+                    let _ = fs::remove_file(root.join(&installer));
+                    find_launch_target(&root, &installer, &game_version)
+                });
+            let _ = tx.unbounded_send(InstallerEvent::Finished(result));
+        });
+
+        while let Some(event) = rx.next().await {
+            let _ = output.send(event).await;
+        }
+    })
+}
+
+fn _run_installer(root: &Path, installer: &str, java: &str, on_line: impl Fn(String) + Send + Sync) -> Result<(), String> {
+    let mut child = Command::new(java)
+        .args(["-jar", installer, "--installServer"])
+        .current_dir(root)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Couldn't run java, is it installed? ({e})"))?;
+
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+    thread::scope(|s| {
+        s.spawn(|| BufReader::new(stderr).lines().map_while(Result::ok).for_each(&on_line));
+        BufReader::new(stdout).lines().map_while(Result::ok).for_each(&on_line);
+    });
+
+    let status = child.wait().map_err(|e| e.to_string())?;
+    if !status.success() {
+        return Err(format!("The installer failed ({status})"));
+    }
+    Ok(())
+}
+
+/// what the installer left to start the server with. 1.17 and newer get a script with the library args baked in,
+/// older forge a `forge-<version>.jar`, or `forge-<version>-universal.jar` before 1.12.
+/// the server itself has to be in `libraries/net/minecraft/server/<version>` like init expects (next to the launch jar before 1.17),
+/// so a script left over from an earlier install doesn't count
+fn find_launch_target(root: &Path, installer: &str, game_version: &str) -> Result<String, String> {
+    if !root.join("libraries").join("net").join("minecraft").join("server").join(game_version).is_dir()
+        && !root.join(format!("minecraft_server.{game_version}.jar")).is_file() {
+        return Err(format!("The installer finished, but didn't install the Minecraft {game_version} server"));
+    }
+    let script = if cfg!(windows) {"run.bat"} else {"run.sh"};
+    let base = installer.strip_suffix("-installer.jar").unwrap_or(installer);
+    [script.to_string(), format!("{base}.jar"), format!("{base}-universal.jar")].into_iter()
+        .find(|f| root.join(f).is_file())
+        .ok_or_else(|| format!("The installer finished, but left neither {script} nor {base}.jar to start the server with"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn launch_target_is_what_the_installer_left() {
+        let root = std::env::temp_dir().join(format!("mcservermodgui-installer-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let script = if cfg!(windows) {"run.bat"} else {"run.sh"};
+
+        let installed = |version: &str| fs::create_dir_all(root.join("libraries/net/minecraft/server").join(version)).unwrap();
+
+        installed("1.16.5");
+        assert!(find_launch_target(&root, "forge-1.16.5-36.2.39-installer.jar", "1.16.5").is_err());
+        fs::write(root.join("minecraft_server.1.7.10.jar"), "").unwrap();
+        fs::write(root.join("forge-1.7.10-10.13.4.1614-1.7.10-universal.jar"), "").unwrap();
+        assert_eq!(find_launch_target(&root, "forge-1.7.10-10.13.4.1614-1.7.10-installer.jar", "1.7.10").unwrap(), "forge-1.7.10-10.13.4.1614-1.7.10-universal.jar");
+        fs::write(root.join("forge-1.16.5-36.2.39.jar"), "").unwrap();
+        assert_eq!(find_launch_target(&root, "forge-1.16.5-36.2.39-installer.jar", "1.16.5").unwrap(), "forge-1.16.5-36.2.39.jar");
+
+        // a script left over from an earlier install isn't enough on its own
+        fs::write(root.join(script), "").unwrap();
+        assert!(find_launch_target(&root, "forge-1.20.1-47.2.0-installer.jar", "1.20.1").is_err());
+        installed("1.20.1");
+        assert_eq!(find_launch_target(&root, "forge-1.20.1-47.2.0-installer.jar", "1.20.1").unwrap(), script);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn neoforge_versions_follow_minecraft() {
+        assert_eq!(neoforge_prefix("1.21"), "21.0.");
+        assert_eq!(neoforge_prefix("1.21.1"), "21.1.");
+        assert_eq!(neoforge_prefix("1.20.4"), "20.4.");
+    }
+}
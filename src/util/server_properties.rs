@@ -0,0 +1,252 @@
+use std::{fmt::Display, fs, path::Path};
+
+// server.properties is a java .properties file. lines are kept as they were unless their value changes,
+// so comments, ordering and anything we don't know about survive a save
+
+#[derive(Debug, Clone)]
+enum Line {
+    Other(String), // comments, blank lines, anything that isn't key=value
+    Entry { key: String, value: String, raw: Option<String> }, // raw is None once the value has been changed
+}
+
+#[derive(Debug, Clone)]
+pub struct ServerProperties {
+    lines: Vec<Line>,
+    newline: &'static str, // written the way the file had them
+    ends_with_newline: bool,
+}
+impl Default for ServerProperties {
+    fn default() -> Self {
+        ServerProperties { lines: vec![], newline: "\n", ends_with_newline: true }
+    }
+}
+impl ServerProperties {
+    pub fn parse(s: &str) -> Self {
+        let lines = s.lines().map(|l| {
+            let trimmed = l.trim_start();
+            if trimmed.is_empty() || trimmed.starts_with(['#', '!']) {
+                return Line::Other(l.to_string());
+            }
+            let (key, value) = split_entry(trimmed);
+            Line::Entry { key: unescape(key), value: unescape(value), raw: Some(l.to_string()) }
+        }).collect();
+        ServerProperties {
+            lines,
+            newline: if s.contains("\r\n") {"\r\n"} else {"\n"},
+            ends_with_newline: s.is_empty() || s.ends_with('\n'),
+        }
+    }
+
+    pub fn load(root: &Path) -> Result<Self, String> {
+        match fs::read_to_string(root.join("server.properties")) {
+            Ok(s) => Ok(Self::parse(&s)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()), // the server writes it on first start
+            Err(e) => Err(format!("Couldn't read server.properties: {e}")),
+        }
+    }
+
+    pub fn save(&self, root: &Path) -> Result<(), String> {
+        fs::write(root.join("server.properties"), self.to_string()).map_err(|e| format!("Couldn't write server.properties: {e}"))
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.lines.iter().find_map(|l| match l {
+            Line::Entry { key: k, value, .. } if k == key => Some(value.as_str()),
+            _ => None,
+        })
+    }
+
+    /// changes `key`, adding it at the end if it isn't there yet
+    pub fn set(&mut self, key: &str, new: String) {
+        for l in &mut self.lines {
+            if let Line::Entry { key: k, value, raw } = l && k == key {
+                if *value != new {
+                    *value = new;
+                    *raw = None;
+                }
+                return;
+            }
+        }
+        self.lines.push(Line::Entry { key: key.to_string(), value: new, raw: None });
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().filter_map(|l| match l {
+            Line::Entry { key, .. } => Some(key.as_str()),
+            Line::Other(_) => None,
+        })
+    }
+}
+impl Display for ServerProperties {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, l) in self.lines.iter().enumerate() {
+            match l {
+                Line::Other(s) | Line::Entry { raw: Some(s), .. } => write!(f, "{s}")?,
+                Line::Entry { key, value, raw: None } => write!(f, "{}={}", escape(key, true), escape(value, false))?,
+            }
+            if i + 1 < self.lines.len() || self.ends_with_newline {
+                write!(f, "{}", self.newline)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// the key ends at the first unescaped `=`, `:` or whitespace
+fn split_entry(line: &str) -> (&str, &str) {
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == '=' || c == ':' || c.is_whitespace() {
+            let rest = line[i..].trim_start();
+            let rest = rest.strip_prefix(['=', ':']).unwrap_or(rest).trim_start();
+            return (&line[..i], rest);
+        }
+    }
+    (line, "")
+}
+
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some('u') => {
+                let hex: String = chars.by_ref().take(4).collect();
+                out.extend(u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32));
+            }
+            Some(other) => out.push(other),
+            None => (),
+        }
+    }
+    out
+}
+
+fn escape(s: &str, is_key: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for (i, c) in s.chars().enumerate() {
+        match c {
+            '\\' | '=' | ':' | '#' | '!' => {out.push('\\'); out.push(c)}
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            ' ' if is_key || i == 0 => out.push_str("\\ "),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldKind {
+    Bool,
+    Port,
+    Int { min: i64, max: i64 },
+    Choice(&'static [&'static str]),
+    Text,
+}
+
+/// the properties that get a proper field in the editor, in the order they're shown
+pub const FIELDS: &[(&str, &str, FieldKind)] = &[
+    ("motd", "Message of the day", FieldKind::Text),
+    ("server-port", "Port", FieldKind::Port),
+    ("max-players", "Max players", FieldKind::Int { min: 0, max: 2147483647 }),
+    ("gamemode", "Game mode", FieldKind::Choice(&["survival", "creative", "adventure", "spectator"])),
+    ("force-gamemode", "Force game mode", FieldKind::Bool),
+    ("difficulty", "Difficulty", FieldKind::Choice(&["peaceful", "easy", "normal", "hard"])),
+    ("hardcore", "Hardcore", FieldKind::Bool),
+    ("pvp", "PvP", FieldKind::Bool),
+    ("online-mode", "Online mode", FieldKind::Bool),
+    ("white-list", "Whitelist", FieldKind::Bool),
+    ("enforce-whitelist", "Enforce whitelist", FieldKind::Bool),
+    ("view-distance", "View distance", FieldKind::Int { min: 3, max: 32 }),
+    ("simulation-distance", "Simulation distance", FieldKind::Int { min: 3, max: 32 }),
+    ("spawn-protection", "Spawn protection", FieldKind::Int { min: 0, max: 2147483647 }),
+    ("level-name", "World folder", FieldKind::Text),
+    ("level-seed", "World seed", FieldKind::Text),
+    ("level-type", "World type", FieldKind::Text),
+    ("allow-flight", "Allow flight", FieldKind::Bool),
+    ("enable-command-block", "Command blocks", FieldKind::Bool),
+    ("enable-rcon", "RCON", FieldKind::Bool),
+    ("rcon.port", "RCON port", FieldKind::Port),
+    ("enable-query", "Query", FieldKind::Bool),
+    ("query.port", "Query port", FieldKind::Port),
+];
+
+pub fn field_kind(key: &str) -> FieldKind {
+    FIELDS.iter().find(|(k, _, _)| *k == key).map_or(FieldKind::Text, |(_, _, kind)| *kind)
+}
+
+pub fn validate(kind: FieldKind, value: &str) -> Result<(), String> {
+    match kind {
+        FieldKind::Bool if value == "true" || value == "false" => Ok(()),
+        FieldKind::Bool => Err("must be true or false".to_string()),
+        FieldKind::Port => match value.parse::<u16>() {
+            Ok(p) if p > 0 => Ok(()),
+            _ => Err("must be a port between 1 and 65535".to_string()),
+        },
+        FieldKind::Int { min, max } => match value.parse::<i64>() {
+            Ok(n) if (min..=max).contains(&n) => Ok(()),
+            _ => Err(format!("must be a whole number from {min} to {max}")),
+        },
+        FieldKind::Choice(options) if options.contains(&value) => Ok(()),
+        FieldKind::Choice(options) => Err(format!("must be one of {}", options.join(", "))),
+        FieldKind::Text => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = "#Minecraft server properties\n#Sat Oct 17 12:00:00 UTC 2026\nmotd=A \\u00A7aMinecraft Server\nserver-port = 25565\n\n! old style comment\nlevel-seed:\nresource-pack=https\\://example.com/pack.zip?a\\=b\n";
+
+    #[test]
+    fn unchanged_files_are_written_back_as_they_were() {
+        assert_eq!(ServerProperties::parse(FILE).to_string(), FILE);
+        let crlf = FILE.replace('\n', "\r\n");
+        assert_eq!(ServerProperties::parse(&crlf).to_string(), crlf);
+        let unterminated = FILE.trim_end();
+        assert_eq!(ServerProperties::parse(unterminated).to_string(), unterminated);
+    }
+
+    #[test]
+    fn escapes_are_read() {
+        let p = ServerProperties::parse(FILE);
+        assert_eq!(p.get("motd"), Some("A \u{a7}aMinecraft Server"));
+        assert_eq!(p.get("server-port"), Some("25565"));
+        assert_eq!(p.get("level-seed"), Some(""));
+        assert_eq!(p.get("resource-pack"), Some("https://example.com/pack.zip?a=b"));
+        assert_eq!(p.keys().collect::<Vec<_>>(), vec!["motd", "server-port", "level-seed", "resource-pack"]);
+    }
+
+    #[test]
+    fn set_only_rewrites_its_own_line() {
+        let mut p = ServerProperties::parse(FILE);
+        p.set("server-port", "25566".to_string());
+        p.set("motd", "A \u{a7}aMinecraft Server".to_string()); // the same value keeps the line as written
+        assert_eq!(p.to_string(), FILE.replace("server-port = 25565", "server-port=25566"));
+    }
+
+    #[test]
+    fn new_keys_go_at_the_end_escaped() {
+        let mut p = ServerProperties::parse(FILE);
+        p.set("resource-pack", "https://example.com/other.zip".to_string());
+        p.set("level-name", " world: #1".to_string());
+        let written = p.to_string();
+        assert!(written.ends_with("resource-pack=https\\://example.com/other.zip\nlevel-name=\\ world\\: \\#1\n"), "{written}");
+        let reread = ServerProperties::parse(&written);
+        assert_eq!(reread.get("resource-pack"), Some("https://example.com/other.zip"));
+        assert_eq!(reread.get("level-name"), Some(" world: #1"));
+    }
+}
@@ -0,0 +1,74 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::ProgramData;
+
+/// every server folder this user has opened, kept in their own config folder rather than in any one server
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+pub struct ServerRegistry {
+    #[serde(default)]
+    pub servers: Vec<ServerEntry>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct ServerEntry {
+    pub root: PathBuf,
+    #[serde(default)]
+    pub last_opened: u64, // unix seconds
+}
+impl ServerEntry {
+    /// the server's own mcservermodgui.toml. None until setup has been through it
+    pub fn program_data(&self) -> Result<Option<ProgramData>, String> {
+        ProgramData::load(&self.root)
+    }
+}
+
+impl ServerRegistry {
+    pub fn load() -> Self {
+        let Some(path) = registry_path() else {return Self::default()};
+        let Ok(s) = fs::read_to_string(&path) else {return Self::default()};
+        toml::from_str(&s).unwrap_or_else(|e| {
+            eprintln!("Couldn't read {}: {e}", path.display());
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = registry_path().ok_or("Couldn't find a config folder to keep the server list in")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Couldn't create {}: {e}", parent.display()))?;
+        }
+        let s = toml::to_string_pretty(self).map_err(|e| format!("Couldn't serialize server list: {e}"))?;
+        fs::write(&path, s).map_err(|e| format!("Couldn't write {}: {e}", path.display()))
+    }
+
+    /// adds `root` if it's new and marks it as just opened. most recently opened comes first
+    pub fn touch(&mut self, root: &Path) {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        match self.servers.iter_mut().find(|s| s.root == root) {
+            Some(s) => s.last_opened = now,
+            None => self.servers.push(ServerEntry { root: root.to_path_buf(), last_opened: now }),
+        }
+        self.servers.sort_by_key(|s| std::cmp::Reverse(s.last_opened));
+    }
+
+    pub fn remove(&mut self, root: &Path) {
+        self.servers.retain(|s| s.root != root);
+    }
+}
+
+/// `servers.toml` in the platform's per-user config folder
+fn registry_path() -> Option<PathBuf> {
+    let dir = if cfg!(windows) {
+        PathBuf::from(env::var_os("APPDATA")?)
+    } else if cfg!(target_os = "macos") {
+        PathBuf::from(env::var_os("HOME")?).join("Library").join("Application Support")
+    } else {
+        env::var_os("XDG_CONFIG_HOME").filter(|d| !d.is_empty()).map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))?
+    };
+    Some(dir.join("mcservermodgui").join("servers.toml"))
+}
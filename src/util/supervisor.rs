@@ -0,0 +1,394 @@
+use std::{env, ffi::OsString, fmt::Display, io::{BufRead, BufReader, Write}, path::{Path, PathBuf}, process::{Child, ChildStdin, Command, Stdio}, sync::{Arc, Mutex}, thread, time::Duration};
+
+use iced::futures::{SinkExt, Stream, StreamExt, channel::mpsc};
+
+use crate::{ModLoader, ProgramData};
+
+// the server runs on plain threads (there's no async process support without pulling tokio in directly),
+// which report back to one loop that owns all the state. the app talks to that loop through a `Supervisor`
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerState {
+    Stopped,
+    Starting,
+    Running,
+    Stopping,
+    Crashed(String),
+}
+impl Display for ServerState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ServerState::Stopped => write!(f, "stopped"),
+            ServerState::Starting => write!(f, "starting"),
+            ServerState::Running => write!(f, "running"),
+            ServerState::Stopping => write!(f, "stopping"),
+            ServerState::Crashed(reason) => write!(f, "crashed ({reason})"),
+        }
+    }
+}
+
+/// what gets run and where. nothing in here is minecraft specific, so any script that prints and reads lines can stand in for a server
+#[derive(Debug, Clone)]
+pub struct LaunchConfig {
+    pub program: OsString,
+    pub args: Vec<OsString>,
+    pub dir: PathBuf,
+    pub path_prefix: Option<PathBuf>, // put in front of PATH so scripts that call `java` get the configured one
+    pub stop_command: String,
+    pub stop_timeout: Duration,
+    pub start_timeout: Option<Duration>, // killed and counted as crashed if it isn't up by then
+}
+impl LaunchConfig {
+    /// how to launch the server described by `program_data` in `root`
+    pub fn for_server(program_data: &ProgramData, root: &Path) -> Result<Self, String> {
+        let jar = program_data.server_jar.as_deref()
+            .ok_or("No server jar is configured. Set server_jar in .mcservermodgui/mcservermodgui.toml")?;
+        if !root.join(jar).is_file() {
+            return Err(format!("{jar} doesn't exist"));
+        }
+        let java = program_data.java.clone().unwrap_or_else(|| "java".to_string());
+        // velocity doesn't know `nogui` and treats it as an error
+        let nogui = if program_data.loader == ModLoader::Velocity {vec![]} else {vec!["nogui".into()]};
+
+        let (program, mut args): (OsString, Vec<OsString>) = if jar.ends_with(".sh") {
+            ("sh".into(), vec![jar.into()])
+        } else if jar.ends_with(".bat") {
+            ("cmd".into(), vec!["/C".into(), jar.into()])
+        } else {
+            (java.clone().into(), vec!["-jar".into(), jar.into()])
+        };
+        args.extend(nogui);
+
+        Ok(LaunchConfig {
+            program,
+            args,
+            dir: root.to_path_buf(),
+            path_prefix: Path::new(&java).parent().filter(|p| !p.as_os_str().is_empty()).map(Path::to_path_buf),
+            stop_command: "stop".to_string(),
+            stop_timeout: Duration::from_secs(30),
+            start_timeout: None,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum SupervisorEvent {
+    Ready(Supervisor),
+    StateChanged(ServerState),
+    Output(String),
+}
+
+#[derive(Debug)]
+enum Input {
+    Start(LaunchConfig),
+    Stop,
+    Restart(LaunchConfig),
+    Kill,
+    SendLine(String),
+
+    Line(String),
+    Exited(u64, Result<std::process::ExitStatus, String>),
+    StopTimedOut(u64),
+    StartTimedOut(u64),
+}
+
+/// a handle to the supervisor loop. sending to a loop that's gone does nothing
+#[derive(Debug, Clone)]
+pub struct Supervisor(mpsc::UnboundedSender<Input>);
+impl Supervisor {
+    pub fn start(&self, config: LaunchConfig) {
+        let _ = self.0.unbounded_send(Input::Start(config));
+    }
+    /// asks the server to stop, killing it if it hasn't after the config's timeout
+    pub fn stop(&self) {
+        let _ = self.0.unbounded_send(Input::Stop);
+    }
+    pub fn restart(&self, config: LaunchConfig) {
+        let _ = self.0.unbounded_send(Input::Restart(config));
+    }
+    pub fn kill(&self) {
+        let _ = self.0.unbounded_send(Input::Kill);
+    }
+    /// writes `line` to the server's stdin, like typing it into its console
+    pub fn send_line(&self, line: String) {
+        let _ = self.0.unbounded_send(Input::SendLine(line));
+    }
+}
+
+struct ServerProcess {
+    child: Arc<Mutex<Child>>,
+    stdin: ChildStdin,
+    config: LaunchConfig,
+}
+impl Drop for ServerProcess {
+    fn drop(&mut self) {
+        // if the app goes away first, at least let the server save and shut itself down
+        let _ = writeln!(self.stdin, "{}", self.config.stop_command);
+    }
+}
+
+/// the stream behind the supervisor subscription. the first event is always `Ready` with the handle to drive it
+pub fn run() -> impl Stream<Item = SupervisorEvent> {
+    iced::stream::channel(100, async |mut output| {
+        let (tx, mut rx) = mpsc::unbounded();
+        let _ = output.send(SupervisorEvent::Ready(Supervisor(tx.clone()))).await;
+
+        let mut process: Option<ServerProcess> = None;
+        let mut state = ServerState::Stopped;
+        let mut restart_with: Option<LaunchConfig> = None;
+        let mut generation = 0u64; // tells the timers and exits of an earlier run apart from the current one
+        let mut start_timed_out: Option<Duration> = None;
+
+        while let Some(input) = rx.next().await {
+            let new_state = match input {
+                Input::Start(config) | Input::Restart(config) if process.is_none() => {
+                    generation += 1;
+                    start_timed_out = None;
+                    if let Some(timeout) = config.start_timeout {
+                        let (tx, generation) = (tx.clone(), generation);
+                        thread::spawn(move || {
+                            thread::sleep(timeout);
+                            let _ = tx.unbounded_send(Input::StartTimedOut(generation));
+                        });
+                    }
+                    match spawn(config, tx.clone(), generation) {
+                        Ok(p) => {
+                            process = Some(p);
+                            Some(ServerState::Starting)
+                        }
+                        Err(err) => Some(ServerState::Crashed(err)),
+                    }
+                }
+                Input::Start(_) => None,
+                Input::Restart(config) => {
+                    restart_with = Some(config);
+                    if state == ServerState::Stopping {None} else {stop(&mut process, &tx, generation)}
+                }
+                Input::Stop => {
+                    restart_with = None;
+                    if state == ServerState::Stopping {None} else {stop(&mut process, &tx, generation)}
+                }
+                Input::Kill => {
+                    if let Some(p) = &process {
+                        kill(&p.child);
+                    }
+                    None
+                }
+                Input::SendLine(line) => {
+                    if let Some(p) = &mut process && let Err(err) = writeln!(p.stdin, "{line}") {
+                        eprintln!("Couldn't write to the server: {err}");
+                    }
+                    None
+                }
+                Input::Line(line) => {
+                    // vanilla, paper and velocity all announce they're up with "Done (1.234s)!"
+                    let done = state == ServerState::Starting && line.contains("Done (");
+                    let _ = output.send(SupervisorEvent::Output(line)).await;
+                    done.then_some(ServerState::Running)
+                }
+                Input::Exited(g, status) if g == generation => {
+                    let was_stopping = state == ServerState::Stopping;
+                    process = None;
+                    let exited = match status {
+                        _ if let Some(timeout) = start_timed_out => ServerState::Crashed(format!("didn't start within {}s", timeout.as_secs())),
+                        Ok(s) if s.success() || was_stopping => ServerState::Stopped,
+                        Ok(s) => ServerState::Crashed(format!("exited with {s}")),
+                        Err(err) => ServerState::Crashed(err),
+                    };
+                    if let Some(config) = restart_with.take() && exited == ServerState::Stopped {
+                        let _ = tx.unbounded_send(Input::Start(config));
+                    }
+                    Some(exited)
+                }
+                Input::StopTimedOut(g) => {
+                    if g == generation && state == ServerState::Stopping && let Some(p) = &process {
+                        eprintln!("server didn't stop in time, killing it");
+                        kill(&p.child);
+                    }
+                    None
+                }
+                Input::StartTimedOut(g) => {
+                    if g == generation && state == ServerState::Starting && let Some(p) = &process {
+                        eprintln!("server didn't start in time, killing it");
+                        start_timed_out = p.config.start_timeout;
+                        kill(&p.child);
+                    }
+                    None
+                }
+                Input::Exited(..) => None,
+            };
+
+            if let Some(new_state) = new_state && new_state != state {
+                state = new_state;
+                let _ = output.send(SupervisorEvent::StateChanged(state.clone())).await;
+            }
+        }
+    })
+}
+
+/// sends the stop command and starts the kill timer
+fn stop(process: &mut Option<ServerProcess>, tx: &mpsc::UnboundedSender<Input>, generation: u64) -> Option<ServerState> {
+    let p = process.as_mut()?;
+    if let Err(err) = writeln!(p.stdin, "{}", p.config.stop_command) {
+        eprintln!("Couldn't ask the server to stop, killing it: {err}");
+        kill(&p.child);
+    }
+    let tx = tx.clone();
+    let timeout = p.config.stop_timeout;
+    thread::spawn(move || {
+        thread::sleep(timeout);
+        let _ = tx.unbounded_send(Input::StopTimedOut(generation));
+    });
+    Some(ServerState::Stopping)
+}
+
+/// kills the server along with anything it started
+fn kill(child: &Mutex<Child>) {
+    let mut child = child.lock().unwrap();
+    #[cfg(unix)]
+    if let Ok(pid) = libc::pid_t::try_from(child.id()) {
+        // the group keeps the child's id for as long as anything in it is alive, even once the child itself has exited
+        unsafe {libc::killpg(pid, libc::SIGKILL);}
+    }
+    #[cfg(windows)]
+    let _ = Command::new("taskkill").args(["/T", "/F", "/PID", &child.id().to_string()]).output();
+    let _ = child.kill();
+}
+
+fn spawn(config: LaunchConfig, tx: mpsc::UnboundedSender<Input>, generation: u64) -> Result<ServerProcess, String> {
+    let mut command = Command::new(&config.program);
+    command.args(&config.args)
+        .current_dir(&config.dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(prefix) = &config.path_prefix {
+        let path = env::var_os("PATH").unwrap_or_default();
+        let joined = env::join_paths([prefix.clone()].into_iter().chain(env::split_paths(&path))).map_err(|e| e.to_string())?;
+        command.env("PATH", joined);
+    }
+    // its own process group, so killing it also gets the java that `run.sh` starts without `exec`
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+    let mut child = command.spawn().map_err(|e| format!("Couldn't run {}: {e}", config.program.to_string_lossy()))?;
+
+    let stdin = child.stdin.take().unwrap();
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+    let readers = [Box::new(stdout) as Box<dyn std::io::Read + Send>, Box::new(stderr)].map(|pipe| {
+        let tx = tx.clone();
+        thread::spawn(move || {
+            for line in BufReader::new(pipe).lines().map_while(Result::ok) {
+                let _ = tx.unbounded_send(Input::Line(line));
+            }
+        })
+    });
+
+    let child = Arc::new(Mutex::new(child));
+    let waiter = child.clone();
+    thread::spawn(move || {
+        // polled rather than waited on so `kill` can still get at the child
+        let status = loop {
+            match waiter.lock().unwrap().try_wait() {
+                Ok(Some(status)) => break Ok(status),
+                Ok(None) => (),
+                Err(err) => break Err(err.to_string()),
+            }
+            thread::sleep(Duration::from_millis(200));
+        };
+        // the last lines (usually the crash) have to come before the exit
+        for reader in readers {
+            let _ = reader.join();
+        }
+        let _ = tx.unbounded_send(Input::Exited(generation, status));
+    });
+
+    Ok(ServerProcess { child, stdin, config })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// runs `script` under the supervisor, stopping it once it's up if `stop_timeout` is given,
+    /// and collects every event until it's stopped or crashed
+    fn run_script(script: &str, stop_timeout: Option<Duration>) -> Vec<SupervisorEvent> {
+        let config = LaunchConfig {
+            program: "sh".into(),
+            args: vec!["-c".into(), script.into()],
+            dir: env::temp_dir(),
+            path_prefix: None,
+            stop_command: "stop".to_string(),
+            stop_timeout: stop_timeout.unwrap_or(Duration::from_secs(30)),
+            start_timeout: None,
+        };
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let mut events = Box::pin(run());
+            let Some(SupervisorEvent::Ready(supervisor)) = events.next().await else {panic!("the first event wasn't Ready")};
+            supervisor.start(config);
+            let mut seen = vec![];
+            while let Some(event) = events.next().await {
+                let finished = matches!(event, SupervisorEvent::StateChanged(ServerState::Stopped | ServerState::Crashed(_)));
+                if stop_timeout.is_some() && matches!(event, SupervisorEvent::StateChanged(ServerState::Running)) {
+                    supervisor.stop();
+                }
+                seen.push(event);
+                if finished {break}
+            }
+            seen
+        })
+    }
+
+    fn states(events: &[SupervisorEvent]) -> Vec<ServerState> {
+        events.iter().filter_map(|e| match e {
+            SupervisorEvent::StateChanged(s) => Some(s.clone()),
+            _ => None,
+        }).collect()
+    }
+
+    fn lines(events: &[SupervisorEvent]) -> Vec<&str> {
+        events.iter().filter_map(|e| match e {
+            SupervisorEvent::Output(l) => Some(l.as_str()),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn done_line_means_running_and_a_clean_exit_means_stopped() {
+        let events = run_script("echo 'Done (1.5s)! For help, type \"help\"'; echo bye; exit 0", None);
+        assert_eq!(states(&events), vec![ServerState::Starting, ServerState::Running, ServerState::Stopped]);
+        assert_eq!(lines(&events), vec!["Done (1.5s)! For help, type \"help\"", "bye"]);
+    }
+
+    #[test]
+    fn output_before_a_crash_comes_before_the_exit() {
+        let events = run_script("echo 'Done (1s)!'; echo 'it broke' >&2; exit 3", None);
+        let states = states(&events);
+        assert_eq!(states[..2], [ServerState::Starting, ServerState::Running]);
+        assert!(matches!(&states[2], ServerState::Crashed(reason) if reason.contains('3')), "{states:?}");
+        // stdout and stderr are read separately, so only the exit is ordered against them
+        assert_eq!(lines(&events).len(), 2);
+        assert!(lines(&events).contains(&"it broke"));
+    }
+
+    #[test]
+    fn a_server_that_ignores_stop_gets_killed() {
+        let started = std::time::Instant::now();
+        let events = run_script("echo 'Done (1s)!'; while read line; do echo \"ignoring $line\"; done", Some(Duration::from_millis(500)));
+        assert_eq!(states(&events), vec![ServerState::Starting, ServerState::Running, ServerState::Stopping, ServerState::Stopped]);
+        assert!(lines(&events).contains(&"ignoring stop"));
+        assert!(started.elapsed() >= Duration::from_millis(500));
+    }
+
+    #[test]
+    fn killing_a_launch_script_also_kills_what_it_started() {
+        // like `run.sh`, the outer shell doesn't exec the inner one, which holds on to the pipes and ignores stop
+        let script = "sh -c 'echo \"Done (1s)!\"; while read line; do echo \"ignoring $line\"; done'; echo unreachable";
+        let (tx, rx) = std::sync::mpsc::channel();
+        thread::spawn(move || {let _ = tx.send(run_script(script, Some(Duration::from_millis(500))));});
+        let events = rx.recv_timeout(Duration::from_secs(10)).expect("the server never got reported as exited");
+        assert_eq!(states(&events), vec![ServerState::Starting, ServerState::Running, ServerState::Stopping, ServerState::Stopped]);
+        assert!(!lines(&events).contains(&"unreachable"));
+    }
+}
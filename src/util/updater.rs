@@ -0,0 +1,133 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use iced::futures::{SinkExt, Stream, StreamExt, channel::mpsc::Sender};
+
+use crate::ModLoader;
+use crate::states::mod_downloader::provider::Version;
+use crate::util::{mod_backups, modpack, reqwests::{self, DownloadEvent}};
+
+#[derive(Debug, Clone)]
+pub enum UpdateEvent {
+    Progressed { downloaded: u64, total: Option<u64> },
+    /// the new jar is in place. holds why the old one is still in the mods folder too, if it couldn't be removed
+    Done(Option<String>),
+    Failed(String),
+}
+
+/// swaps the installed `old_file` for `new`. the new jar is downloaded and checked next to the old one as a `.part` first,
+/// so a failed update leaves things as they were and only a finished one backs the old jar up
+pub fn update_jar(root: PathBuf, loader: ModLoader, old_file: String, new: Version) -> impl Stream<Item = UpdateEvent> {
+    iced::stream::channel(16, async move |mut output| {
+        let event = match _update(&root, loader, &old_file, &new, &mut output).await {
+            Ok(left_over) => UpdateEvent::Done(left_over),
+            Err(err) => UpdateEvent::Failed(err),
+        };
+        let _ = output.send(event).await;
+    })
+}
+
+async fn _update(root: &Path, loader: ModLoader, old_file: &str, new: &Version, output: &mut Sender<UpdateEvent>) -> Result<Option<String>, String> {
+    let file = new.files.iter().find(|f| f.primary).or(new.files.first()).ok_or_else(|| format!("{} has no files", new.name))?;
+    if let Some(page) = &file.manual_url {
+        return Err(format!("{} can't be downloaded here, its author only allows it from {page}", file.filename));
+    }
+    let mods_dir = root.join(loader.mods_folder());
+    let old_path = mods_dir.join(old_file);
+    let new_path = modpack::join_file_name(&mods_dir, &file.filename)?;
+    // loaders only pick up `.jar`, so the download can wait in the mods folder and be renamed into place from there
+    let part = mods_dir.join(format!("{}.part", file.filename));
+
+    let mut events = Box::pin(reqwests::download_file(file.url.clone(), part.clone(), file.hashes.clone(), root.join(".mcservermodgui").join("quarantine")));
+    let mut finished = None;
+    while let Some(event) = events.next().await {
+        match event {
+            DownloadEvent::Progressed { downloaded, total } => {let _ = output.send(UpdateEvent::Progressed { downloaded, total }).await;}
+            DownloadEvent::Finished(result) => finished = Some(result),
+        }
+    }
+    if let Err(err) = finished.unwrap_or_else(|| Err(format!("The download of {} stopped without finishing", file.filename))) {
+        let _ = fs::remove_file(&part);
+        return Err(err);
+    }
+
+    if old_path == new_path {
+        // renaming over the old jar swaps it in one go, so it only needs copying out first
+        mod_backups::back_up(root, &old_path).inspect_err(|_| {let _ = fs::remove_file(&part);})?;
+        fs::rename(&part, &new_path).map_err(|e| format!("Couldn't move {} into place: {e}", file.filename))?;
+        return Ok(None);
+    }
+    fs::rename(&part, &new_path).map_err(|e| format!("Couldn't move {} into place: {e}", file.filename))?;
+    match mod_backups::move_to_backups(root, &old_path) {
+        Ok(_) => Ok(None),
+        Err(err) => Ok(Some(format!("Couldn't remove {old_file}: {err}"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::states::mod_downloader::provider::File;
+    use crate::util::{hashes::sha1_hex, mock_http};
+
+    fn version(base: &str, file_name: &str, sha1: String) -> Version {
+        Version {
+            id: "v2".to_string(),
+            project_id: "p".to_string(),
+            name: "Foo 2.0".to_string(),
+            loaders: vec![ModLoader::Fabric],
+            dependencies: vec![],
+            files: vec![File {
+                url: format!("{base}/{file_name}"),
+                filename: file_name.to_string(),
+                primary: true,
+                size: 3,
+                hashes: HashMap::from([("sha1".to_string(), sha1)]),
+                manual_url: None,
+            }],
+        }
+    }
+
+    fn run(root: &Path, old_file: &str, new: Version) -> Vec<UpdateEvent> {
+        tokio::runtime::Runtime::new().unwrap().block_on(update_jar(root.to_path_buf(), ModLoader::Fabric, old_file.to_string(), new).collect())
+    }
+
+    #[test]
+    fn the_old_jar_is_backed_up_and_replaced() {
+        let root = std::env::temp_dir().join(format!("mcservermodgui-updater-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("mods")).unwrap();
+        fs::write(root.join("mods/foo-1.0.jar"), "old").unwrap();
+        let (base, _) = mock_http::serve(|r| match r.path.as_str() {
+            "/foo-2.0.jar" => Some(b"new".to_vec()),
+            "/same/foo-2.0.jar" => Some(b"newer".to_vec()),
+            _ => None,
+        });
+
+        // a bad download leaves the old jar alone
+        let events = run(&root, "foo-1.0.jar", version(&base, "foo-2.0.jar", sha1_hex(b"something else")));
+        assert!(matches!(events.last(), Some(UpdateEvent::Failed(_))), "{events:?}");
+        assert_eq!(fs::read_to_string(root.join("mods/foo-1.0.jar")).unwrap(), "old");
+        assert!(!root.join("mods/foo-2.0.jar").exists());
+        assert!(!root.join("mods/foo-2.0.jar.part").exists());
+        assert!(!mod_backups::backups_dir(&root).exists());
+
+        let events = run(&root, "foo-1.0.jar", version(&base, "foo-2.0.jar", sha1_hex(b"new")));
+        assert!(matches!(events.last(), Some(UpdateEvent::Done(None))), "{events:?}");
+        assert!(!root.join("mods/foo-1.0.jar").exists());
+        assert_eq!(fs::read_to_string(root.join("mods/foo-2.0.jar")).unwrap(), "new");
+        let backups = fs::read_dir(mod_backups::backups_dir(&root)).unwrap()
+            .map(|e| fs::read_to_string(e.unwrap().path()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(backups, vec!["old"]);
+
+        // a jar that keeps its name is replaced in place
+        let events = run(&root, "foo-2.0.jar", version(&format!("{base}/same"), "foo-2.0.jar", sha1_hex(b"newer")));
+        assert!(matches!(events.last(), Some(UpdateEvent::Done(None))), "{events:?}");
+        assert_eq!(fs::read_to_string(root.join("mods/foo-2.0.jar")).unwrap(), "newer");
+        assert_eq!(fs::read_dir(root.join("mods")).unwrap().count(), 1);
+        assert_eq!(fs::read_dir(mod_backups::backups_dir(&root)).unwrap().count(), 2);
+        let _ = fs::remove_dir_all(&root);
+    }
+}